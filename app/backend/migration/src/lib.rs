@@ -2,6 +2,18 @@ pub use sea_orm_migration::prelude::*;
 mod m20250511_132813_create_tables;
 mod m20250628_090238_notification_table;
 mod m20250708_162547_add_coloms_for_notification; 
+mod m20261017_090000_create_reward_table;
+mod m20261017_091500_create_reward_epoch_tables;
+mod m20261017_093000_create_program_event_tables;
+mod m20261017_094500_add_payout_columns_to_reward;
+mod m20261017_100000_create_share_custody_tables;
+mod m20261017_101500_add_commitments_to_custody_keys;
+mod m20261017_103000_create_custody_epochs;
+mod m20261017_104500_add_scheme_to_custody_keys;
+mod m20261017_110000_add_reward_contribution_index;
+mod m20261017_111500_create_reward_summary_view;
+mod m20261017_113000_create_scheduled_checks;
+mod m20261017_114500_store_reward_amounts_in_lamports;
 
 pub struct Migrator;
 
@@ -15,6 +27,30 @@ impl MigratorTrait for Migrator {
             Box::new(m20250628_090238_notification_table::Migration),
             // Third migration: adds website_url and website_id to Notifications table
             Box::new(m20250708_162547_add_coloms_for_notification::Migration), // ✅ Add here
+            // Fourth migration: creates the Reward table backing entities::reward
+            Box::new(m20261017_090000_create_reward_table::Migration),
+            // Fifth migration: adds RewardEpochs and RewardEpochLeaves for merkle reward distribution
+            Box::new(m20261017_091500_create_reward_epoch_tables::Migration),
+            // Sixth migration: adds ProgramEvents and IndexerCursors for the on-chain event indexer
+            Box::new(m20261017_093000_create_program_event_tables::Migration),
+            // Seventh migration: adds payout attempt tracking to the Reward table
+            Box::new(m20261017_094500_add_payout_columns_to_reward::Migration),
+            // Eighth migration: adds ShareHolders, CustodyKeys and KeyShares for encrypted share custody
+            Box::new(m20261017_100000_create_share_custody_tables::Migration),
+            // Ninth migration: stores the Feldman commitments published when a custody key is split
+            Box::new(m20261017_101500_add_commitments_to_custody_keys::Migration),
+            // Tenth migration: adds CustodyEpochs, the audit trail of custody key refreshes and reshares
+            Box::new(m20261017_103000_create_custody_epochs::Migration),
+            // Eleventh migration: marks custody keys that sign by FROST instead of being reconstructed
+            Box::new(m20261017_104500_add_scheme_to_custody_keys::Migration),
+            // Twelfth migration: makes rewards unique per contribution and reward type
+            Box::new(m20261017_110000_add_reward_contribution_index::Migration),
+            // Thirteenth migration: adds the RewardSummary view behind the validator earnings API
+            Box::new(m20261017_111500_create_reward_summary_view::Migration),
+            // Fourteenth migration: adds per website check intervals and the ScheduledChecks table
+            Box::new(m20261017_113000_create_scheduled_checks::Migration),
+            // Fifteenth migration: stores Reward.amount as integer lamports instead of SOL
+            Box::new(m20261017_114500_store_reward_amounts_in_lamports::Migration),
         ]
     }
 }
\ No newline at end of file
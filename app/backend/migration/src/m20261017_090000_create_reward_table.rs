@@ -0,0 +1,119 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Creating Reward table...");
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Reward::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Reward::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(Expr::cust("gen_random_uuid()")),
+                    )
+                    .col(
+                        ColumnDef::new(Reward::UserId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Reward::Amount)
+                            .double()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Reward::RewardType)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Reward::ContributionId).uuid())
+                    .col(ColumnDef::new(Reward::TransactionHash).string())
+                    .col(
+                        ColumnDef::new(Reward::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(Reward::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(Reward::ProcessedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(Reward::EpochId).big_integer())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_reward_user_id")
+                            .from(Reward::Table, Reward::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_reward_user_id_status")
+                    .table(Reward::Table)
+                    .col(Reward::UserId)
+                    .col(Reward::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ Reward table created");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_reward_user_id_status")
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Reward::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+// variants name the table's columns, RewardType is the reward_type column
+#[allow(clippy::enum_variant_names)]
+#[derive(DeriveIden)]
+enum Reward {
+    Table,
+    Id,
+    UserId,
+    Amount,
+    RewardType,
+    ContributionId,
+    TransactionHash,
+    Status,
+    CreatedAt,
+    ProcessedAt,
+    EpochId,
+}
+
+// Reference existing tables without redefining them
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,164 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Creating RewardEpochs and RewardEpochLeaves tables...");
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(RewardEpochs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RewardEpochs::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(Expr::cust("gen_random_uuid()")),
+                    )
+                    .col(
+                        ColumnDef::new(RewardEpochs::EpochId)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(RewardEpochs::MerkleRoot)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RewardEpochs::TotalAmount)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RewardEpochs::LeafCount)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RewardEpochs::ExpirySlot)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RewardEpochs::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(RewardEpochLeaves::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RewardEpochLeaves::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(Expr::cust("gen_random_uuid()")),
+                    )
+                    .col(
+                        ColumnDef::new(RewardEpochLeaves::EpochId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RewardEpochLeaves::LeafIndex)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RewardEpochLeaves::WalletAddress)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RewardEpochLeaves::Amount)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_reward_epoch_leaves_epoch_id")
+                            .from(RewardEpochLeaves::Table, RewardEpochLeaves::EpochId)
+                            .to(RewardEpochs::Table, RewardEpochs::EpochId)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One leaf per wallet per epoch, and leaf indexes are unique within an epoch
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_reward_epoch_leaves_epoch_wallet")
+                    .table(RewardEpochLeaves::Table)
+                    .col(RewardEpochLeaves::EpochId)
+                    .col(RewardEpochLeaves::WalletAddress)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_reward_epoch_leaves_epoch_index")
+                    .table(RewardEpochLeaves::Table)
+                    .col(RewardEpochLeaves::EpochId)
+                    .col(RewardEpochLeaves::LeafIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ RewardEpochs and RewardEpochLeaves tables created");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RewardEpochLeaves::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(RewardEpochs::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum RewardEpochs {
+    Table,
+    Id,
+    EpochId,
+    MerkleRoot,
+    TotalAmount,
+    LeafCount,
+    ExpirySlot,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RewardEpochLeaves {
+    Table,
+    Id,
+    EpochId,
+    LeafIndex,
+    WalletAddress,
+    Amount,
+}
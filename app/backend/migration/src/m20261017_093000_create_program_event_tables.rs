@@ -0,0 +1,154 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Creating ProgramEvents and IndexerCursors tables...");
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProgramEvents::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ProgramEvents::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(Expr::cust("gen_random_uuid()")),
+                    )
+                    .col(
+                        ColumnDef::new(ProgramEvents::Signature)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProgramEvents::Slot)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProgramEvents::EventIndex)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProgramEvents::EventName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProgramEvents::Payload)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProgramEvents::BlockTime)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProgramEvents::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Re-indexing the same transaction must not duplicate its events
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_program_events_signature_index")
+                    .table(ProgramEvents::Table)
+                    .col(ProgramEvents::Signature)
+                    .col(ProgramEvents::EventIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_program_events_event_name")
+                    .table(ProgramEvents::Table)
+                    .col(ProgramEvents::EventName)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(IndexerCursors::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(IndexerCursors::ProgramId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(IndexerCursors::LastSignature)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IndexerCursors::LastSlot)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IndexerCursors::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ ProgramEvents and IndexerCursors tables created");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(IndexerCursors::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ProgramEvents::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProgramEvents {
+    Table,
+    Id,
+    Signature,
+    Slot,
+    EventIndex,
+    EventName,
+    Payload,
+    BlockTime,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum IndexerCursors {
+    Table,
+    ProgramId,
+    LastSignature,
+    LastSlot,
+    UpdatedAt,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Adding payout tracking columns to Reward table...");
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("Reward"))
+                    .add_column(
+                        ColumnDef::new(Alias::new("payout_attempts"))
+                            .integer()
+                            .not_null()
+                            .default(0)
+                    )
+                    .add_column(
+                        ColumnDef::new(Alias::new("payout_expiry_slot"))
+                            .big_integer()
+                            .null()
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The payout service looks rewards up by the signature it submitted
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_reward_transaction_hash")
+                    .table(Alias::new("Reward"))
+                    .col(Alias::new("transaction_hash"))
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ Payout columns added to Reward table");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Removing payout columns from Reward table...");
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_reward_transaction_hash")
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("Reward"))
+                    .drop_column(Alias::new("payout_attempts"))
+                    .drop_column(Alias::new("payout_expiry_slot"))
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ Payout columns removed from Reward table");
+        Ok(())
+    }
+}
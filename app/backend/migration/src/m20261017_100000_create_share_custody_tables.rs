@@ -0,0 +1,225 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Creating ShareHolders, CustodyKeys and KeyShares tables...");
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ShareHolders::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ShareHolders::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(Expr::cust("gen_random_uuid()")),
+                    )
+                    .col(
+                        ColumnDef::new(ShareHolders::UserId)
+                            .uuid()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ShareHolders::EncryptionPublicKey)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ShareHolders::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_share_holders_user_id")
+                            .from(ShareHolders::Table, ShareHolders::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(CustodyKeys::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CustodyKeys::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(Expr::cust("gen_random_uuid()")),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyKeys::PublicKey)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyKeys::Threshold)
+                            .small_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyKeys::TotalShares)
+                            .small_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyKeys::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(KeyShares::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(KeyShares::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(Expr::cust("gen_random_uuid()")),
+                    )
+                    .col(
+                        ColumnDef::new(KeyShares::KeyId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(KeyShares::HolderId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(KeyShares::ShareIndex)
+                            .small_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(KeyShares::Ciphertext)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(KeyShares::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_key_shares_key_id")
+                            .from(KeyShares::Table, KeyShares::KeyId)
+                            .to(CustodyKeys::Table, CustodyKeys::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_key_shares_holder_id")
+                            .from(KeyShares::Table, KeyShares::HolderId)
+                            .to(ShareHolders::Table, ShareHolders::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A holder keeps at most one share of a key, and every share x-coordinate appears once
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_key_shares_key_holder")
+                    .table(KeyShares::Table)
+                    .col(KeyShares::KeyId)
+                    .col(KeyShares::HolderId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_key_shares_key_index")
+                    .table(KeyShares::Table)
+                    .col(KeyShares::KeyId)
+                    .col(KeyShares::ShareIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ ShareHolders, CustodyKeys and KeyShares tables created");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(KeyShares::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(CustodyKeys::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ShareHolders::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ShareHolders {
+    Table,
+    Id,
+    UserId,
+    EncryptionPublicKey,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum CustodyKeys {
+    Table,
+    Id,
+    PublicKey,
+    Threshold,
+    TotalShares,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum KeyShares {
+    Table,
+    Id,
+    KeyId,
+    HolderId,
+    ShareIndex,
+    Ciphertext,
+    CreatedAt,
+}
@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Adding share commitments to CustodyKeys table...");
+
+        // Keys split before verifiable sharing keep an empty list and can't be recovered by the
+        // ceremony, they have to be split again
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("CustodyKeys"))
+                    .add_column(
+                        ColumnDef::new(Alias::new("commitments"))
+                            .json_binary()
+                            .not_null()
+                            .default(Expr::cust("'[]'::jsonb"))
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ Commitments column added to CustodyKeys table");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Removing commitments column from CustodyKeys table...");
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("CustodyKeys"))
+                    .drop_column(Alias::new("commitments"))
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ Commitments column removed from CustodyKeys table");
+        Ok(())
+    }
+}
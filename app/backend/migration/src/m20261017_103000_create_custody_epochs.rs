@@ -0,0 +1,265 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Creating CustodyEpochs table and epoch columns...");
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(CustodyEpochs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CustodyEpochs::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(Expr::cust("gen_random_uuid()")),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyEpochs::KeyId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyEpochs::Epoch)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyEpochs::Kind)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyEpochs::Threshold)
+                            .small_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyEpochs::TotalShares)
+                            .small_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyEpochs::HolderIds)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyEpochs::Commitments)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyEpochs::InitiatedBy)
+                            .uuid()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(CustodyEpochs::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_custody_epochs_key_id")
+                            .from(CustodyEpochs::Table, CustodyEpochs::KeyId)
+                            .to(CustodyKeys::Table, CustodyKeys::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_custody_epochs_key_epoch")
+                    .table(CustodyEpochs::Table)
+                    .col(CustodyEpochs::KeyId)
+                    .col(CustodyEpochs::Epoch)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CustodyKeys::Table)
+                    .add_column(
+                        ColumnDef::new(CustodyKeys::CurrentEpoch)
+                            .integer()
+                            .not_null()
+                            .default(0)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(KeyShares::Table)
+                    .add_column(
+                        ColumnDef::new(KeyShares::Epoch)
+                            .integer()
+                            .not_null()
+                            .default(0)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every epoch issues a new row per holder, so uniqueness now holds within an epoch
+        manager
+            .drop_index(Index::drop().name("idx_key_shares_key_holder").to_owned())
+            .await?;
+
+        manager
+            .drop_index(Index::drop().name("idx_key_shares_key_index").to_owned())
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_key_shares_key_epoch_holder")
+                    .table(KeyShares::Table)
+                    .col(KeyShares::KeyId)
+                    .col(KeyShares::Epoch)
+                    .col(KeyShares::HolderId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_key_shares_key_epoch_index")
+                    .table(KeyShares::Table)
+                    .col(KeyShares::KeyId)
+                    .col(KeyShares::Epoch)
+                    .col(KeyShares::ShareIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Keys split before epochs existed get their split recorded as epoch 0
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"INSERT INTO "CustodyEpochs" (key_id, epoch, kind, threshold, total_shares, holder_ids, commitments)
+                SELECT k.id, 0, 'split', k.threshold, k.total_shares,
+                    COALESCE((SELECT jsonb_agg(s.holder_id) FROM "KeyShares" s WHERE s.key_id = k.id), '[]'::jsonb),
+                    k.commitments
+                FROM "CustodyKeys" k"#,
+            )
+            .await?;
+
+        println!("✅ CustodyEpochs table and epoch columns created");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(Index::drop().name("idx_key_shares_key_epoch_index").to_owned())
+            .await?;
+
+        manager
+            .drop_index(Index::drop().name("idx_key_shares_key_epoch_holder").to_owned())
+            .await?;
+
+        // only the epoch 0 shares fit the old per key uniqueness
+        manager
+            .get_connection()
+            .execute_unprepared(r#"DELETE FROM "KeyShares" WHERE epoch <> 0"#)
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_key_shares_key_holder")
+                    .table(KeyShares::Table)
+                    .col(KeyShares::KeyId)
+                    .col(KeyShares::HolderId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_key_shares_key_index")
+                    .table(KeyShares::Table)
+                    .col(KeyShares::KeyId)
+                    .col(KeyShares::ShareIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(KeyShares::Table)
+                    .drop_column(KeyShares::Epoch)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CustodyKeys::Table)
+                    .drop_column(CustodyKeys::CurrentEpoch)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(CustodyEpochs::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum CustodyKeys {
+    Table,
+    Id,
+    CurrentEpoch,
+}
+
+#[derive(DeriveIden)]
+enum KeyShares {
+    Table,
+    Epoch,
+    KeyId,
+    HolderId,
+    ShareIndex,
+}
+
+#[derive(DeriveIden)]
+enum CustodyEpochs {
+    Table,
+    Id,
+    KeyId,
+    Epoch,
+    Kind,
+    Threshold,
+    TotalShares,
+    HolderIds,
+    Commitments,
+    InitiatedBy,
+    CreatedAt,
+}
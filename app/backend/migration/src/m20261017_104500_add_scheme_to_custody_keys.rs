@@ -0,0 +1,45 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Adding signing scheme to CustodyKeys table...");
+
+        // 'seed' keys can be rebuilt by a recovery ceremony, 'frost' keys only ever sign by threshold
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("CustodyKeys"))
+                    .add_column(
+                        ColumnDef::new(Alias::new("scheme"))
+                            .string()
+                            .not_null()
+                            .default("seed")
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ Scheme column added to CustodyKeys table");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Removing scheme column from CustodyKeys table...");
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("CustodyKeys"))
+                    .drop_column(Alias::new("scheme"))
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ Scheme column removed from CustodyKeys table");
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Adding contribution index to Reward table...");
+
+        // One reward per contribution and reward type, so the reward engine can run over the same
+        // contributions again without crediting them twice
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_reward_contribution_type")
+                    .table(Alias::new("Reward"))
+                    .col(Alias::new("contribution_id"))
+                    .col(Alias::new("reward_type"))
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ Contribution index added to Reward table");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Removing contribution index from Reward table...");
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_reward_contribution_type")
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ Contribution index removed from Reward table");
+        Ok(())
+    }
+}
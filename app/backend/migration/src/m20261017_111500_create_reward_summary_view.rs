@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Creating RewardSummary view...");
+
+        // Daily rollup of rewards per user, type and status, backing entities::reward_summary.
+        // Totals by status, counts by type and per day earnings are all sums over its rows.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"CREATE OR REPLACE VIEW "RewardSummary" AS
+                SELECT user_id,
+                    (created_at AT TIME ZONE 'UTC')::date AS day,
+                    reward_type,
+                    status,
+                    COUNT(*)::bigint AS reward_count,
+                    SUM(amount)::double precision AS total_amount
+                FROM "Reward"
+                GROUP BY user_id, day, reward_type, status"#,
+            )
+            .await?;
+
+        // The reward history is paged newest first per user
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_reward_user_id_created_at")
+                    .table(Alias::new("Reward"))
+                    .col(Alias::new("user_id"))
+                    .col(Alias::new("created_at"))
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ RewardSummary view created");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Dropping RewardSummary view...");
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_reward_user_id_created_at")
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(r#"DROP VIEW IF EXISTS "RewardSummary""#)
+            .await?;
+
+        println!("✅ RewardSummary view dropped");
+        Ok(())
+    }
+}
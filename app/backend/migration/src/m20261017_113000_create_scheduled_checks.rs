@@ -0,0 +1,173 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Adding check schedule to WebsiteRegister and creating ScheduledChecks table...");
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("WebsiteRegister"))
+                    .add_column(
+                        ColumnDef::new(Alias::new("check_interval_secs"))
+                            .integer()
+                            .not_null()
+                            .default(300),
+                    )
+                    .add_column(
+                        ColumnDef::new(Alias::new("next_check_at"))
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScheduledChecks::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ScheduledChecks::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(Expr::cust("gen_random_uuid()")),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledChecks::RunId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledChecks::WebsiteId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledChecks::ValidatorId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledChecks::ScheduledFor)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledChecks::DueBy)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledChecks::Status)
+                            .string()
+                            .not_null()
+                            .default("scheduled"),
+                    )
+                    .col(ColumnDef::new(ScheduledChecks::PerformanceId).uuid())
+                    .col(
+                        ColumnDef::new(ScheduledChecks::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_scheduled_checks_website_id")
+                            .from(ScheduledChecks::Table, ScheduledChecks::WebsiteId)
+                            .to(WebsiteRegister::Table, WebsiteRegister::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_scheduled_checks_validator_id")
+                            .from(ScheduledChecks::Table, ScheduledChecks::ValidatorId)
+                            .to(Validators::Table, Validators::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The scheduler settles runs whose deadline has passed
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_scheduled_checks_status_due_by")
+                    .table(ScheduledChecks::Table)
+                    .col(ScheduledChecks::Status)
+                    .col(ScheduledChecks::DueBy)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_scheduled_checks_run_validator")
+                    .table(ScheduledChecks::Table)
+                    .col(ScheduledChecks::RunId)
+                    .col(ScheduledChecks::ValidatorId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ Check schedule columns and ScheduledChecks table created");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Dropping ScheduledChecks table and check schedule columns...");
+
+        manager
+            .drop_table(Table::drop().table(ScheduledChecks::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("WebsiteRegister"))
+                    .drop_column(Alias::new("check_interval_secs"))
+                    .drop_column(Alias::new("next_check_at"))
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("✅ ScheduledChecks table and check schedule columns dropped");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ScheduledChecks {
+    Table,
+    Id,
+    RunId,
+    WebsiteId,
+    ValidatorId,
+    ScheduledFor,
+    DueBy,
+    Status,
+    PerformanceId,
+    CreatedAt,
+}
+
+// Reference existing tables without redefining them
+#[derive(DeriveIden)]
+enum WebsiteRegister {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Validators {
+    Table,
+    Id,
+}
@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// RewardSummary sums Reward.amount, so it is dropped while the column changes type and recreated
+// with integer totals.
+const SUMMARY_VIEW: &str = r#"CREATE OR REPLACE VIEW "RewardSummary" AS
+    SELECT user_id,
+        (created_at AT TIME ZONE 'UTC')::date AS day,
+        reward_type,
+        status,
+        COUNT(*)::bigint AS reward_count,
+        SUM(amount)::{total_type} AS total_amount
+    FROM "Reward"
+    GROUP BY user_id, day, reward_type, status"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Storing Reward amounts as integer lamports...");
+
+        let db = manager.get_connection();
+        db.execute_unprepared(r#"DROP VIEW IF EXISTS "RewardSummary""#).await?;
+        db.execute_unprepared(
+            r#"ALTER TABLE "Reward" ALTER COLUMN amount TYPE BIGINT USING ROUND(amount * 1000000000)::bigint"#,
+        )
+        .await?;
+        db.execute_unprepared(&SUMMARY_VIEW.replace("{total_type}", "bigint")).await?;
+
+        println!("✅ Reward amounts are lamports");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        println!("🔄 Storing Reward amounts as SOL again...");
+
+        let db = manager.get_connection();
+        db.execute_unprepared(r#"DROP VIEW IF EXISTS "RewardSummary""#).await?;
+        db.execute_unprepared(
+            r#"ALTER TABLE "Reward" ALTER COLUMN amount TYPE DOUBLE PRECISION USING amount / 1000000000.0"#,
+        )
+        .await?;
+        db.execute_unprepared(&SUMMARY_VIEW.replace("{total_type}", "double precision")).await?;
+
+        println!("✅ Reward amounts are SOL");
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use sea_orm::entity::prelude::*;
+
+pub const KIND_SPLIT: &str = "split";
+pub const KIND_DKG: &str = "dkg"; // generated by the holders themselves, no dealer ever held the key
+pub const KIND_REFRESH: &str = "refresh"; // shares rerandomized, holders add the epoch's delta to their share
+pub const KIND_RESHARE: &str = "reshare"; // new threshold or holder set, holders receive whole new shares
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "CustodyEpochs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    pub key_id: Uuid,
+    pub epoch: i32,
+    pub kind: String,
+    pub threshold: i16,
+    pub total_shares: i16,
+    pub holder_ids: Json,
+    pub commitments: Json, // commitments valid from this epoch on
+    pub initiated_by: Option<Uuid>,
+    #[sea_orm(default_value = "CURRENT_TIMESTAMP")]
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(belongs_to = "super::custody_key::Entity", from = "Column::KeyId", to = "super::custody_key::Column::Id")]
+    CustodyKeys,
+}
+
+impl Related<super::custody_key::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CustodyKeys.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
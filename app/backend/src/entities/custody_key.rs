@@ -0,0 +1,42 @@
+use sea_orm::entity::prelude::*;
+
+pub const SCHEME_SEED: &str = "seed"; // shares of an ed25519 seed, a recovery ceremony can rebuild the keypair
+pub const SCHEME_FROST: &str = "frost"; // shares of a signing scalar, only ever used through threshold signing
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "CustodyKeys")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    #[sea_orm(unique)]
+    pub public_key: String, // base58 solana pubkey the shares reconstruct to
+    pub threshold: i16,
+    pub total_shares: i16,
+    pub commitments: Json, // base58 Feldman commitments, one per coefficient, first one commits to the seed
+    pub current_epoch: i32,
+    pub scheme: String,
+    #[sea_orm(default_value = "CURRENT_TIMESTAMP")]
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::key_share::Entity")]
+    KeyShares,
+    #[sea_orm(has_many = "super::custody_epoch::Entity")]
+    Epochs,
+}
+
+impl Related<super::key_share::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::KeyShares.def()
+    }
+}
+
+impl Related<super::custody_epoch::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Epochs.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
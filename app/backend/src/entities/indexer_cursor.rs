@@ -0,0 +1,16 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "IndexerCursors")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub program_id: String,
+    pub last_signature: String, // newest transaction already indexed
+    pub last_slot: i64,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
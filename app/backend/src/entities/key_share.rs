@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "KeyShares")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    pub key_id: Uuid,
+    pub holder_id: Uuid,
+    pub share_index: i16, // x-coordinate of the share, lets recovery check a share came from its holder
+    #[sea_orm(column_type = "Text")]
+    pub ciphertext: String, // base64 share sealed to the holder's encryption key, a delta in refresh epochs
+    pub epoch: i32,
+    #[sea_orm(default_value = "CURRENT_TIMESTAMP")]
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(belongs_to = "super::custody_key::Entity", from = "Column::KeyId", to = "super::custody_key::Column::Id")]
+    CustodyKeys,
+    #[sea_orm(belongs_to = "super::share_holder::Entity", from = "Column::HolderId", to = "super::share_holder::Column::Id")]
+    ShareHolders,
+}
+
+impl Related<super::custody_key::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CustodyKeys.def()
+    }
+}
+
+impl Related<super::share_holder::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ShareHolders.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -6,6 +6,15 @@ pub mod computing_contribution;
 pub mod usage_metric;
 pub mod reward;
 pub mod reward_summary;
+pub mod reward_epoch;
+pub mod reward_epoch_leaf;
 pub mod website_register;
 pub mod website_performance;
-pub mod notification;
\ No newline at end of file
+pub mod notification;
+pub mod program_event;
+pub mod indexer_cursor;
+pub mod share_holder;
+pub mod custody_key;
+pub mod key_share;
+pub mod custody_epoch;
+pub mod scheduled_check;
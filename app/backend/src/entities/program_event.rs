@@ -0,0 +1,21 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "ProgramEvents")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    pub signature: String,
+    pub slot: i64,
+    pub event_index: i32, // position of the event within the transaction logs
+    pub event_name: String,
+    pub payload: Json,
+    pub block_time: Option<DateTimeWithTimeZone>,
+    #[sea_orm(default_value = "CURRENT_TIMESTAMP")]
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
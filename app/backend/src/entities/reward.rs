@@ -1,18 +1,30 @@
 use sea_orm::entity::prelude::*;
 
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_IN_EPOCH: &str = "in_epoch";
+pub const STATUS_SUBMITTED: &str = "submitted";
+pub const STATUS_PAID: &str = "paid";
+pub const STATUS_FAILED: &str = "failed";
+
+// What a reward was earned for, contribution_id points at the row of that contribution
+pub const REWARD_TYPE_WEBSITE_CHECK: &str = "website_check";
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)] 
 #[sea_orm(table_name = "Reward")]
 pub struct Model{
     #[sea_orm(primary_key)]
     pub id: Uuid,
     pub user_id: Uuid,
-    pub amount: f64,
+    pub amount: i64, // lamports
     pub reward_type: String,
     pub contribution_id: Option<Uuid>,
     pub transaction_hash: Option<String>,
     pub status: String,
     pub created_at: DateTimeWithTimeZone,
     pub processed_at: Option<DateTimeWithTimeZone>,
+    pub epoch_id: Option<i64>,
+    pub payout_attempts: i32,
+    pub payout_expiry_slot: Option<i64>, // last slot the submitted withdrawal can land in
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "RewardEpochs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    #[sea_orm(unique)]
+    pub epoch_id: i64,
+    pub merkle_root: String, // base58, same encoding as solana Hash
+    pub total_amount: i64,   // lamports
+    pub leaf_count: i32,
+    pub expiry_slot: i64,
+    #[sea_orm(default_value = "CURRENT_TIMESTAMP")]
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::reward_epoch_leaf::Entity")]
+    Leaves,
+}
+
+impl Related<super::reward_epoch_leaf::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Leaves.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
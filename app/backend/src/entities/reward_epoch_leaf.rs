@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "RewardEpochLeaves")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    pub epoch_id: i64,
+    pub leaf_index: i32,
+    pub wallet_address: String,
+    pub amount: i64, // lamports
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::reward_epoch::Entity",
+        from = "Column::EpochId",
+        to = "super::reward_epoch::Column::EpochId"
+    )]
+    Epoch,
+}
+
+impl Related<super::reward_epoch::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Epoch.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
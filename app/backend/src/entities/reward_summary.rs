@@ -1,10 +1,22 @@
-// use sea_orm::entity::prelude::*;
-// use::std::collections::HashMap;
+use sea_orm::entity::prelude::*;
 
-// #[derive(Clone, Debug, PartialEq, DeriveEntityModel)] 
-// #[sea_orm(table_name = "RewardSummary")]
-// pub struct Model{
-//     pub total_amount: f64,
-//     pub reward_counts: HashMap<String, i32>,
-//     pub rewards: Vec<Reward>,
-// }
+// Read only, backed by the "RewardSummary" view: one row per user, day, reward type and status
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "RewardSummary")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user_id: Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub day: Date,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub reward_type: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub status: String,
+    pub reward_count: i64,
+    pub total_amount: i64, // lamports
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
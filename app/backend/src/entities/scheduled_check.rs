@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+
+pub const STATUS_SCHEDULED: &str = "scheduled";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_MISSED: &str = "missed";
+
+// One validator's assignment in a scheduled run of a website check. Every validator assigned to
+// the same run shares its run_id.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ScheduledChecks")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    pub run_id: Uuid,
+    pub website_id: Uuid,
+    pub validator_id: Uuid,
+    pub scheduled_for: DateTimeWithTimeZone,
+    pub due_by: DateTimeWithTimeZone, // a result after this counts as missed
+    pub status: String,
+    pub performance_id: Option<Uuid>, // the WebsitePerformance row that completed it
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(belongs_to = "super::website_register::Entity", from = "Column::WebsiteId", to = "super::website_register::Column::Id")]
+    WebsiteRegister,
+    #[sea_orm(belongs_to = "super::validator::Entity", from = "Column::ValidatorId", to = "super::validator::Column::Id")]
+    Validators,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -10,6 +10,8 @@ pub struct Model{
     pub user_id : Uuid,
     #[sea_orm(default_value = "CURRENT_TIMESTAMP")]
     pub timestamp: DateTimeWithTimeZone,
+    pub check_interval_secs: i32,
+    pub next_check_at: Option<DateTimeWithTimeZone>, // None until the scheduler first picks it up
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,216 @@
+use borsh::BorshDeserialize;
+use serde::{Serialize, Serializer};
+use solana_sdk::{hash::hashv, pubkey::Pubkey};
+
+// Event structs mirror the #[event] definitions of the d-uptime program field for field, the
+// borsh layout has to match exactly for decoding to work.
+
+fn as_base58<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&pubkey.to_string())
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct VaultInitialized {
+    #[serde(serialize_with = "as_base58")]
+    pub admin: Pubkey,
+    #[serde(serialize_with = "as_base58")]
+    pub operator: Pubkey,
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct WithdrawalSucces {
+    #[serde(serialize_with = "as_base58")]
+    pub user: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub timestamp: i64,
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct TokenWithdrawalSucces {
+    #[serde(serialize_with = "as_base58")]
+    pub user: Pubkey,
+    #[serde(serialize_with = "as_base58")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub timestamp: i64,
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct CircuitBreakerTriggered {
+    #[serde(serialize_with = "as_base58")]
+    pub vault: Pubkey,
+    pub attempted: u64,
+    pub epoch_outflow: u64,
+    pub max_outflow_per_epoch: u64,
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct TokenCircuitBreakerTriggered {
+    #[serde(serialize_with = "as_base58")]
+    pub vault: Pubkey,
+    #[serde(serialize_with = "as_base58")]
+    pub mint: Pubkey,
+    pub attempted: u64,
+    pub epoch_outflow: u64,
+    pub max_outflow_per_epoch: u64,
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct PauseStatus {
+    #[serde(serialize_with = "as_base58")]
+    pub vault: Pubkey,
+    pub flags: u8,
+    pub paused: bool,
+    pub pause_flags: u8,
+    #[serde(serialize_with = "as_base58")]
+    pub changed_by: Pubkey,
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct RewardClaimed {
+    #[serde(serialize_with = "as_base58")]
+    pub validator: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub remaining: u64,
+    pub timestamp: i64,
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct EpochPosted {
+    #[serde(serialize_with = "as_base58")]
+    pub vault: Pubkey,
+    pub epoch_id: u64,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+    pub leaf_count: u32,
+    pub expiry_slot: u64,
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct EpochClaimed {
+    #[serde(serialize_with = "as_base58")]
+    pub vault: Pubkey,
+    pub epoch_id: u64,
+    #[serde(serialize_with = "as_base58")]
+    pub validator: Pubkey,
+    pub index: u32,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum ValidatorStatus {
+    Pending,
+    Active,
+    Inactive,
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ValidatorRegistered {
+    #[serde(serialize_with = "as_base58")]
+    pub vault: Pubkey,
+    #[serde(serialize_with = "as_base58")]
+    pub wallet: Pubkey,
+    pub device_hash: [u8; 32],
+    pub region_code: u16,
+    pub registration_slot: u64,
+}
+
+#[derive(BorshDeserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ValidatorStatusChanged {
+    #[serde(serialize_with = "as_base58")]
+    pub vault: Pubkey,
+    #[serde(serialize_with = "as_base58")]
+    pub wallet: Pubkey,
+    pub previous_status: ValidatorStatus,
+    pub status: ValidatorStatus,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProgramEvent {
+    VaultInitialized(VaultInitialized),
+    WithdrawalSucces(WithdrawalSucces),
+    TokenWithdrawalSucces(TokenWithdrawalSucces),
+    CircuitBreakerTriggered(CircuitBreakerTriggered),
+    TokenCircuitBreakerTriggered(TokenCircuitBreakerTriggered),
+    PauseStatus(PauseStatus),
+    RewardClaimed(RewardClaimed),
+    EpochPosted(EpochPosted),
+    EpochClaimed(EpochClaimed),
+    ValidatorRegistered(ValidatorRegistered),
+    ValidatorStatusChanged(ValidatorStatusChanged),
+}
+
+// Anchor prefixes every event with the first 8 bytes of sha256("event:<StructName>")
+pub fn event_discriminator(name: &str) -> [u8; 8] {
+    let hash = hashv(&[b"event:", name.as_bytes()]).to_bytes();
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+fn decode_as<T: BorshDeserialize>(data: &[u8]) -> Option<T> {
+    T::try_from_slice(data).ok()
+}
+
+impl ProgramEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProgramEvent::VaultInitialized(_) => "VaultInitialized",
+            ProgramEvent::WithdrawalSucces(_) => "WithdrawalSucces",
+            ProgramEvent::TokenWithdrawalSucces(_) => "TokenWithdrawalSucces",
+            ProgramEvent::CircuitBreakerTriggered(_) => "CircuitBreakerTriggered",
+            ProgramEvent::TokenCircuitBreakerTriggered(_) => "TokenCircuitBreakerTriggered",
+            ProgramEvent::PauseStatus(_) => "PauseStatus",
+            ProgramEvent::RewardClaimed(_) => "RewardClaimed",
+            ProgramEvent::EpochPosted(_) => "EpochPosted",
+            ProgramEvent::EpochClaimed(_) => "EpochClaimed",
+            ProgramEvent::ValidatorRegistered(_) => "ValidatorRegistered",
+            ProgramEvent::ValidatorStatusChanged(_) => "ValidatorStatusChanged",
+        }
+    }
+
+    // Returns None for events we don't track (or a layout we can't read) so one unknown event
+    // doesn't stop the rest of the transaction from being indexed.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+        let (discriminator, payload) = data.split_at(8);
+        let event = match discriminator {
+            d if d == event_discriminator("VaultInitialized") => ProgramEvent::VaultInitialized(decode_as(payload)?),
+            d if d == event_discriminator("WithdrawalSucces") => ProgramEvent::WithdrawalSucces(decode_as(payload)?),
+            d if d == event_discriminator("TokenWithdrawalSucces") => ProgramEvent::TokenWithdrawalSucces(decode_as(payload)?),
+            d if d == event_discriminator("CircuitBreakerTriggered") => ProgramEvent::CircuitBreakerTriggered(decode_as(payload)?),
+            d if d == event_discriminator("TokenCircuitBreakerTriggered") => ProgramEvent::TokenCircuitBreakerTriggered(decode_as(payload)?),
+            d if d == event_discriminator("PauseStatus") => ProgramEvent::PauseStatus(decode_as(payload)?),
+            d if d == event_discriminator("RewardClaimed") => ProgramEvent::RewardClaimed(decode_as(payload)?),
+            d if d == event_discriminator("EpochPosted") => ProgramEvent::EpochPosted(decode_as(payload)?),
+            d if d == event_discriminator("EpochClaimed") => ProgramEvent::EpochClaimed(decode_as(payload)?),
+            d if d == event_discriminator("ValidatorRegistered") => ProgramEvent::ValidatorRegistered(decode_as(payload)?),
+            d if d == event_discriminator("ValidatorStatusChanged") => ProgramEvent::ValidatorStatusChanged(decode_as(payload)?),
+            _ => return None,
+        };
+        Some(event)
+    }
+
+    pub fn payload(&self) -> serde_json::Value {
+        let value = match self {
+            ProgramEvent::VaultInitialized(event) => serde_json::to_value(event),
+            ProgramEvent::WithdrawalSucces(event) => serde_json::to_value(event),
+            ProgramEvent::TokenWithdrawalSucces(event) => serde_json::to_value(event),
+            ProgramEvent::CircuitBreakerTriggered(event) => serde_json::to_value(event),
+            ProgramEvent::TokenCircuitBreakerTriggered(event) => serde_json::to_value(event),
+            ProgramEvent::PauseStatus(event) => serde_json::to_value(event),
+            ProgramEvent::RewardClaimed(event) => serde_json::to_value(event),
+            ProgramEvent::EpochPosted(event) => serde_json::to_value(event),
+            ProgramEvent::EpochClaimed(event) => serde_json::to_value(event),
+            ProgramEvent::ValidatorRegistered(event) => serde_json::to_value(event),
+            ProgramEvent::ValidatorStatusChanged(event) => serde_json::to_value(event),
+        };
+        value.unwrap_or(serde_json::Value::Null)
+    }
+}
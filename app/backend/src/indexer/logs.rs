@@ -0,0 +1,131 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::indexer::events::ProgramEvent;
+
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+// Returns the raw `Program data:` payloads emitted by `program_id` itself. The runtime logs
+// "Program <id> invoke [n]" / "Program <id> success|failed" around every invocation, so we keep a
+// stack and ignore data logged while another program (a CPI target) is on top of it.
+pub fn program_data(logs: &[String], program_id: &Pubkey) -> Vec<Vec<u8>> {
+    let program_id = program_id.to_string();
+    let mut invocations: Vec<&str> = Vec::new();
+    let mut payloads = Vec::new();
+
+    for line in logs {
+        if let Some(encoded) = line.strip_prefix(PROGRAM_DATA_PREFIX) {
+            if invocations.last() == Some(&program_id.as_str()) {
+                match STANDARD.decode(encoded.trim()) {
+                    Ok(data) => payloads.push(data),
+                    Err(e) => println!("Skipping undecodable program data : {}", e),
+                }
+            }
+            continue;
+        }
+
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        let mut parts = rest.split_whitespace();
+        let (Some(id), Some(action)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        if action == "invoke" {
+            invocations.push(id);
+        } else if action == "success" || action.starts_with("failed") {
+            invocations.pop();
+        }
+    }
+    payloads
+}
+
+pub fn parse_events(logs: &[String], program_id: &Pubkey) -> Vec<ProgramEvent> {
+    program_data(logs, program_id)
+        .iter()
+        .filter_map(|data| ProgramEvent::decode(data))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::events::event_discriminator;
+    use crate::indexer::source::{FixtureLogSource, LogSource, TransactionLogs};
+
+    fn data_line(name: &str, payload: Vec<u8>) -> String {
+        let mut data = event_discriminator(name).to_vec();
+        data.extend(payload);
+        format!("{}{}", PROGRAM_DATA_PREFIX, STANDARD.encode(data))
+    }
+
+    fn transaction(signature: &str, logs: Vec<String>) -> TransactionLogs {
+        TransactionLogs {
+            signature: signature.to_string(),
+            slot: 42,
+            block_time: Some(1_700_000_000),
+            failed: false,
+            logs,
+        }
+    }
+
+    #[tokio::test]
+    async fn fixture_source_resumes_after_cursor() {
+        let transactions = vec![transaction("a", vec![]), transaction("b", vec![]), transaction("c", vec![])];
+        let path = std::env::temp_dir().join(format!("indexer-fixture-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_string(&transactions).unwrap()).unwrap();
+        let source = FixtureLogSource::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let signatures = |transactions: Vec<TransactionLogs>| -> Vec<String> {
+            transactions.into_iter().map(|transaction| transaction.signature).collect()
+        };
+        assert_eq!(signatures(source.fetch_after(None).await.unwrap()), ["a", "b", "c"]);
+        assert_eq!(signatures(source.fetch_after(Some("b")).await.unwrap()), ["c"]);
+        assert!(source.fetch_after(Some("c")).await.unwrap().is_empty());
+        // a cursor the source never saw replays everything rather than skipping it
+        assert_eq!(signatures(source.fetch_after(Some("z")).await.unwrap()).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn parses_fixture_events_and_skips_cpi_data() {
+        let program_id = Pubkey::new_unique();
+        let (vault, user, mint, token_program) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            data_line("WithdrawalSucces", borsh::to_vec(&(user.to_bytes(), 900u64, 100u64, 1_700_000_000i64)).unwrap()),
+            format!("Program {} invoke [2]", token_program),
+            data_line("WithdrawalSucces", borsh::to_vec(&(user.to_bytes(), 1u64, 1u64, 1i64)).unwrap()),
+            format!("Program {} success", token_program),
+            data_line("TokenWithdrawalSucces", borsh::to_vec(&(user.to_bytes(), mint.to_bytes(), 50u64, 5u64, 1_700_000_000i64)).unwrap()),
+            data_line("CircuitBreakerTriggered", borsh::to_vec(&(vault.to_bytes(), 10u64, 95u64, 100u64)).unwrap()),
+            data_line("TokenCircuitBreakerTriggered", borsh::to_vec(&(vault.to_bytes(), mint.to_bytes(), 10u64, 95u64, 100u64)).unwrap()),
+            data_line("PauseStatus", borsh::to_vec(&(vault.to_bytes(), 3u8, true, 3u8, vault.to_bytes())).unwrap()),
+            data_line("Unknown", vec![0; 16]),
+            format!("Program {} success", program_id),
+        ];
+        let source = FixtureLogSource::new(vec![transaction("sig", logs)]);
+        let transactions = source.fetch_after(None).await.unwrap();
+        let events = parse_events(&transactions[0].logs, &program_id);
+
+        let names: Vec<&str> = events.iter().map(ProgramEvent::name).collect();
+        assert_eq!(
+            names,
+            ["WithdrawalSucces", "TokenWithdrawalSucces", "CircuitBreakerTriggered", "TokenCircuitBreakerTriggered", "PauseStatus"]
+        );
+        let ProgramEvent::WithdrawalSucces(withdrawal) = &events[0] else { unreachable!() };
+        assert_eq!((withdrawal.user, withdrawal.amount, withdrawal.fee), (user, 900, 100));
+        let ProgramEvent::TokenCircuitBreakerTriggered(triggered) = &events[3] else { unreachable!() };
+        assert_eq!((triggered.mint, triggered.epoch_outflow), (mint, 95));
+        let ProgramEvent::PauseStatus(pause) = &events[4] else { unreachable!() };
+        assert!(pause.paused);
+        assert_eq!(events[1].payload()["mint"], mint.to_string());
+    }
+
+    #[test]
+    fn truncated_event_is_skipped() {
+        let mut data = event_discriminator("CircuitBreakerTriggered").to_vec();
+        data.extend([0u8; 12]);
+        assert_eq!(ProgramEvent::decode(&data), None);
+    }
+}
@@ -0,0 +1,182 @@
+pub mod events;
+pub mod logs;
+pub mod source;
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::OnConflict, ActiveValue::NotSet, ColumnTrait, DatabaseConnection,
+    DatabaseTransaction, EntityTrait, QueryFilter, Set, TransactionTrait,
+};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::entities::{indexer_cursor, program_event, reward, validator};
+use crate::indexer::events::ProgramEvent;
+use crate::indexer::logs::parse_events;
+use crate::indexer::source::{LogSource, TransactionLogs};
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// declare_id! of programs/d-uptime, overridable with D_UPTIME_PROGRAM_ID
+pub const D_UPTIME_PROGRAM_ID: &str = "UMUmkqXqujVtpUrSKsYb9QcVmJprPPNsGePF89HtH9i";
+
+pub struct Indexer<S: LogSource> {
+    db: DatabaseConnection,
+    source: S,
+    program_id: Pubkey,
+}
+
+impl<S: LogSource + 'static> Indexer<S> {
+    pub fn new(db: DatabaseConnection, source: S, program_id: Pubkey) -> Self {
+        Self { db, source, program_id }
+    }
+
+    // Indexes every transaction since the stored cursor and returns how many events were stored.
+    // Each transaction commits together with the cursor, so a crash never skips or half-applies one.
+    pub async fn run_once(&self) -> Result<usize, BoxError> {
+        let cursor = indexer_cursor::Entity::find_by_id(self.program_id.to_string())
+            .one(&self.db)
+            .await?;
+        let transactions = self
+            .source
+            .fetch_after(cursor.as_ref().map(|cursor| cursor.last_signature.as_str()))
+            .await?;
+
+        let mut stored = 0;
+        for transaction in transactions {
+            let txn = self.db.begin().await?;
+            stored += self.index_transaction(&txn, &transaction).await?;
+            self.save_cursor(&txn, &transaction).await?;
+            txn.commit().await?;
+        }
+        Ok(stored)
+    }
+
+    pub fn spawn(self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                match self.run_once().await {
+                    Ok(0) => {}
+                    Ok(stored) => println!("Indexed {} program events", stored),
+                    Err(e) => eprintln!("Indexer run failed : {}", e),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    async fn index_transaction(&self, txn: &DatabaseTransaction, transaction: &TransactionLogs) -> Result<usize, BoxError> {
+        // a failed transaction emitted nothing that stuck. The payout service owns retrying or failing
+        // the rewards it was paying, marking them here would race its retry_or_fail
+        if transaction.failed {
+            return Ok(0);
+        }
+
+        let block_time = transaction
+            .block_time
+            .and_then(|timestamp| DateTime::<Utc>::from_timestamp(timestamp, 0))
+            .map(|time| time.fixed_offset());
+        let events = parse_events(&transaction.logs, &self.program_id);
+
+        for (index, event) in events.iter().enumerate() {
+            let model = program_event::ActiveModel {
+                id: NotSet,
+                signature: Set(transaction.signature.clone()),
+                slot: Set(transaction.slot as i64),
+                event_index: Set(index as i32),
+                event_name: Set(event.name().to_string()),
+                payload: Set(event.payload()),
+                block_time: Set(block_time),
+                created_at: NotSet,
+            };
+            program_event::Entity::insert(model)
+                .on_conflict(
+                    OnConflict::columns([program_event::Column::Signature, program_event::Column::EventIndex])
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .exec_without_returning(txn)
+                .await?;
+
+            self.apply_event(txn, transaction, event).await?;
+        }
+        Ok(events.len())
+    }
+
+    async fn apply_event(&self, txn: &DatabaseTransaction, transaction: &TransactionLogs, event: &ProgramEvent) -> Result<(), BoxError> {
+        let processed_at = transaction
+            .block_time
+            .and_then(|timestamp| DateTime::<Utc>::from_timestamp(timestamp, 0))
+            .unwrap_or_else(Utc::now)
+            .fixed_offset();
+
+        match event {
+            // payouts record their signature on the reward before sending, the event confirms it
+            ProgramEvent::WithdrawalSucces(_) | ProgramEvent::RewardClaimed(_) => {
+                reward::Entity::update_many()
+                    .col_expr(reward::Column::Status, reward::STATUS_PAID.into())
+                    .col_expr(reward::Column::ProcessedAt, processed_at.into())
+                    .filter(reward::Column::TransactionHash.eq(transaction.signature.clone()))
+                    .exec(txn)
+                    .await?;
+            }
+            // merkle claims are sent by the validator, so match the rewards by epoch and wallet
+            ProgramEvent::EpochClaimed(claimed) => {
+                let Some(claimant) = validator::Entity::find()
+                    .filter(validator::Column::WalletAddress.eq(claimed.validator.to_string()))
+                    .one(txn)
+                    .await?
+                else {
+                    println!("EpochClaimed for unknown wallet {}", claimed.validator);
+                    return Ok(());
+                };
+                reward::Entity::update_many()
+                    .col_expr(reward::Column::Status, reward::STATUS_PAID.into())
+                    .col_expr(reward::Column::TransactionHash, transaction.signature.clone().into())
+                    .col_expr(reward::Column::ProcessedAt, processed_at.into())
+                    .filter(reward::Column::UserId.eq(claimant.user_id))
+                    .filter(reward::Column::EpochId.eq(claimed.epoch_id as i64))
+                    .filter(reward::Column::Status.eq(reward::STATUS_IN_EPOCH))
+                    .exec(txn)
+                    .await?;
+            }
+            ProgramEvent::CircuitBreakerTriggered(triggered) => {
+                println!(
+                    "Circuit breaker paused vault {} : {} lamports attempted, {} of {} already out this epoch",
+                    triggered.vault, triggered.attempted, triggered.epoch_outflow, triggered.max_outflow_per_epoch
+                );
+            }
+            ProgramEvent::TokenCircuitBreakerTriggered(triggered) => {
+                println!(
+                    "Circuit breaker paused vault {} on mint {} : {} attempted, {} of {} already out this epoch",
+                    triggered.vault, triggered.mint, triggered.attempted, triggered.epoch_outflow, triggered.max_outflow_per_epoch
+                );
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn save_cursor(&self, txn: &DatabaseTransaction, transaction: &TransactionLogs) -> Result<(), BoxError> {
+        let cursor = indexer_cursor::ActiveModel {
+            program_id: Set(self.program_id.to_string()),
+            last_signature: Set(transaction.signature.clone()),
+            last_slot: Set(transaction.slot as i64),
+            updated_at: Set(Utc::now().fixed_offset()),
+        };
+        indexer_cursor::Entity::insert(cursor)
+            .on_conflict(
+                OnConflict::column(indexer_cursor::Column::ProgramId)
+                    .update_columns([
+                        indexer_cursor::Column::LastSignature,
+                        indexer_cursor::Column::LastSlot,
+                        indexer_cursor::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(txn)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::indexer::BoxError;
+use crate::utils::solana_rpc::JsonRpcClient;
+
+// Everything the indexer needs from one transaction.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TransactionLogs {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub failed: bool,
+    pub logs: Vec<String>,
+}
+
+#[async_trait]
+pub trait LogSource: Send + Sync {
+    // Transactions that touched the program after the `after` signature (everything when None),
+    // oldest first. Failed transactions are included so the cursor can move past them.
+    async fn fetch_after(&self, after: Option<&str>) -> Result<Vec<TransactionLogs>, BoxError>;
+}
+
+// Reads logs over JSON-RPC, works the same against a local test validator and a cluster.
+pub struct RpcLogSource {
+    rpc: JsonRpcClient,
+    program_id: Pubkey,
+    page_size: usize,
+}
+
+impl RpcLogSource {
+    pub fn new(rpc_url: String, program_id: Pubkey) -> Self {
+        Self {
+            rpc: JsonRpcClient::new(rpc_url),
+            program_id,
+            page_size: 100,
+        }
+    }
+
+    async fn transaction_logs(&self, signature: &str) -> Result<Vec<String>, BoxError> {
+        let result = self
+            .rpc
+            .call(
+                "getTransaction",
+                json!([signature, { "encoding": "json", "commitment": "confirmed", "maxSupportedTransactionVersion": 0 }]),
+            )
+            .await?;
+        Ok(serde_json::from_value(result["meta"]["logMessages"].clone()).unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl LogSource for RpcLogSource {
+    async fn fetch_after(&self, after: Option<&str>) -> Result<Vec<TransactionLogs>, BoxError> {
+        // getSignaturesForAddress pages newest first, so walk back with `before` until `until` is reached
+        let mut signatures: Vec<Value> = Vec::new();
+        let mut before: Option<String> = None;
+        loop {
+            let mut config = json!({ "limit": self.page_size, "commitment": "confirmed" });
+            if let Some(after) = after {
+                config["until"] = json!(after);
+            }
+            if let Some(before) = &before {
+                config["before"] = json!(before);
+            }
+            let page: Vec<Value> = serde_json::from_value(
+                self.rpc.call("getSignaturesForAddress", json!([self.program_id.to_string(), config])).await?,
+            )?;
+            let page_len = page.len();
+            before = page.last().and_then(|entry| entry["signature"].as_str()).map(str::to_string);
+            signatures.extend(page);
+            if page_len < self.page_size || before.is_none() {
+                break;
+            }
+        }
+
+        let mut transactions = Vec::with_capacity(signatures.len());
+        for entry in signatures.into_iter().rev() {
+            let Some(signature) = entry["signature"].as_str() else {
+                continue;
+            };
+            transactions.push(TransactionLogs {
+                signature: signature.to_string(),
+                slot: entry["slot"].as_u64().unwrap_or_default(),
+                block_time: entry["blockTime"].as_i64(),
+                failed: !entry["err"].is_null(),
+                logs: self.transaction_logs(signature).await?,
+            });
+        }
+        Ok(transactions)
+    }
+}
+
+// Serves recorded transactions from a JSON file (an array of TransactionLogs), for replaying
+// fixtures without a validator.
+pub struct FixtureLogSource {
+    transactions: Vec<TransactionLogs>,
+}
+
+impl FixtureLogSource {
+    pub fn new(transactions: Vec<TransactionLogs>) -> Self {
+        Self { transactions }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, BoxError> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Self::new(serde_json::from_str(&contents)?))
+    }
+}
+
+#[async_trait]
+impl LogSource for FixtureLogSource {
+    async fn fetch_after(&self, after: Option<&str>) -> Result<Vec<TransactionLogs>, BoxError> {
+        let start = match after {
+            Some(after) => self
+                .transactions
+                .iter()
+                .position(|transaction| transaction.signature == after)
+                .map(|position| position + 1)
+                .unwrap_or(0),
+            None => 0,
+        };
+        Ok(self.transactions[start..].to_vec())
+    }
+}
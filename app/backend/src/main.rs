@@ -4,16 +4,21 @@ use axum::{
 };
 use migration::{Migrator, MigratorTrait};
 use sea_orm::Database;
-use std::{env, sync::Arc};
+use std::{env, str::FromStr, sync::Arc, time::Duration};
 use tower_http::cors::CorsLayer;
 
 pub mod redis;
 pub mod entities;
+pub mod indexer;
 pub mod middleware;
+pub mod payout;
+pub mod rewards;
 pub mod routes;
+pub mod scheduler;
+pub mod shamir_secret;
 pub mod types;
 pub mod utils;
-use crate::{redis::{client::RedisClientManager, cookie_manager::SessionStore, pubsub_manager::RedisPubSub}, types::redis::AppState};
+use crate::{redis::{challenge_manager::ChallengeStore, client::RedisClientManager, cookie_manager::SessionStore, pubsub_manager::RedisPubSub, shares_manager::ShareStorage}, types::{custody::CustodyState, redis::AppState}};
 
 #[tokio::main]
 async fn main() -> Result<(), std::io::Error> {
@@ -38,19 +43,97 @@ async fn main() -> Result<(), std::io::Error> {
     }
     println!("Database connected successfully!");
 
+    let program_id = env::var("D_UPTIME_PROGRAM_ID").unwrap_or_else(|_| indexer::D_UPTIME_PROGRAM_ID.to_string());
+    let program_id = solana_sdk::pubkey::Pubkey::from_str(&program_id).map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("D_UPTIME_PROGRAM_ID must be a valid pubkey : {}", e))
+    })?;
+
+    // Index d-uptime program events when an RPC endpoint is configured (a local test validator in dev)
+    let rpc_url = env::var("SOLANA_RPC_URL").ok();
+    if let Some(rpc_url) = &rpc_url {
+        let source = indexer::source::RpcLogSource::new(rpc_url.clone(), program_id);
+        indexer::Indexer::new(db.clone(), source, program_id).spawn(Duration::from_secs(10));
+        println!("Program event indexer started for {}", program_id);
+    }
+
+    // Credit rewards for accepted website checks
+    rewards::engine::RewardEngine::new(db.clone(), rewards::engine::RewardConfig::from_env()).spawn(Duration::from_secs(300));
+    println!("Reward engine started");
+
     // Initialize shared application state
     let redis_client_manager = RedisClientManager::new().await.expect("failed to initialize redis client manager - ensure redis server is running.");
     let redis_client = redis_client_manager.get_client();
     let redis_pubsub_manager = Arc::new(RedisPubSub::new(redis_client.clone()));
     let redis_cookie_manager = Arc::new(SessionStore::new(redis_client.clone()));
 
+    // Announce due website checks to validators on each website's interval
+    scheduler::CheckScheduler::new(db.clone(), redis_pubsub_manager.clone(), scheduler::SchedulerConfig::default()).spawn(Duration::from_secs(15));
+    println!("Check scheduler started");
+
     // Create combined application state that includes database, websocket, and session management
     let app_state = AppState {
         db: db.clone(),
         session_store: redis_cookie_manager,
-        pubsub : redis_pubsub_manager
+        pubsub : redis_pubsub_manager,
+        challenge_store: Arc::new(ChallengeStore::new(redis_client.clone()))
+    };
+
+    // Share custody, CUSTODY_ADMIN_IDS lists the users allowed to split keys and start recoveries
+    let custody_admins = env::var("CUSTODY_ADMIN_IDS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|id| uuid::Uuid::parse_str(id.trim()).ok())
+        .collect();
+    // CUSTODY_SEAL_KEY (base64 X25519 secret) seals ceremony keys at rest, every instance needs the same
+    // one. Without it a random key is used and running ceremonies don't survive a restart.
+    let custody_seal_key = match env::var("CUSTODY_SEAL_KEY") {
+        Ok(encoded) => base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded.trim())
+            .ok()
+            .and_then(|bytes| crypto_box::SecretKey::from_slice(&bytes).ok())
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "CUSTODY_SEAL_KEY must be a base64 32 byte key"))?,
+        Err(_) => {
+            println!("CUSTODY_SEAL_KEY not set, using a per-process key : run a single instance, restarts drop running ceremonies");
+            crypto_box::SecretKey::generate(&mut crypto_box::aead::OsRng)
+        }
+    };
+    let custody_state = CustodyState {
+        db: db.clone(),
+        share_storage: Arc::new(ShareStorage::new(redis_client.clone())),
+        seal_key: Arc::new(custody_seal_key),
+        admins: Arc::new(custody_admins),
+        program_id,
     };
 
+    // Pay pending rewards out of the vault. Withdrawals are signed by the holders of the FROST custody
+    // key PAYOUT_CUSTODY_KEY, the vault's operator, this instance never holds an operator key.
+    // PAYOUT_FEE_PAYER_PATH is a keypair file that only pays the transaction fees.
+    if let (Some(rpc_url), Ok(vault), Ok(custody_key), Ok(fee_payer_path)) = (
+        rpc_url,
+        env::var("D_UPTIME_VAULT"),
+        env::var("PAYOUT_CUSTODY_KEY"),
+        env::var("PAYOUT_FEE_PAYER_PATH"),
+    ) {
+        let pubkey = |name: &str, value: &str| {
+            solana_sdk::pubkey::Pubkey::from_str(value).map_err(|e| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} must be a valid pubkey : {}", name, e))
+            })
+        };
+        let fee_payer = solana_sdk::signature::read_keypair_file(fee_payer_path.trim()).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("failed to read fee payer keypair {} : {}", fee_payer_path.trim(), e))
+        })?;
+        let config = payout::PayoutConfig {
+            program_id,
+            vault: pubkey("D_UPTIME_VAULT", &vault)?,
+            max_attempts: 5,
+            // holders sign on their own devices, give them as long as a signing session lasts
+            expiry_slots: 2_250,
+        };
+        let signer = payout::signer::ThresholdSigner::new(custody_state.clone(), pubkey("PAYOUT_CUSTODY_KEY", &custody_key)?);
+        let rpc = payout::rpc::HttpPayoutRpc::new(rpc_url);
+        payout::PayoutService::new(db.clone(), rpc, signer, fee_payer, config).spawn(Duration::from_secs(5));
+        println!("Payout service started for vault {}, signing with custody key {}", vault, custody_key);
+    }
+
     // Build the application router with all routes and middleware
     let app = Router::new()
         .route("/", get(sayhello))
@@ -74,6 +157,22 @@ async fn main() -> Result<(), std::io::Error> {
             "/notifications",
             routes::notification::notification_router().with_state(db.clone()),
         )
+        .nest(
+            "/epochs",
+            routes::epoch::epoch_router().with_state(db.clone()),
+        )
+        .nest(
+            "/rewards",
+            routes::rewards::rewards_router().with_state(db.clone()),
+        )
+        .nest(
+            "/custody",
+            routes::custody::custody_router().with_state(custody_state.clone()),
+        )
+        .nest(
+            "/threshold",
+            routes::threshold::threshold_router().with_state(custody_state),
+        )
         .nest("/sse", routes::sse::sse_router().with_state(app_state))
         .layer(
             CorsLayer::very_permissive()
@@ -14,6 +14,9 @@ pub async fn jwt_auth_middleware(
 ) -> Result<Response, StatusCode> {
     match extract_jwt_from_headers(&headers) {
         Ok(user_details) => {
+            // Both ids are Uuids, so as a bare Extension<Uuid> the validator_id replaces the
+            // user_id. Handlers that need either one specifically take Extension<AuthenticatedUser>.
+            request.extensions_mut().insert(user_details.clone());
             request.extensions_mut().insert(user_details.user_id);
 
             if let Some(validator_id) = user_details.validator_id {
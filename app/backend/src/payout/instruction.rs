@@ -0,0 +1,115 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::{
+    ed25519_instruction::new_ed25519_instruction_with_signature,
+    hash::hashv,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    sysvar,
+};
+use solana_system_interface::program as system_program;
+
+use crate::indexer::BoxError;
+
+// Vault layout version the header below is written for (VAULT_VERSION in the program).
+pub const VAULT_VERSION: u8 = 1;
+
+// PAUSE_WITHDRAWALS bit of Vault.pause_flags
+pub const PAUSE_WITHDRAWALS: u8 = 1 << 1;
+
+// Same borsh layout as WithdrawalMessage in the d-uptime program, 88 bytes.
+#[derive(BorshSerialize, Clone, Debug, PartialEq)]
+pub struct WithdrawalMessage {
+    pub vault: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub nonce: u64,
+    pub expiry_slot: u64,
+}
+
+// Leading fields of the on-chain Vault account, up to the withdrawal nonce. Field order has to
+// follow the program's Vault struct.
+#[derive(BorshDeserialize, Debug)]
+#[cfg_attr(test, derive(BorshSerialize))]
+pub struct VaultHeader {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub creator: Pubkey,
+    pub operator: Pubkey,
+    pub pending_admin: Option<Pubkey>,
+    pub pending_operator: Option<Pubkey>,
+    pub operator_activation_slot: u64,
+    pub operators: Vec<Pubkey>,
+    pub operator_threshold: u8,
+    pub pending_operators: Vec<Pubkey>,
+    pub pending_operator_threshold: u8,
+    pub operator_set_activation_slot: u64,
+    pub guardian: Option<Pubkey>,
+    pub fee_account: Pubkey,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub withdrawal_fee_bps: u16,
+    pub pending_withdrawal_fee_bps: Option<u16>,
+    pub fee_effective_slot: u64,
+    pub last_admin_withdrawal: Option<i64>,
+    pub pause_flags: u8,
+    pub withdrawal_counter: u64,
+}
+
+impl VaultHeader {
+    pub fn parse(data: &[u8]) -> Result<Self, BoxError> {
+        if data.len() <= 8 || data[8] != VAULT_VERSION {
+            return Err("vault account is not at the expected layout version".into());
+        }
+        // deserialize instead of try_from_slice, the account continues past the header
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+
+    // Operators and threshold the program checks withdrawal signatures against, same rule as
+    // Vault::withdrawal_authority: without a configured set the single operator signs alone.
+    pub fn withdrawal_authority(&self) -> (Vec<Pubkey>, u8) {
+        if self.operators.is_empty() {
+            (vec![self.operator], 1)
+        } else {
+            (self.operators.clone(), self.operator_threshold)
+        }
+    }
+}
+
+// Anchor instruction data starts with the first 8 bytes of sha256("global:<instruction name>")
+fn instruction_discriminator(name: &str) -> [u8; 8] {
+    let hash = hashv(&[b"global:", name.as_bytes()]).to_bytes();
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+// One ed25519 sigverify instruction per operator signature over the message, followed by the vault
+// `withdrawal` instruction that checks them through the instructions sysvar. The signatures are
+// made elsewhere, aggregated by FROST threshold signing under a custody key set as the operator.
+pub fn withdrawal_instructions_with_signatures(
+    program_id: &Pubkey,
+    signatures: &[(Pubkey, [u8; 64])],
+    fee_account: &Pubkey,
+    message: &WithdrawalMessage,
+) -> Result<Vec<Instruction>, BoxError> {
+    let message_bytes = borsh::to_vec(message)?;
+    let mut instructions: Vec<Instruction> = signatures
+        .iter()
+        .map(|(operator, signature)| new_ed25519_instruction_with_signature(&message_bytes, signature, &operator.to_bytes()))
+        .collect();
+
+    let mut data = instruction_discriminator("withdrawal").to_vec();
+    (message.amount, message.nonce, message.expiry_slot).serialize(&mut data)?;
+    instructions.push(Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(message.vault, false),
+            AccountMeta::new(message.recipient, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            AccountMeta::new(*fee_account, false),
+        ],
+        data,
+    });
+    Ok(instructions)
+}
@@ -0,0 +1,618 @@
+pub mod instruction;
+pub mod rpc;
+pub mod signer;
+
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::Utc;
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Value,
+};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use uuid::Uuid;
+
+use crate::entities::{reward, validator};
+use crate::indexer::events::ProgramEvent;
+use crate::indexer::logs::parse_events;
+use crate::indexer::BoxError;
+use crate::payout::instruction::{withdrawal_instructions_with_signatures, VaultHeader, WithdrawalMessage, PAUSE_WITHDRAWALS};
+use crate::payout::rpc::{PayoutRpc, SignatureState};
+use crate::payout::signer::WithdrawalSigner;
+
+pub struct PayoutConfig {
+    pub program_id: Pubkey,
+    pub vault: Pubkey,
+    pub max_attempts: i32,
+    pub expiry_slots: u64, // how long a signed withdrawal stays valid, roughly a blockhash lifetime
+}
+
+// Pays pending rewards out of the vault, one wallet per withdrawal. Only one withdrawal is in flight
+// at a time: its nonce is the vault's withdrawal counter, so a second one signed meanwhile could
+// only fail. A submission is retried once it has expired on-chain without landing, or has failed.
+// A landed one only counts as paid when it logged WithdrawalSucces, a withdrawal that trips the
+// circuit breaker still succeeds but pauses the vault instead of paying.
+// `signer` signs withdrawals as the vault operator, through FROST threshold signing so no operator
+// key is ever held here. `fee_payer` only pays transaction fees and has no say over the vault.
+pub struct PayoutService<R: PayoutRpc, S: WithdrawalSigner> {
+    db: DatabaseConnection,
+    rpc: R,
+    signer: S,
+    fee_payer: Keypair,
+    config: PayoutConfig,
+    pending: Mutex<Option<WithdrawalMessage>>, // the withdrawal the signer is working on
+}
+
+impl<R: PayoutRpc + 'static, S: WithdrawalSigner + 'static> PayoutService<R, S> {
+    pub fn new(db: DatabaseConnection, rpc: R, signer: S, fee_payer: Keypair, config: PayoutConfig) -> Self {
+        Self { db, rpc, signer, fee_payer, config, pending: Mutex::new(None) }
+    }
+
+    // The signer produces a single signature, so its key has to satisfy the vault on its own. A fee
+    // payer in the operator set would be an operator key sitting on this host, it is refused.
+    fn check_authority(&self, vault: &VaultHeader) -> Result<(), BoxError> {
+        let (operators, threshold) = vault.withdrawal_authority();
+        if operators.contains(&self.fee_payer.pubkey()) {
+            return Err("the fee payer is a vault operator, operators have to sign through threshold custody".into());
+        }
+        if !operators.contains(&self.signer.operator()) || threshold > 1 {
+            return Err(format!(
+                "vault needs {} operator signatures, {} can only provide its own",
+                threshold,
+                self.signer.operator()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    // The message for this withdrawal, the one already handed to the signer while it is still
+    // valid, so a signature in progress isn't thrown away
+    fn withdrawal_message(&self, recipient: Pubkey, amount: u64, nonce: u64, slot: u64) -> WithdrawalMessage {
+        let mut pending = self.pending.lock().unwrap();
+        match pending.as_ref() {
+            Some(message)
+                if message.vault == self.config.vault
+                    && message.recipient == recipient
+                    && message.amount == amount
+                    && message.nonce == nonce
+                    && message.expiry_slot > slot =>
+            {
+                message.clone()
+            }
+            _ => {
+                let message = WithdrawalMessage {
+                    vault: self.config.vault,
+                    recipient,
+                    amount,
+                    nonce,
+                    expiry_slot: slot + self.config.expiry_slots,
+                };
+                *pending = Some(message.clone());
+                message
+            }
+        }
+    }
+
+    pub async fn run_once(&self) -> Result<(), BoxError> {
+        if self.track_submitted().await? {
+            return Ok(());
+        }
+        self.submit_next().await
+    }
+
+    pub fn spawn(self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.run_once().await {
+                    eprintln!("Payout run failed : {}", e);
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    // Settles submitted payouts, returns true while one is still waiting to land.
+    async fn track_submitted(&self) -> Result<bool, BoxError> {
+        let submitted = reward::Entity::find()
+            .filter(reward::Column::Status.eq(reward::STATUS_SUBMITTED))
+            .all(&self.db)
+            .await?;
+
+        let mut per_signature: HashMap<String, Vec<reward::Model>> = HashMap::new();
+        for row in submitted {
+            let Some(signature) = row.transaction_hash.clone() else {
+                continue;
+            };
+            per_signature.entry(signature).or_default().push(row);
+        }
+
+        let mut in_flight = false;
+        for (signature, rows) in per_signature {
+            let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+            let parsed_signature = Signature::from_str(&signature)?;
+            match self.rpc.get_signature_state(&parsed_signature).await? {
+                Some(SignatureState::Confirmed) => {
+                    let Some(paid) = self.confirmed_paid(&parsed_signature).await? else {
+                        in_flight = true;
+                        continue;
+                    };
+                    if !paid {
+                        println!("Payout {} landed without paying, the vault tripped its circuit breaker", signature);
+                        self.retry_or_fail(&rows).await?;
+                        continue;
+                    }
+                    reward::Entity::update_many()
+                        .col_expr(reward::Column::Status, reward::STATUS_PAID.into())
+                        .col_expr(reward::Column::ProcessedAt, Utc::now().fixed_offset().into())
+                        .filter(reward::Column::Id.is_in(ids))
+                        .exec(&self.db)
+                        .await?;
+                    println!("Payout {} confirmed", signature);
+                }
+                Some(SignatureState::Failed) => {
+                    println!("Payout {} failed on-chain", signature);
+                    self.retry_or_fail(&rows).await?;
+                }
+                None => {
+                    // past the expiry slot the program rejects the message, so it can never land
+                    let expiry_slot = rows.iter().filter_map(|row| row.payout_expiry_slot).max().unwrap_or_default();
+                    if self.rpc.get_slot().await? > expiry_slot as u64 {
+                        println!("Payout {} expired without landing", signature);
+                        self.retry_or_fail(&rows).await?;
+                    } else {
+                        in_flight = true;
+                    }
+                }
+            }
+        }
+        Ok(in_flight)
+    }
+
+    // Whether a confirmed withdrawal logged WithdrawalSucces, None until the node serves its logs
+    async fn confirmed_paid(&self, signature: &Signature) -> Result<Option<bool>, BoxError> {
+        let Some(logs) = self.rpc.get_transaction_logs(signature).await? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            parse_events(&logs, &self.config.program_id)
+                .iter()
+                .any(|event| matches!(event, ProgramEvent::WithdrawalSucces(_))),
+        ))
+    }
+
+    // The vault to pay out of, None while its withdrawals are paused and the program would reject them
+    async fn payable_vault(&self) -> Result<Option<VaultHeader>, BoxError> {
+        let vault_data = self
+            .rpc
+            .get_account_data(&self.config.vault)
+            .await?
+            .ok_or("vault account not found")?;
+        let vault = VaultHeader::parse(&vault_data)?;
+        if vault.pause_flags & PAUSE_WITHDRAWALS != 0 {
+            return Ok(None);
+        }
+        Ok(Some(vault))
+    }
+
+    async fn retry_or_fail(&self, rows: &[reward::Model]) -> Result<(), BoxError> {
+        let (exhausted, retryable): (Vec<&reward::Model>, Vec<&reward::Model>) =
+            rows.iter().partition(|row| row.payout_attempts >= self.config.max_attempts);
+
+        if !exhausted.is_empty() {
+            reward::Entity::update_many()
+                .col_expr(reward::Column::Status, reward::STATUS_FAILED.into())
+                .filter(reward::Column::Id.is_in(exhausted.iter().map(|row| row.id)))
+                .exec(&self.db)
+                .await?;
+        }
+        if !retryable.is_empty() {
+            reward::Entity::update_many()
+                .col_expr(reward::Column::Status, reward::STATUS_PENDING.into())
+                .col_expr(reward::Column::TransactionHash, Expr::value(Value::String(None)))
+                .col_expr(reward::Column::PayoutExpirySlot, Expr::value(Value::BigInt(None)))
+                .filter(reward::Column::Id.is_in(retryable.iter().map(|row| row.id)))
+                .exec(&self.db)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // Builds, records and sends the withdrawal for the next wallet with pending rewards.
+    async fn submit_next(&self) -> Result<(), BoxError> {
+        let pending_rewards = reward::Entity::find()
+            .filter(reward::Column::Status.eq(reward::STATUS_PENDING))
+            .filter(reward::Column::EpochId.is_null())
+            .all(&self.db)
+            .await?;
+        if pending_rewards.is_empty() {
+            return Ok(());
+        }
+
+        let user_ids: Vec<Uuid> = pending_rewards.iter().map(|row| row.user_id).collect();
+        let wallets: HashMap<Uuid, String> = validator::Entity::find()
+            .filter(validator::Column::UserId.is_in(user_ids))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|validator| (validator.user_id, validator.wallet_address))
+            .collect();
+
+        let mut per_wallet: BTreeMap<String, (u64, Vec<Uuid>)> = BTreeMap::new();
+        for pending in pending_rewards {
+            let Some(wallet) = wallets.get(&pending.user_id) else {
+                continue;
+            };
+            let entry = per_wallet.entry(wallet.clone()).or_insert((0, Vec::new()));
+            entry.0 = entry.0.checked_add(u64::try_from(pending.amount)?).ok_or("pending rewards of a wallet overflow u64")?;
+            entry.1.push(pending.id);
+        }
+
+        let Some((wallet, (amount, reward_ids))) = per_wallet
+            .into_iter()
+            .find(|(wallet, (amount, _))| *amount > 0 && Pubkey::from_str(wallet).is_ok())
+        else {
+            return Ok(());
+        };
+        let recipient = Pubkey::from_str(&wallet)?;
+
+        let Some(vault) = self.payable_vault().await? else {
+            // keep the rewards pending until the vault is unpaused
+            return Ok(());
+        };
+        self.check_authority(&vault)?;
+        let message = self.withdrawal_message(recipient, amount, vault.withdrawal_counter, self.rpc.get_slot().await?);
+        let Some(operator_signature) = self.signer.sign(&message, &vault.fee_account).await? else {
+            // still being signed, asked for again on the next run
+            return Ok(());
+        };
+        let expiry_slot = message.expiry_slot;
+
+        let instructions = withdrawal_instructions_with_signatures(
+            &self.config.program_id,
+            &[(self.signer.operator(), operator_signature)],
+            &vault.fee_account,
+            &message,
+        )?;
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(&instructions, Some(&self.fee_payer.pubkey()), &[&self.fee_payer], blockhash);
+        let signature = transaction.signatures[0];
+        *self.pending.lock().unwrap() = None;
+
+        // recorded before sending, so a crash after the send still finds the payout instead of paying twice
+        let recorded = reward::Entity::update_many()
+            .col_expr(reward::Column::Status, reward::STATUS_SUBMITTED.into())
+            .col_expr(reward::Column::TransactionHash, signature.to_string().into())
+            .col_expr(reward::Column::PayoutExpirySlot, (expiry_slot as i64).into())
+            .col_expr(reward::Column::PayoutAttempts, Expr::col(reward::Column::PayoutAttempts).add(1))
+            .filter(reward::Column::Id.is_in(reward_ids.iter().copied()))
+            .filter(reward::Column::Status.eq(reward::STATUS_PENDING))
+            .exec(&self.db)
+            .await?;
+        if recorded.rows_affected != reward_ids.len() as u64 {
+            // some rewards went into an epoch meanwhile, the unsent payout expires and is requeued
+            return Err(format!("pending rewards of {} changed while building payout {}", wallet, signature).into());
+        }
+
+        match self.rpc.send_transaction(&transaction).await {
+            Ok(_) => println!("Submitted payout of {} lamports to {} : {}", amount, wallet, signature),
+            // the expiry check in track_submitted requeues it if it really never lands
+            Err(e) => eprintln!("Sending payout {} failed : {}", signature, e),
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use async_trait::async_trait;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use curve25519_dalek::scalar::Scalar;
+    use rand::rngs::OsRng;
+    use sea_orm::{ConnectionTrait, Database, PaginatorTrait, Schema, Set};
+    use solana_sdk::{hash::Hash, native_token::LAMPORTS_PER_SOL};
+
+    use super::*;
+    use crate::indexer::events::event_discriminator;
+    use crate::payout::instruction::VAULT_VERSION;
+    use crate::shamir_secret::frost;
+    use crate::shamir_secret::vss::{self, Commitments, VerifiableShare};
+
+    struct MockRpc {
+        vault: Vec<u8>,
+        logs: Option<Vec<String>>,
+        sent: Mutex<Vec<Transaction>>,
+    }
+
+    #[async_trait]
+    impl PayoutRpc for MockRpc {
+        async fn get_slot(&self) -> Result<u64, BoxError> {
+            Ok(100)
+        }
+
+        async fn get_latest_blockhash(&self) -> Result<Hash, BoxError> {
+            Ok(Hash::new_unique())
+        }
+
+        async fn get_account_data(&self, _pubkey: &Pubkey) -> Result<Option<Vec<u8>>, BoxError> {
+            Ok(Some(self.vault.clone()))
+        }
+
+        async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, BoxError> {
+            self.sent.lock().unwrap().push(transaction.clone());
+            Ok(transaction.signatures[0])
+        }
+
+        async fn get_signature_state(&self, _signature: &Signature) -> Result<Option<SignatureState>, BoxError> {
+            Ok(Some(SignatureState::Confirmed))
+        }
+
+        async fn get_transaction_logs(&self, _signature: &Signature) -> Result<Option<Vec<String>>, BoxError> {
+            Ok(self.logs.clone())
+        }
+    }
+
+    // The holders of a 2-of-3 FROST key, signing as soon as they are asked once `ready` is set
+    struct HolderSigner {
+        shares: Vec<VerifiableShare>,
+        commitments: Commitments,
+        ready: AtomicBool,
+        asked: Mutex<Vec<WithdrawalMessage>>,
+    }
+
+    impl HolderSigner {
+        fn new(ready: bool) -> Self {
+            let (shares, commitments) = vss::split(&Scalar::random(&mut OsRng), 2, 3);
+            Self { shares, commitments, ready: AtomicBool::new(ready), asked: Mutex::new(Vec::new()) }
+        }
+    }
+
+    #[async_trait]
+    impl WithdrawalSigner for HolderSigner {
+        fn operator(&self) -> Pubkey {
+            Pubkey::from(frost::group_key(&self.commitments).compress().to_bytes())
+        }
+
+        async fn sign(&self, message: &WithdrawalMessage, _fee_account: &Pubkey) -> Result<Option<[u8; 64]>, BoxError> {
+            self.asked.lock().unwrap().push(message.clone());
+            if !self.ready.load(Ordering::SeqCst) {
+                return Ok(None);
+            }
+            let message = borsh::to_vec(message)?;
+            let signers = &self.shares[1..];
+            let (nonces, commitments): (Vec<_>, Vec<_>) = signers
+                .iter()
+                .map(|share| {
+                    let (nonces, commitment) = frost::commit(share).unwrap();
+                    (nonces, (share.index, commitment))
+                })
+                .unzip();
+            let signature_shares: Vec<_> = signers
+                .iter()
+                .zip(&nonces)
+                .map(|(share, nonces)| {
+                    let key_package = frost::key_package(share, &self.commitments).unwrap();
+                    (share.index, frost::sign(&key_package, nonces, &commitments, &message).unwrap())
+                })
+                .collect();
+            let signature = frost::aggregate(&commitments, &signature_shares, &self.commitments, &message).map_err(|e| e.to_string())?;
+            Ok(Some(signature))
+        }
+    }
+
+    fn vault_data(operators: Vec<Pubkey>, operator_threshold: u8, pause_flags: u8) -> Vec<u8> {
+        let header = VaultHeader {
+            version: VAULT_VERSION,
+            admin: Pubkey::new_unique(),
+            creator: Pubkey::new_unique(),
+            operator: operators[0],
+            pending_admin: None,
+            pending_operator: None,
+            operator_activation_slot: 0,
+            operators,
+            operator_threshold,
+            pending_operators: Vec::new(),
+            pending_operator_threshold: 0,
+            operator_set_activation_slot: 0,
+            guardian: None,
+            fee_account: Pubkey::new_unique(),
+            total_deposited: 10 * LAMPORTS_PER_SOL,
+            total_withdrawn: 0,
+            withdrawal_fee_bps: 0,
+            pending_withdrawal_fee_bps: None,
+            fee_effective_slot: 0,
+            last_admin_withdrawal: None,
+            pause_flags,
+            withdrawal_counter: 7,
+        };
+        let mut data = vec![0u8; 8];
+        data.extend(borsh::to_vec(&header).unwrap());
+        data
+    }
+
+    fn program_logs(program_id: &Pubkey, events: &[&str]) -> Vec<String> {
+        let mut logs = vec![format!("Program {} invoke [1]", program_id)];
+        for name in events {
+            let mut data = event_discriminator(name).to_vec();
+            data.extend(borsh::to_vec(&(Pubkey::new_unique().to_bytes(), LAMPORTS_PER_SOL / 2, 0u64, 0i64)).unwrap());
+            logs.push(format!("Program data: {}", STANDARD.encode(data)));
+        }
+        logs.push(format!("Program {} success", program_id));
+        logs
+    }
+
+    fn service(vault: Vec<u8>, logs: Option<Vec<String>>, program_id: Pubkey) -> PayoutService<MockRpc, HolderSigner> {
+        service_with_db(DatabaseConnection::Disconnected, vault, logs, HolderSigner::new(true), program_id)
+    }
+
+    fn service_with_db(
+        db: DatabaseConnection,
+        vault: Vec<u8>,
+        logs: Option<Vec<String>>,
+        signer: HolderSigner,
+        program_id: Pubkey,
+    ) -> PayoutService<MockRpc, HolderSigner> {
+        let rpc = MockRpc { vault, logs, sent: Mutex::new(Vec::new()) };
+        let config = PayoutConfig {
+            program_id,
+            vault: Pubkey::new_unique(),
+            max_attempts: 3,
+            expiry_slots: 150,
+        };
+        PayoutService::new(db, rpc, signer, Keypair::new(), config)
+    }
+
+    // Reward and Validators tables in sqlite, rows are written without RETURNING since sea-orm
+    // can't read a uuid key back from it
+    async fn in_memory_db() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+        let backend = db.get_database_backend();
+        let schema = Schema::new(backend);
+        for table in [schema.create_table_from_entity(reward::Entity), schema.create_table_from_entity(validator::Entity)] {
+            let mut statement = backend.build(&table);
+            statement.sql = statement.sql.replace(" AUTOINCREMENT", "");
+            db.execute(statement).await.unwrap();
+        }
+        db
+    }
+
+    async fn insert_validator(db: &DatabaseConnection, user_id: Uuid, wallet: &Pubkey) {
+        let validator = validator::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            wallet_address: Set(wallet.to_string()),
+            latitude: Set(None),
+            longitude: Set(None),
+            device_id: Set(Uuid::new_v4().to_string()),
+            created_at: Set(None),
+        };
+        validator::Entity::insert(validator).exec_without_returning(db).await.unwrap();
+    }
+
+    async fn count_with_status(db: &DatabaseConnection, status: &str) -> u64 {
+        reward::Entity::find().filter(reward::Column::Status.eq(status)).count(db).await.unwrap()
+    }
+
+    async fn pending_reward(db: &DatabaseConnection, user_id: Uuid, amount: i64) {
+        let row = reward::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            amount: Set(amount),
+            reward_type: Set(reward::REWARD_TYPE_WEBSITE_CHECK.to_string()),
+            contribution_id: Set(Some(Uuid::new_v4())),
+            transaction_hash: Set(None),
+            status: Set(reward::STATUS_PENDING.to_string()),
+            created_at: Set(Utc::now().fixed_offset()),
+            processed_at: Set(None),
+            epoch_id: Set(None),
+            payout_attempts: Set(0),
+            payout_expiry_slot: Set(None),
+        };
+        reward::Entity::insert(row).exec_without_returning(db).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn confirmed_payout_counts_only_with_withdrawal_event() {
+        let program_id = Pubkey::new_unique();
+        let signature = Signature::new_unique();
+
+        let paid = service(Vec::new(), Some(program_logs(&program_id, &["WithdrawalSucces"])), program_id);
+        assert_eq!(paid.confirmed_paid(&signature).await.unwrap(), Some(true));
+
+        // a withdrawal that tripped the circuit breaker lands without paying
+        let tripped = service(Vec::new(), Some(program_logs(&program_id, &["CircuitBreakerTriggered"])), program_id);
+        assert_eq!(tripped.confirmed_paid(&signature).await.unwrap(), Some(false));
+
+        // the same event logged by another program doesn't count
+        let other = service(Vec::new(), Some(program_logs(&Pubkey::new_unique(), &["WithdrawalSucces"])), program_id);
+        assert_eq!(other.confirmed_paid(&signature).await.unwrap(), Some(false));
+
+        let unavailable = service(Vec::new(), None, program_id);
+        assert_eq!(unavailable.confirmed_paid(&signature).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn paused_vault_is_not_paid_out() {
+        let operator = Pubkey::new_unique();
+        let paused = service(vault_data(vec![operator], 1, PAUSE_WITHDRAWALS), None, Pubkey::new_unique());
+        assert!(paused.payable_vault().await.unwrap().is_none());
+
+        let active = service(vault_data(vec![operator], 1, 0), None, Pubkey::new_unique());
+        let vault = active.payable_vault().await.unwrap().unwrap();
+        assert_eq!(vault.withdrawal_counter, 7);
+    }
+
+    #[tokio::test]
+    async fn threshold_key_has_to_satisfy_the_vault_alone() {
+        let payout = service(Vec::new(), None, Pubkey::new_unique());
+        let (operator, other) = (payout.signer.operator(), Pubkey::new_unique());
+        let header = |operators: Vec<Pubkey>, threshold: u8| VaultHeader::parse(&vault_data(operators, threshold, 0)).unwrap();
+
+        assert!(payout.check_authority(&header(vec![other, operator], 1)).is_ok());
+        // the aggregate is a single signature, it can't meet a threshold of two
+        assert!(payout.check_authority(&header(vec![operator, other], 2)).is_err());
+        assert!(payout.check_authority(&header(vec![other], 1)).is_err());
+        // an operator key used to pay fees would be an operator key on this host
+        assert!(payout.check_authority(&header(vec![operator, payout.fee_payer.pubkey()], 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn pending_rewards_are_paid_as_exact_lamports() {
+        let db = in_memory_db().await;
+        let (user_id, wallet) = (Uuid::new_v4(), Pubkey::new_unique());
+        insert_validator(&db, user_id, &wallet).await;
+        // past 2^53 lamports an f64 can't tell these apart
+        for amount in [(1 << 53) + 1, 1, 1] {
+            pending_reward(&db, user_id, amount).await;
+        }
+
+        let signer = HolderSigner::new(true);
+        let operator = signer.operator();
+        let payout = service_with_db(db, vault_data(vec![operator], 1, 0), None, signer, Pubkey::new_unique());
+        payout.submit_next().await.unwrap();
+
+        let transaction = payout.rpc.sent.lock().unwrap()[0].clone();
+        assert_eq!(transaction.message.account_keys[0], payout.fee_payer.pubkey());
+        let withdrawal = transaction.message.instructions.last().unwrap().clone();
+        let amount = u64::from_le_bytes(withdrawal.data[8..16].try_into().unwrap());
+        assert_eq!(amount, (1 << 53) + 3);
+        // the ed25519 check carries the FROST aggregate under the custody key
+        assert!(transaction.message.instructions[0].data.windows(32).any(|window| window == operator.as_ref()));
+        assert_eq!(count_with_status(&payout.db, reward::STATUS_SUBMITTED).await, 3);
+    }
+
+    #[tokio::test]
+    async fn payout_waits_for_the_holders_signature() {
+        let db = in_memory_db().await;
+        let (user_id, wallet) = (Uuid::new_v4(), Pubkey::new_unique());
+        insert_validator(&db, user_id, &wallet).await;
+        pending_reward(&db, user_id, LAMPORTS_PER_SOL as i64).await;
+
+        let signer = HolderSigner::new(false);
+        let vault = vault_data(vec![signer.operator()], 1, 0);
+        let payout = service_with_db(db, vault, None, signer, Pubkey::new_unique());
+        payout.submit_next().await.unwrap();
+        payout.submit_next().await.unwrap();
+        assert!(payout.rpc.sent.lock().unwrap().is_empty());
+        assert_eq!(count_with_status(&payout.db, reward::STATUS_PENDING).await, 1);
+
+        // the holders finish signing the message they were first given
+        payout.signer.ready.store(true, Ordering::SeqCst);
+        payout.submit_next().await.unwrap();
+        let asked = payout.signer.asked.lock().unwrap().clone();
+        assert_eq!(asked.len(), 3);
+        assert!(asked.iter().all(|message| *message == asked[0]));
+        assert_eq!(payout.rpc.sent.lock().unwrap().len(), 1);
+        assert_eq!(count_with_status(&payout.db, reward::STATUS_SUBMITTED).await, 1);
+    }
+}
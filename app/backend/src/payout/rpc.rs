@@ -0,0 +1,109 @@
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::json;
+use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction};
+
+use crate::indexer::BoxError;
+use crate::utils::solana_rpc::JsonRpcClient;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureState {
+    Confirmed,
+    Failed,
+}
+
+// Everything the payout service needs from a cluster, so it can run against a mock or an
+// in-process runtime as well as a real RPC node.
+#[async_trait]
+pub trait PayoutRpc: Send + Sync {
+    async fn get_slot(&self) -> Result<u64, BoxError>;
+    async fn get_latest_blockhash(&self) -> Result<Hash, BoxError>;
+    async fn get_account_data(&self, pubkey: &Pubkey) -> Result<Option<Vec<u8>>, BoxError>;
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, BoxError>;
+    // None while the cluster hasn't seen (or has forgotten) the signature
+    async fn get_signature_state(&self, signature: &Signature) -> Result<Option<SignatureState>, BoxError>;
+    // Log messages of a landed transaction, None until the node can serve it
+    async fn get_transaction_logs(&self, signature: &Signature) -> Result<Option<Vec<String>>, BoxError>;
+}
+
+pub struct HttpPayoutRpc {
+    rpc: JsonRpcClient,
+}
+
+impl HttpPayoutRpc {
+    pub fn new(rpc_url: String) -> Self {
+        Self {
+            rpc: JsonRpcClient::new(rpc_url),
+        }
+    }
+}
+
+#[async_trait]
+impl PayoutRpc for HttpPayoutRpc {
+    async fn get_slot(&self) -> Result<u64, BoxError> {
+        let result = self.rpc.call("getSlot", json!([{ "commitment": "confirmed" }])).await?;
+        result.as_u64().ok_or_else(|| "getSlot returned no slot".into())
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<Hash, BoxError> {
+        let result = self.rpc.call("getLatestBlockhash", json!([{ "commitment": "confirmed" }])).await?;
+        let blockhash = result["value"]["blockhash"].as_str().ok_or("getLatestBlockhash returned no blockhash")?;
+        Ok(Hash::from_str(blockhash)?)
+    }
+
+    async fn get_account_data(&self, pubkey: &Pubkey) -> Result<Option<Vec<u8>>, BoxError> {
+        let result = self
+            .rpc
+            .call("getAccountInfo", json!([pubkey.to_string(), { "encoding": "base64", "commitment": "confirmed" }]))
+            .await?;
+        if result["value"].is_null() {
+            return Ok(None);
+        }
+        let encoded = result["value"]["data"][0].as_str().ok_or("getAccountInfo returned no data")?;
+        Ok(Some(STANDARD.decode(encoded)?))
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, BoxError> {
+        let encoded = STANDARD.encode(bincode::serialize(transaction)?);
+        let result = self
+            .rpc
+            .call("sendTransaction", json!([encoded, { "encoding": "base64", "preflightCommitment": "confirmed" }]))
+            .await?;
+        let signature = result.as_str().ok_or("sendTransaction returned no signature")?;
+        Ok(Signature::from_str(signature)?)
+    }
+
+    async fn get_signature_state(&self, signature: &Signature) -> Result<Option<SignatureState>, BoxError> {
+        let result = self
+            .rpc
+            .call("getSignatureStatuses", json!([[signature.to_string()], { "searchTransactionHistory": true }]))
+            .await?;
+        let status = &result["value"][0];
+        if status.is_null() {
+            return Ok(None);
+        }
+        if !status["err"].is_null() {
+            return Ok(Some(SignatureState::Failed));
+        }
+        match status["confirmationStatus"].as_str() {
+            Some("confirmed") | Some("finalized") => Ok(Some(SignatureState::Confirmed)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn get_transaction_logs(&self, signature: &Signature) -> Result<Option<Vec<String>>, BoxError> {
+        let result = self
+            .rpc
+            .call(
+                "getTransaction",
+                json!([signature.to_string(), { "encoding": "json", "commitment": "confirmed", "maxSupportedTransactionVersion": 0 }]),
+            )
+            .await?;
+        if result.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_value(result["meta"]["logMessages"].clone()).unwrap_or_default()))
+    }
+}
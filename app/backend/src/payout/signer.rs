@@ -0,0 +1,85 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use solana_sdk::pubkey::Pubkey;
+use uuid::Uuid;
+
+use crate::entities::custody_key;
+use crate::indexer::BoxError;
+use crate::payout::instruction::WithdrawalMessage;
+use crate::routes::threshold::{open_signing_session, signing_result, PAYOUT};
+use crate::types::custody::CustodyState;
+use crate::types::threshold::WithdrawalRequest;
+
+// Produces the vault operator's signature over a withdrawal. The signature may take several payout
+// runs to arrive, the same message is asked for until it does.
+#[async_trait]
+pub trait WithdrawalSigner: Send + Sync {
+    // the key the signature is made under, it has to be in the vault's operator set
+    fn operator(&self) -> Pubkey;
+    // None while the signature is still being produced
+    async fn sign(&self, message: &WithdrawalMessage, fee_account: &Pubkey) -> Result<Option<[u8; 64]>, BoxError>;
+}
+
+// Signs under a FROST custody key: each withdrawal becomes a threshold signing session its holders
+// complete on their own devices, found through /threshold/payouts/{public_key}. No part of the key
+// is ever held by this process.
+pub struct ThresholdSigner {
+    state: CustodyState,
+    operator: Pubkey,
+}
+
+impl ThresholdSigner {
+    pub fn new(state: CustodyState, operator: Pubkey) -> Self {
+        Self { state, operator }
+    }
+
+    async fn custody_key(&self) -> Result<custody_key::Model, BoxError> {
+        let key = custody_key::Entity::find()
+            .filter(custody_key::Column::PublicKey.eq(self.operator.to_string()))
+            .one(&self.state.db)
+            .await?
+            .ok_or_else(|| format!("no custody key {}", self.operator))?;
+        if key.scheme != custody_key::SCHEME_FROST {
+            return Err(format!("{} is not a threshold signing key", self.operator).into());
+        }
+        Ok(key)
+    }
+}
+
+#[async_trait]
+impl WithdrawalSigner for ThresholdSigner {
+    fn operator(&self) -> Pubkey {
+        self.operator
+    }
+
+    async fn sign(&self, message: &WithdrawalMessage, fee_account: &Pubkey) -> Result<Option<[u8; 64]>, BoxError> {
+        let message_bytes = borsh::to_vec(message)?;
+        let public_key = self.operator.to_string();
+
+        // the session opened for this message on an earlier run, if it is still open
+        if let Some(session_id) = self.state.share_storage.get_session::<String>(PAYOUT, &public_key).await? {
+            if let Some((session, signature)) = signing_result(&self.state, &session_id).await? {
+                if session.message == STANDARD.encode(&message_bytes) {
+                    return Ok(signature.map(<[u8; 64]>::from));
+                }
+            }
+        }
+
+        let withdrawal = WithdrawalRequest {
+            vault: message.vault.to_string(),
+            recipient: message.recipient.to_string(),
+            amount: message.amount,
+            nonce: message.nonce,
+            expiry_slot: message.expiry_slot,
+            fee_account: fee_account.to_string(),
+        };
+        let session = open_signing_session(&self.state, self.custody_key().await?, withdrawal, &message_bytes, Uuid::nil()).await?;
+        self.state.share_storage.put_session(PAYOUT, &public_key, &session.session_id).await?;
+        println!(
+            "Payout of {} lamports to {} is waiting for the holders of {} in signing session {}",
+            message.amount, message.recipient, public_key, session.session_id
+        );
+        Ok(None)
+    }
+}
@@ -0,0 +1,119 @@
+use chrono::{Duration, SubsecRound, Utc};
+use rand::{rngs::OsRng, RngCore};
+use redis::{AsyncCommands, Client};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::types::redis::WalletChallenge;
+use crate::utils::siws::{get_siws_domain, SiwsMessage};
+
+// A wallet has 5 minutes to sign the challenge it was handed
+pub const CHALLENGE_TTL_SECS: u64 = 5 * 60;
+
+fn challenge_key(nonce: &str) -> String {
+    format!("siws:{}", nonce)
+}
+
+// Single use nonces for Sign-In-With-Solana, a challenge is deleted the moment it is redeemed
+#[derive(Debug, Clone)]
+pub struct ChallengeStore {
+    pub redis_client: Client,
+}
+
+impl ChallengeStore {
+    pub fn new(redis_client: Client) -> Self {
+        println!("initializing challenge store with shared client manager");
+        Self { redis_client }
+    }
+
+    // The statement is what the wallet shows the user, it names the ids the challenge is scoped to
+    pub async fn create_challenge(
+        &self,
+        wallet: &str,
+        purpose: &str,
+        statement: String,
+        user_id: Option<Uuid>,
+        device_id: Option<String>,
+    ) -> Result<WalletChallenge, anyhow::Error> {
+        let mut nonce = [0u8; 16];
+        OsRng.fill_bytes(&mut nonce);
+
+        // millisecond precision, the same the timestamps have in the signed message
+        let issued_at = Utc::now().trunc_subsecs(3);
+        let challenge = WalletChallenge {
+            wallet: wallet.to_string(),
+            nonce: bs58::encode(nonce).into_string(),
+            purpose: purpose.to_string(),
+            statement,
+            user_id,
+            device_id,
+            issued_at,
+            expires_at: issued_at + Duration::seconds(CHALLENGE_TTL_SECS as i64),
+        };
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let challenge_json = serde_json::to_string(&challenge)?;
+        let _: () = conn
+            .set_ex(challenge_key(&challenge.nonce), challenge_json, CHALLENGE_TTL_SECS)
+            .await?;
+
+        println!("Issued {} challenge {} for wallet {}", purpose, challenge.nonce, wallet);
+        Ok(challenge)
+    }
+
+    pub async fn get_challenge(&self, nonce: &str) -> Result<Option<WalletChallenge>, anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let challenge_json: Option<String> = conn.get(challenge_key(nonce)).await?;
+        match challenge_json {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Atomically deletes the challenge, true only for the one caller that redeemed it
+    pub async fn consume_challenge(&self, nonce: &str) -> Result<bool, anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let challenge_json: Option<String> = conn.get_del(challenge_key(nonce)).await?;
+        Ok(challenge_json.is_some())
+    }
+
+    // Checks a signed challenge message end to end and spends its nonce. Returns the challenge so
+    // callers can check the ids it was scoped to, or a status code and message to answer with.
+    pub async fn redeem(
+        &self,
+        purpose: &str,
+        public_key: &str,
+        signature: &str,
+        message: &str,
+    ) -> Result<WalletChallenge, (u32, String)> {
+        let pubkey = Pubkey::from_str(public_key).map_err(|e| (400, format!("could not parse due to {}", e)))?;
+        let signature = Signature::from_str(signature).map_err(|e| (400, format!("could not parse due to {}", e)))?;
+        let sign_in_message = SiwsMessage::parse(message).map_err(|e| (400, format!("Invalid sign in message : {}", e)))?;
+
+        let challenge = match self.get_challenge(&sign_in_message.nonce).await {
+            Ok(Some(challenge)) => challenge,
+            Ok(None) => return Err((401, "Challenge not found, expired or already used".to_string())),
+            Err(e) => return Err((500, format!("Error loading challenge : {}", e))),
+        };
+        if challenge.purpose != purpose {
+            return Err((401, format!("Challenge was issued for {}", challenge.purpose)));
+        }
+        if challenge.wallet != pubkey.to_string() {
+            return Err((401, "Challenge was issued for another wallet".to_string()));
+        }
+        if let Err(e) = sign_in_message.check(&get_siws_domain(), &challenge, Utc::now()) {
+            return Err((401, format!("Sign in message rejected : {}", e)));
+        }
+        if !signature.verify(pubkey.as_ref(), message.as_bytes()) {
+            return Err((401, "Invalid signature".to_string()));
+        }
+
+        // only now is the nonce spent, so two requests racing with the same signature get one through
+        match self.consume_challenge(&challenge.nonce).await {
+            Ok(true) => Ok(challenge),
+            Ok(false) => Err((401, "Challenge already used".to_string())),
+            Err(e) => Err((500, format!("Error redeeming challenge : {}", e))),
+        }
+    }
+}
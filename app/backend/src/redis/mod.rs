@@ -2,4 +2,6 @@ pub mod cookie_manager;
 pub mod pubsub_manager;
 pub mod client;
 pub mod queue_manager;
-pub mod queue_worker;
\ No newline at end of file
+pub mod queue_worker;
+pub mod shares_manager;
+pub mod challenge_manager;
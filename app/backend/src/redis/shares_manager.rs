@@ -1,19 +1,174 @@
 use std::collections::HashMap;
-use anyhow::anyhow;
-use sharks::Share;
 
+use redis::{AsyncCommands, Client};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::types::custody::Ceremony;
+
+// Holds running custody sessions (recovery ceremonies, threshold signing, DKG) and what holders
+// submit to them. Everything secret is stored sealed, to the server's seal key or to the holder
+// or admin it is meant for.
+#[derive(Debug, Clone)]
 pub struct ShareStorage {
-    shares: HashMap<String, Share>,
+    pub redis_client: Client,
+}
+
+// A session that doesn't gather its threshold within 15 minutes has to be started again
+pub const CEREMONY_TTL_SECS: u64 = 15 * 60;
+
+fn session_key(kind: &str, session_id: &str) -> String {
+    format!("{}:{}", kind, session_id)
+}
+
+fn session_part_key(kind: &str, session_id: &str, part: &str) -> String {
+    format!("{}:{}:{}", kind, session_id, part)
 }
 
 impl ShareStorage {
-    pub fn store_share(&mut self, public_key: &str, share: Share) {
-        self.shares.insert(public_key.to_string(), share);
-        println!("Stored share for: {}", public_key);
+    pub fn new(redis_client: Client) -> Self {
+        println!("initializing share storage with shared client manager");
+        Self { redis_client }
+    }
+
+    pub async fn put_session<T: Serialize>(&self, kind: &str, session_id: &str, session: &T) -> Result<(), anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let session_json = serde_json::to_string(session)?;
+        let _: () = conn
+            .set_ex(session_key(kind, session_id), session_json, CEREMONY_TTL_SECS)
+            .await?;
+        Ok(())
     }
-    
-    pub fn retrieve_share(&self, public_key: &str) -> Result<&Share, anyhow::Error> {
-        self.shares.get(public_key)
-            .ok_or(anyhow!("share not found for : {}", public_key))
+
+    pub async fn get_session<T: DeserializeOwned>(&self, kind: &str, session_id: &str) -> Result<Option<T>, anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let session_json: Option<String> = conn.get(session_key(kind, session_id)).await?;
+        match session_json {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Stores one submission in a session part, a second submission under the same field replaces
+    // the first. Returns how many fields the part holds.
+    pub async fn put_entry(&self, kind: &str, session_id: &str, part: &str, field: &str, value: &str) -> Result<usize, anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let part_key = session_part_key(kind, session_id, part);
+        let _: () = conn.hset(&part_key, field, value).await?;
+        let _: () = conn.expire(&part_key, CEREMONY_TTL_SECS as i64).await?;
+        let count: usize = conn.hlen(&part_key).await?;
+        Ok(count)
     }
-}
\ No newline at end of file
+
+    // Like put_entry but a field can only be stored once, None if it already was. Returns how many
+    // fields the part holds.
+    pub async fn put_entry_once(&self, kind: &str, session_id: &str, part: &str, field: &str, value: &str) -> Result<Option<usize>, anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let part_key = session_part_key(kind, session_id, part);
+        let stored: bool = conn.hset_nx(&part_key, field, value).await?;
+        if !stored {
+            return Ok(None);
+        }
+        let _: () = conn.expire(&part_key, CEREMONY_TTL_SECS as i64).await?;
+        let count: usize = conn.hlen(&part_key).await?;
+        Ok(Some(count))
+    }
+
+    // Records value as used for kind for good, false if it was recorded before
+    pub async fn remember(&self, kind: &str, value: &str) -> Result<bool, anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let added: usize = conn.sadd(format!("{}:seen", kind), value).await?;
+        Ok(added == 1)
+    }
+
+    pub async fn entries(&self, kind: &str, session_id: &str, part: &str) -> Result<HashMap<String, String>, anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let entries: HashMap<String, String> = conn.hgetall(session_part_key(kind, session_id, part)).await?;
+        Ok(entries)
+    }
+
+    // Sets a session value only if it isn't set yet, true if this call set it. Concurrent
+    // submissions use it to agree on a single outcome.
+    pub async fn set_once(&self, kind: &str, session_id: &str, part: &str, value: &str) -> Result<bool, anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let set: Option<String> = redis::cmd("SET")
+            .arg(session_part_key(kind, session_id, part))
+            .arg(value)
+            .arg("NX")
+            .arg("EX")
+            .arg(CEREMONY_TTL_SECS)
+            .query_async(&mut conn)
+            .await?;
+        Ok(set.is_some())
+    }
+
+    pub async fn get_value(&self, kind: &str, session_id: &str, part: &str) -> Result<Option<String>, anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let value: Option<String> = conn.get(session_part_key(kind, session_id, part)).await?;
+        Ok(value)
+    }
+
+    // Reads a session value and deletes it in the same step, so only one caller ever gets it
+    pub async fn take_value(&self, kind: &str, session_id: &str, part: &str) -> Result<Option<String>, anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let value: Option<String> = redis::cmd("GETDEL")
+            .arg(session_part_key(kind, session_id, part))
+            .query_async(&mut conn)
+            .await?;
+        Ok(value)
+    }
+
+    pub async fn close_session(&self, kind: &str, session_id: &str, parts: &[&str]) -> Result<(), anyhow::Error> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let mut keys = vec![session_key(kind, session_id)];
+        keys.extend(parts.iter().map(|part| session_part_key(kind, session_id, part)));
+        let _: () = conn.del(keys).await?;
+        println!("Closed {} session {}", kind, session_id);
+        Ok(())
+    }
+
+    pub async fn start_ceremony(&self, ceremony: &Ceremony) -> Result<(), anyhow::Error> {
+        self.put_session("ceremony", &ceremony.ceremony_id, ceremony).await?;
+        println!("Started recovery ceremony {} for {}", ceremony.ceremony_id, ceremony.public_key);
+        Ok(())
+    }
+
+    pub async fn get_ceremony(&self, ceremony_id: &str) -> Result<Option<Ceremony>, anyhow::Error> {
+        self.get_session("ceremony", ceremony_id).await
+    }
+
+    // Stores a holder's sealed share, a holder submitting twice replaces its earlier share.
+    // Returns how many holders have submitted so far.
+    pub async fn store_share(&self, ceremony_id: &str, holder_id: &str, ciphertext: &str) -> Result<usize, anyhow::Error> {
+        let collected = self.put_entry("ceremony", ceremony_id, "shares", holder_id, ciphertext).await?;
+        println!("Stored share for holder {} in ceremony {}", holder_id, ceremony_id);
+        Ok(collected)
+    }
+
+    // Sealed shares submitted to a ceremony, keyed by holder id
+    pub async fn retrieve_shares(&self, ceremony_id: &str) -> Result<HashMap<String, String>, anyhow::Error> {
+        self.entries("ceremony", ceremony_id, "shares").await
+    }
+
+    // The ceremony secret, sealed to the server's seal key
+    pub async fn store_ceremony_key(&self, ceremony_id: &str, sealed_key: &str) -> Result<bool, anyhow::Error> {
+        self.set_once("ceremony", ceremony_id, "key", sealed_key).await
+    }
+
+    pub async fn get_ceremony_key(&self, ceremony_id: &str) -> Result<Option<String>, anyhow::Error> {
+        self.get_value("ceremony", ceremony_id, "key").await
+    }
+
+    pub async fn close_ceremony(&self, ceremony_id: &str) -> Result<(), anyhow::Error> {
+        self.close_session("ceremony", ceremony_id, &["shares", "key"]).await
+    }
+
+    // A recovered keypair sealed to the admin who started the recovery, kept until they pick it up
+    // or CEREMONY_TTL_SECS passes
+    pub async fn store_recovered_key(&self, ceremony_id: &str, started_by: &str, sealed_key: &str) -> Result<bool, anyhow::Error> {
+        self.set_once("recovered", ceremony_id, started_by, sealed_key).await
+    }
+
+    pub async fn take_recovered_key(&self, ceremony_id: &str, user_id: &str) -> Result<Option<String>, anyhow::Error> {
+        self.take_value("recovered", ceremony_id, user_id).await
+    }
+}
@@ -0,0 +1,489 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use uuid::Uuid;
+
+use crate::entities::{reward, scheduled_check, validator, website_performance};
+use crate::indexer::BoxError;
+use crate::types::reward::{RewardCredit, RewardRun};
+
+// Inserts are batched so a long backlog doesn't turn into one huge statement
+const INSERT_BATCH: usize = 500;
+
+// Multipliers are in basis points, 10_000 is 1x
+pub const BPS: u64 = 10_000;
+
+pub struct RewardConfig {
+    pub per_check_lamports: u64,        // credited for one accepted website check before multipliers
+    pub consensus_multiplier_bps: u64,  // reached when every peer agrees, see consensus_weight_bps
+    pub consensus_window_secs: i64,     // checks of a website this close together are compared
+    pub min_consensus_peers: usize,     // other validators needed before their majority counts
+    pub underserved_multiplier_bps: u64, // applied to validators in thinly covered regions
+    pub underserved_max_validators: usize,
+    pub region_cell_degrees: f64,       // regions are latitude/longitude cells of this size
+    pub lookback_hours: i64,            // how far back each run looks for uncredited checks
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            per_check_lamports: 100_000,
+            consensus_multiplier_bps: 15_000,
+            consensus_window_secs: 120,
+            min_consensus_peers: 2,
+            underserved_multiplier_bps: 12_500,
+            underserved_max_validators: 2,
+            region_cell_degrees: 10.0,
+            lookback_hours: 24,
+        }
+    }
+}
+
+impl RewardConfig {
+    // Every field can be set through its REWARD_* variable, unset ones keep the default
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        let value = |name: &str| lookup(name).map(|value| (name.to_string(), value));
+        Self {
+            per_check_lamports: parse_or(value("REWARD_PER_CHECK_LAMPORTS"), defaults.per_check_lamports),
+            consensus_multiplier_bps: parse_or(value("REWARD_CONSENSUS_MULTIPLIER_BPS"), defaults.consensus_multiplier_bps),
+            consensus_window_secs: parse_or(value("REWARD_CONSENSUS_WINDOW_SECS"), defaults.consensus_window_secs),
+            min_consensus_peers: parse_or(value("REWARD_MIN_CONSENSUS_PEERS"), defaults.min_consensus_peers),
+            underserved_multiplier_bps: parse_or(value("REWARD_UNDERSERVED_MULTIPLIER_BPS"), defaults.underserved_multiplier_bps),
+            underserved_max_validators: parse_or(value("REWARD_UNDERSERVED_MAX_VALIDATORS"), defaults.underserved_max_validators),
+            region_cell_degrees: parse_or(value("REWARD_REGION_CELL_DEGREES"), defaults.region_cell_degrees),
+            lookback_hours: parse_or(value("REWARD_LOOKBACK_HOURS"), defaults.lookback_hours),
+        }
+    }
+}
+
+fn parse_or<T: FromStr>(value: Option<(String, String)>, default: T) -> T {
+    let Some((name, value)) = value else {
+        return default;
+    };
+    value.trim().parse().unwrap_or_else(|_| {
+        eprintln!("Ignoring {}={}, it isn't a valid value", name, value);
+        default
+    })
+}
+
+// Credits pending Reward rows for accepted contributions. A contribution is credited at most once
+// per reward type (unique index on contribution_id, reward_type), so runs can overlap freely.
+pub struct RewardEngine {
+    db: DatabaseConnection,
+    config: RewardConfig,
+}
+
+impl RewardEngine {
+    pub fn new(db: DatabaseConnection, config: RewardConfig) -> Self {
+        Self { db, config }
+    }
+
+    pub fn spawn(self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                match self.run_once(false).await {
+                    Ok(run) if run.inserted > 0 => {
+                        println!("Credited {} rewards, {} lamports", run.inserted, run.total_amount)
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Reward run failed : {}", e),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    // Scores the website checks of the lookback window. Checks newer than the consensus window are
+    // left for the next run, other validators' checks of the same moment may still be coming in.
+    pub async fn run_once(&self, dry_run: bool) -> Result<RewardRun, BoxError> {
+        let window = chrono::Duration::seconds(self.config.consensus_window_secs);
+        let to = Utc::now() - window;
+        let from = to - chrono::Duration::hours(self.config.lookback_hours);
+
+        // runs the scheduler hasn't settled yet are left for a later run
+        let assignments = scheduled_check::Entity::find()
+            .filter(scheduled_check::Column::Status.eq(scheduled_check::STATUS_COMPLETED))
+            .filter(scheduled_check::Column::DueBy.gte((from - window).fixed_offset()))
+            .filter(scheduled_check::Column::ScheduledFor.lte((to + window).fixed_offset()))
+            .all(&self.db)
+            .await?;
+        let checks = website_performance::Entity::find()
+            .filter(website_performance::Column::Timestamp.gte((from - window).fixed_offset()))
+            .filter(website_performance::Column::Timestamp.lte((to + window).fixed_offset()))
+            .order_by_asc(website_performance::Column::Timestamp)
+            .all(&self.db)
+            .await?;
+        let validators: HashMap<Uuid, validator::Model> = validator::Entity::find()
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|validator| (validator.id, validator))
+            .collect();
+
+        let candidates: Vec<Uuid> = checks
+            .iter()
+            .filter(|check| in_range(check.timestamp, from, to))
+            .map(|check| check.id)
+            .collect();
+        let mut credited = HashSet::new();
+        for ids in candidates.chunks(INSERT_BATCH) {
+            let rows = reward::Entity::find()
+                .filter(reward::Column::RewardType.eq(reward::REWARD_TYPE_WEBSITE_CHECK))
+                .filter(reward::Column::ContributionId.is_in(ids.iter().copied()))
+                .all(&self.db)
+                .await?;
+            credited.extend(rows.into_iter().filter_map(|row| row.contribution_id));
+        }
+
+        let scored = score_website_checks(&self.config, &checks, &validators, &assignments, |check| {
+            in_range(check.timestamp, from, to) && !credited.contains(&check.id)
+        });
+        let mut run = RewardRun {
+            dry_run,
+            total_amount: scored.credits.iter().map(|credit| credit.amount).sum(),
+            credits: scored.credits,
+            already_credited: credited.len(),
+            rejected: scored.rejected,
+            inserted: 0,
+        };
+        if dry_run {
+            return Ok(run);
+        }
+
+        for credits in run.credits.chunks(INSERT_BATCH) {
+            let rows = credits.iter().map(|credit| reward::ActiveModel {
+                user_id: Set(credit.user_id),
+                amount: Set(credit.amount as i64),
+                reward_type: Set(credit.reward_type.clone()),
+                contribution_id: Set(Some(credit.contribution_id)),
+                status: Set(reward::STATUS_PENDING.to_string()),
+                ..Default::default()
+            });
+            // a concurrent run may have credited some of these meanwhile, those rows are skipped
+            run.inserted += reward::Entity::insert_many(rows)
+                .on_conflict(
+                    OnConflict::columns([reward::Column::ContributionId, reward::Column::RewardType])
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .exec_without_returning(&self.db)
+                .await?;
+        }
+        Ok(run)
+    }
+}
+
+fn in_range(timestamp: DateTime<FixedOffset>, from: DateTime<Utc>, to: DateTime<Utc>) -> bool {
+    timestamp >= from && timestamp <= to
+}
+
+// A check counts as "up" for consensus when the site answered with a non error status
+fn is_up(check: &website_performance::Model) -> bool {
+    matches!(check.http_status_code, Some(code) if (200.0..400.0).contains(&code))
+}
+
+// A check is accepted when it comes from a registered validator and is a complete measurement. It
+// is only paid when it also answers one of the validator's scheduled assignments.
+fn accepted_validator<'a>(
+    check: &website_performance::Model,
+    validators: &'a HashMap<Uuid, validator::Model>,
+) -> Option<&'a validator::Model> {
+    if check.http_status_code.is_none() || check.total_time_ms.is_none() {
+        return None;
+    }
+    Uuid::parse_str(&check.validator_id).ok().and_then(|id| validators.get(&id))
+}
+
+fn region_cell(config: &RewardConfig, validator: &validator::Model) -> Option<(i64, i64)> {
+    let (latitude, longitude) = (validator.latitude?, validator.longitude?);
+    Some((
+        (latitude / config.region_cell_degrees).floor() as i64,
+        (longitude / config.region_cell_degrees).floor() as i64,
+    ))
+}
+
+pub struct ScoredChecks {
+    pub credits: Vec<RewardCredit>,
+    pub rejected: usize,
+}
+
+// The check that answers each assignment, as the scheduler settled it. Missed runs and anything
+// else a validator sent for the same run earn nothing.
+fn assigned_checks(assignments: &[scheduled_check::Model]) -> HashSet<Uuid> {
+    assignments
+        .iter()
+        .filter(|assignment| assignment.status == scheduled_check::STATUS_COMPLETED)
+        .filter_map(|assignment| assignment.performance_id)
+        .collect()
+}
+
+// Weight of a check the peers agreed with: 1x at an even split, consensus_multiplier_bps when every
+// peer agrees, linear in the agreeing share in between. Only called with a strict majority.
+fn consensus_weight_bps(config: &RewardConfig, agreeing: usize, votes: usize) -> u64 {
+    let bonus = config.consensus_multiplier_bps.saturating_sub(BPS) as u128;
+    let margin = (2 * agreeing).saturating_sub(votes) as u128;
+    BPS + (bonus * margin / votes.max(1) as u128) as u64
+}
+
+// per_check_lamports times the consensus weight and, in thin regions, the underserved multiplier,
+// rounded down to whole lamports and capped to what Reward.amount (i64) holds
+fn credit_amount(config: &RewardConfig, consensus_bps: u64, underserved: bool) -> u64 {
+    let mut amount = config.per_check_lamports as u128 * consensus_bps as u128 / BPS as u128;
+    if underserved {
+        amount = amount * config.underserved_multiplier_bps as u128 / BPS as u128;
+    }
+    amount.min(i64::MAX as u128) as u64
+}
+
+// Scores the checks `credit` selects. `checks` also holds the surrounding checks that only serve as
+// peers for consensus. Only checks answering an assignment are paid or vote, and a check is paid
+// only when enough other assigned validators saw the website the same way, more the more of them do.
+pub fn score_website_checks(
+    config: &RewardConfig,
+    checks: &[website_performance::Model],
+    validators: &HashMap<Uuid, validator::Model>,
+    assignments: &[scheduled_check::Model],
+    credit: impl Fn(&website_performance::Model) -> bool,
+) -> ScoredChecks {
+    let assigned = assigned_checks(assignments);
+
+    let mut per_region: HashMap<(i64, i64), usize> = HashMap::new();
+    for validator in validators.values() {
+        if let Some(cell) = region_cell(config, validator) {
+            *per_region.entry(cell).or_default() += 1;
+        }
+    }
+
+    let mut per_website: HashMap<&str, Vec<(&website_performance::Model, &validator::Model)>> = HashMap::new();
+    for check in checks.iter().filter(|check| assigned.contains(&check.id)) {
+        if let Some(validator) = accepted_validator(check, validators) {
+            per_website.entry(check.website_id.as_str()).or_default().push((check, validator));
+        }
+    }
+
+    let window = chrono::Duration::seconds(config.consensus_window_secs);
+    let mut scored = ScoredChecks { credits: Vec::new(), rejected: 0 };
+    for check in checks.iter().filter(|check| credit(check)) {
+        let Some(validator) = accepted_validator(check, validators).filter(|_| assigned.contains(&check.id)) else {
+            scored.rejected += 1;
+            continue;
+        };
+
+        // each other validator votes with its check closest in time to this one
+        let mut votes: HashMap<Uuid, (chrono::Duration, bool)> = HashMap::new();
+        for (peer, peer_validator) in &per_website[check.website_id.as_str()] {
+            let distance = (peer.timestamp - check.timestamp).abs();
+            if peer_validator.id == validator.id || distance > window {
+                continue;
+            }
+            let vote = votes.entry(peer_validator.id).or_insert((distance, is_up(peer)));
+            if distance < vote.0 {
+                *vote = (distance, is_up(peer));
+            }
+        }
+        let agreeing = votes.values().filter(|(_, up)| *up == is_up(check)).count();
+        if votes.len() < config.min_consensus_peers || 2 * agreeing <= votes.len() {
+            scored.rejected += 1;
+            continue;
+        }
+        let consensus_bps = consensus_weight_bps(config, agreeing, votes.len());
+
+        let underserved = region_cell(config, validator)
+            .map(|cell| per_region[&cell] <= config.underserved_max_validators)
+            .unwrap_or(false);
+
+        scored.credits.push(RewardCredit {
+            contribution_id: check.id,
+            reward_type: reward::REWARD_TYPE_WEBSITE_CHECK.to_string(),
+            user_id: validator.user_id,
+            validator_id: validator.id,
+            amount: credit_amount(config, consensus_bps, underserved),
+            consensus_bps,
+            underserved,
+        });
+    }
+    scored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validator(latitude: f64) -> validator::Model {
+        let id = Uuid::new_v4();
+        validator::Model {
+            id,
+            user_id: Uuid::new_v4(),
+            wallet_address: id.to_string(),
+            latitude: Some(latitude),
+            longitude: Some(0.0),
+            device_id: id.to_string(),
+            created_at: None,
+        }
+    }
+
+    fn check(validator: &validator::Model, website_id: Uuid, at: DateTime<Utc>, status: f64) -> website_performance::Model {
+        website_performance::Model {
+            id: Uuid::new_v4(),
+            validator_id: validator.id.to_string(),
+            website_id: website_id.to_string(),
+            timestamp: at.fixed_offset(),
+            http_status_code: Some(status),
+            dns_resolution_ms: None,
+            connection_time_ms: None,
+            tls_handshake_ms: None,
+            time_to_first_byte_ms: None,
+            content_download_ms: None,
+            total_time_ms: Some(120.0),
+        }
+    }
+
+    // An assignment the scheduler settled, completed by `result` or missed without one
+    fn settled(validator: &validator::Model, website_id: Uuid, run_id: Uuid, at: DateTime<Utc>, result: Option<&website_performance::Model>) -> scheduled_check::Model {
+        scheduled_check::Model {
+            id: Uuid::new_v4(),
+            run_id,
+            website_id,
+            validator_id: validator.id,
+            scheduled_for: at.fixed_offset(),
+            due_by: (at + chrono::Duration::seconds(120)).fixed_offset(),
+            status: match result {
+                Some(_) => scheduled_check::STATUS_COMPLETED.to_string(),
+                None => scheduled_check::STATUS_MISSED.to_string(),
+            },
+            performance_id: result.map(|result| result.id),
+            created_at: at.fixed_offset(),
+        }
+    }
+
+    fn by_id(validators: &[&validator::Model]) -> HashMap<Uuid, validator::Model> {
+        validators.iter().map(|validator| (validator.id, (*validator).clone())).collect()
+    }
+
+    #[test]
+    fn pays_one_assigned_check_per_run_with_consensus() {
+        let config = RewardConfig { underserved_max_validators: 0, ..RewardConfig::default() };
+        let (website, run, at) = (Uuid::new_v4(), Uuid::new_v4(), Utc::now());
+        let (a, b, c, d, outsider) = (validator(10.0), validator(20.0), validator(30.0), validator(40.0), validator(50.0));
+        let validators = by_id(&[&a, &b, &c, &d, &outsider]);
+
+        let first = check(&a, website, at + chrono::Duration::seconds(5), 200.0);
+        let repeat = check(&a, website, at + chrono::Duration::seconds(10), 200.0);
+        let from_b = check(&b, website, at + chrono::Duration::seconds(6), 200.0);
+        let from_c = check(&c, website, at + chrono::Duration::seconds(7), 200.0);
+        // d's result came in after its run was settled as missed, the outsider was never assigned
+        let late = check(&d, website, at + chrono::Duration::seconds(8), 200.0);
+        let unassigned = check(&outsider, website, at + chrono::Duration::seconds(5), 200.0);
+        let checks = vec![first.clone(), repeat.clone(), from_b.clone(), from_c.clone(), late.clone(), unassigned.clone()];
+        let assignments = vec![
+            settled(&a, website, run, at, Some(&first)),
+            settled(&b, website, run, at, Some(&from_b)),
+            settled(&c, website, run, at, Some(&from_c)),
+            settled(&d, website, run, at, None),
+        ];
+
+        let scored = score_website_checks(&config, &checks, &validators, &assignments, |_| true);
+        let paid: HashSet<Uuid> = scored.credits.iter().map(|credit| credit.contribution_id).collect();
+        assert_eq!(paid, HashSet::from([first.id, from_b.id, from_c.id]));
+        assert_eq!(scored.rejected, 3);
+        // every peer agreed with each of them
+        assert!(scored.credits.iter().all(|credit| credit.consensus_bps == config.consensus_multiplier_bps));
+        assert!(scored.credits.iter().all(|credit| credit.amount == 150_000));
+
+        // the repeat stays unpaid once the first check of the run has been credited
+        let scored = score_website_checks(&config, &checks, &validators, &assignments, |check| check.id == repeat.id);
+        assert!(scored.credits.is_empty());
+    }
+
+    #[test]
+    fn checks_without_consensus_earn_nothing() {
+        let config = RewardConfig::default();
+        let (website, run, at) = (Uuid::new_v4(), Uuid::new_v4(), Utc::now());
+        let (a, b, c) = (validator(10.0), validator(20.0), validator(30.0));
+        let validators = by_id(&[&a, &b, &c]);
+
+        // a lone dissenter and the two peers it disagrees with
+        let checks = vec![
+            check(&a, website, at + chrono::Duration::seconds(5), 500.0),
+            check(&b, website, at + chrono::Duration::seconds(6), 200.0),
+            check(&c, website, at + chrono::Duration::seconds(7), 200.0),
+        ];
+        let assignments: Vec<_> = [&a, &b, &c]
+            .iter()
+            .zip(&checks)
+            .map(|(validator, result)| settled(validator, website, run, at, Some(result)))
+            .collect();
+        let scored = score_website_checks(&config, &checks, &validators, &assignments, |check| check.id == checks[0].id);
+        assert!(scored.credits.is_empty());
+        assert_eq!(scored.rejected, 1);
+
+        // with only one assigned peer there is no majority to agree with
+        let scored = score_website_checks(&config, &checks[1..], &validators, &assignments, |_| true);
+        assert!(scored.credits.is_empty());
+    }
+
+    #[test]
+    fn reward_grows_with_agreement() {
+        let config = RewardConfig { underserved_max_validators: 1, ..RewardConfig::default() };
+        let (website, run, at) = (Uuid::new_v4(), Uuid::new_v4(), Utc::now());
+        // a sits alone in its region, b, c and d share one
+        let (a, b, c, d) = (validator(10.0), validator(40.0), validator(41.0), validator(42.0));
+        let validators = by_id(&[&a, &b, &c, &d]);
+
+        let checks = vec![
+            check(&a, website, at + chrono::Duration::seconds(5), 200.0),
+            check(&b, website, at + chrono::Duration::seconds(6), 200.0),
+            check(&c, website, at + chrono::Duration::seconds(7), 200.0),
+            check(&d, website, at + chrono::Duration::seconds(8), 500.0),
+        ];
+        let assignments: Vec<_> = [&a, &b, &c, &d]
+            .iter()
+            .zip(&checks)
+            .map(|(validator, result)| settled(validator, website, run, at, Some(result)))
+            .collect();
+        let scored = score_website_checks(&config, &checks, &validators, &assignments, |_| true);
+        let credits: HashMap<Uuid, &RewardCredit> = scored.credits.iter().map(|credit| (credit.validator_id, credit)).collect();
+
+        // two of three peers agree: 1x plus a third of the 0.5x bonus, then 1.25x for the thin region
+        assert_eq!(credits[&a.id].consensus_bps, 11_666);
+        assert!(credits[&a.id].underserved);
+        assert_eq!(credits[&a.id].amount, 145_825);
+        assert_eq!(credits[&b.id].amount, 116_660);
+        assert!(!credits.contains_key(&d.id));
+        assert_eq!(scored.rejected, 1);
+    }
+
+    #[test]
+    fn config_reads_every_field_from_env() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("REWARD_PER_CHECK_LAMPORTS", "5000"),
+            ("REWARD_CONSENSUS_MULTIPLIER_BPS", "20000"),
+            ("REWARD_CONSENSUS_WINDOW_SECS", "60"),
+            ("REWARD_MIN_CONSENSUS_PEERS", "3"),
+            ("REWARD_UNDERSERVED_MULTIPLIER_BPS", "11000"),
+            ("REWARD_UNDERSERVED_MAX_VALIDATORS", "4"),
+            ("REWARD_REGION_CELL_DEGREES", "5.5"),
+            ("REWARD_LOOKBACK_HOURS", "not a number"),
+        ]);
+        let config = RewardConfig::from_lookup(|name| env.get(name).map(|value| value.to_string()));
+        assert_eq!(config.per_check_lamports, 5000);
+        assert_eq!(config.consensus_multiplier_bps, 20_000);
+        assert_eq!(config.consensus_window_secs, 60);
+        assert_eq!(config.min_consensus_peers, 3);
+        assert_eq!(config.underserved_multiplier_bps, 11_000);
+        assert_eq!(config.underserved_max_validators, 4);
+        assert_eq!(config.region_cell_degrees, 5.5);
+        // unparsable values keep the default
+        assert_eq!(config.lookback_hours, RewardConfig::default().lookback_hours);
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use solana_sdk::{hash::Hash, pubkey::Pubkey};
+use uuid::Uuid;
+
+use crate::entities::{reward, reward_epoch, reward_epoch_leaf, validator};
+use crate::rewards::merkle::{leaf_hash, verify_proof, MerkleTree};
+use crate::types::epoch::EpochProof;
+
+// Matches MAX_EPOCH_LEAVES in the d-uptime program (size of the on-chain claim bitmap).
+pub const MAX_EPOCH_LEAVES: usize = 1024;
+
+pub struct EpochLeaf {
+    pub index: u32,
+    pub wallet: Pubkey,
+    pub amount: u64,
+}
+
+pub struct BuiltEpoch {
+    pub epoch_id: u64,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+    pub expiry_slot: u64,
+    pub leaves: Vec<EpochLeaf>,
+}
+
+fn build_tree(leaves: &[EpochLeaf]) -> MerkleTree {
+    MerkleTree::new(
+        leaves
+            .iter()
+            .map(|leaf| leaf_hash(leaf.index, &leaf.wallet, leaf.amount))
+            .collect(),
+    )
+}
+
+// Collects pending rewards into one leaf per validator wallet, persists the epoch and marks the
+// rewards as part of it. The returned root/total are what the operator posts with `post_epoch`.
+pub async fn build_epoch(
+    db: &DatabaseConnection,
+    epoch_id: u64,
+    expiry_slot: u64,
+) -> Result<BuiltEpoch, Box<dyn std::error::Error + Send + Sync>> {
+    let pending_rewards = reward::Entity::find()
+        .filter(reward::Column::Status.eq(reward::STATUS_PENDING))
+        .filter(reward::Column::EpochId.is_null())
+        .all(db)
+        .await?;
+
+    let user_ids: Vec<Uuid> = pending_rewards.iter().map(|reward| reward.user_id).collect();
+    let wallets: HashMap<Uuid, String> = validator::Entity::find()
+        .filter(validator::Column::UserId.is_in(user_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|validator| (validator.user_id, validator.wallet_address))
+        .collect();
+
+    // BTreeMap keeps leaf order deterministic (sorted by wallet) so the tree can be rebuilt later
+    let mut per_wallet: BTreeMap<String, (u64, Vec<Uuid>)> = BTreeMap::new();
+    for pending in pending_rewards {
+        let Some(wallet) = wallets.get(&pending.user_id) else {
+            println!("Skipping reward {} : user {} has no validator wallet", pending.id, pending.user_id);
+            continue;
+        };
+        let entry = per_wallet.entry(wallet.clone()).or_insert((0, Vec::new()));
+        entry.0 = entry.0.checked_add(u64::try_from(pending.amount)?).ok_or("pending rewards of a wallet overflow u64")?;
+        entry.1.push(pending.id);
+    }
+
+    // whatever doesn't fit stays pending for the next epoch
+    let mut leaves = Vec::new();
+    let mut reward_ids = Vec::new();
+    for (wallet, (amount, ids)) in per_wallet.into_iter().take(MAX_EPOCH_LEAVES) {
+        let wallet = match Pubkey::from_str(&wallet) {
+            Ok(pubkey) => pubkey,
+            Err(e) => {
+                println!("Skipping invalid wallet address {} : {}", wallet, e);
+                continue;
+            }
+        };
+        if amount == 0 {
+            continue;
+        }
+        leaves.push(EpochLeaf {
+            index: leaves.len() as u32,
+            wallet,
+            amount,
+        });
+        reward_ids.extend(ids);
+    }
+
+    if leaves.is_empty() {
+        return Err("no pending rewards to distribute".into());
+    }
+
+    let tree = build_tree(&leaves);
+    let merkle_root = tree.root();
+    let total_amount: u64 = leaves.iter().map(|leaf| leaf.amount).sum();
+
+    let txn = db.begin().await?;
+    reward_epoch::ActiveModel {
+        epoch_id: Set(epoch_id as i64),
+        merkle_root: Set(Hash::new_from_array(merkle_root).to_string()),
+        total_amount: Set(total_amount as i64),
+        leaf_count: Set(leaves.len() as i32),
+        expiry_slot: Set(expiry_slot as i64),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    for leaf in &leaves {
+        reward_epoch_leaf::ActiveModel {
+            epoch_id: Set(epoch_id as i64),
+            leaf_index: Set(leaf.index as i32),
+            wallet_address: Set(leaf.wallet.to_string()),
+            amount: Set(leaf.amount as i64),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+    }
+
+    // the payout service may have picked some of these rewards up meanwhile, they can't be paid twice
+    let claimed = reward::Entity::update_many()
+        .col_expr(reward::Column::Status, reward::STATUS_IN_EPOCH.into())
+        .col_expr(reward::Column::EpochId, (epoch_id as i64).into())
+        .filter(reward::Column::Id.is_in(reward_ids.iter().copied()))
+        .filter(reward::Column::Status.eq(reward::STATUS_PENDING))
+        .exec(&txn)
+        .await?;
+    if claimed.rows_affected != reward_ids.len() as u64 {
+        txn.rollback().await?;
+        return Err("pending rewards changed while building the epoch, try again".into());
+    }
+    txn.commit().await?;
+
+    println!(
+        "Built reward epoch {} with {} leaves, total {} lamports, root {}",
+        epoch_id,
+        leaves.len(),
+        total_amount,
+        Hash::new_from_array(merkle_root)
+    );
+
+    Ok(BuiltEpoch {
+        epoch_id,
+        merkle_root,
+        total_amount,
+        expiry_slot,
+        leaves,
+    })
+}
+
+// Rebuilds the epoch tree from its stored leaves and returns the proof for `wallet_address`.
+pub async fn epoch_proof(
+    db: &DatabaseConnection,
+    epoch_id: u64,
+    wallet_address: &str,
+) -> Result<Option<EpochProof>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(epoch) = reward_epoch::Entity::find()
+        .filter(reward_epoch::Column::EpochId.eq(epoch_id as i64))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let stored_leaves = reward_epoch_leaf::Entity::find()
+        .filter(reward_epoch_leaf::Column::EpochId.eq(epoch_id as i64))
+        .order_by_asc(reward_epoch_leaf::Column::LeafIndex)
+        .all(db)
+        .await?;
+
+    let mut leaves = Vec::with_capacity(stored_leaves.len());
+    for stored in &stored_leaves {
+        leaves.push(EpochLeaf {
+            index: stored.leaf_index as u32,
+            wallet: Pubkey::from_str(&stored.wallet_address)?,
+            amount: stored.amount as u64,
+        });
+    }
+
+    let Some(leaf) = leaves.iter().find(|leaf| leaf.wallet.to_string() == wallet_address) else {
+        return Ok(None);
+    };
+
+    let tree = build_tree(&leaves);
+    let merkle_root = Hash::new_from_array(tree.root()).to_string();
+    if merkle_root != epoch.merkle_root {
+        return Err(format!("stored leaves for epoch {} don't match its root", epoch_id).into());
+    }
+
+    let proof = tree.proof(leaf.index as usize).unwrap_or_default();
+    if !verify_proof(&proof, &tree.root(), leaf_hash(leaf.index, &leaf.wallet, leaf.amount)) {
+        return Err(format!("generated proof for {} doesn't verify", wallet_address).into());
+    }
+
+    Ok(Some(EpochProof {
+        epoch_id,
+        leaf_index: leaf.index,
+        wallet_address: wallet_address.to_string(),
+        amount: leaf.amount,
+        merkle_root,
+        expiry_slot: epoch.expiry_slot as u64,
+        proof: proof
+            .into_iter()
+            .map(|node| Hash::new_from_array(node).to_string())
+            .collect(),
+    }))
+}
\ No newline at end of file
@@ -0,0 +1,69 @@
+use solana_sdk::{hash::hashv, pubkey::Pubkey};
+
+// Mirrors the hashing in programs/d-uptime: leaves are prefixed with 0x00 and inner nodes with 0x01,
+// inner nodes hash the sorted pair so a proof is just the list of siblings.
+pub fn leaf_hash(index: u32, wallet: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[&[0u8], &index.to_le_bytes(), wallet.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    if left <= right {
+        hashv(&[&[1u8], left, right]).to_bytes()
+    } else {
+        hashv(&[&[1u8], right, left]).to_bytes()
+    }
+}
+
+pub struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        let mut layers = vec![leaves];
+        while layers.last().map(|layer| layer.len() > 1).unwrap_or(false) {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single, // odd node is carried up unchanged
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+        Self { layers }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first().copied())
+            .unwrap_or([0u8; 32])
+    }
+
+    pub fn proof(&self, mut index: usize) -> Option<Vec<[u8; 32]>> {
+        if index >= self.layers[0].len() {
+            return None;
+        }
+
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            let sibling = index ^ 1;
+            if sibling < layer.len() {
+                proof.push(layer[sibling]);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+pub fn verify_proof(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(leaf, |node, sibling| node_hash(&node, sibling));
+    computed == *root
+}
\ No newline at end of file
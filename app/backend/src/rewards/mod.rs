@@ -0,0 +1,3 @@
+pub mod merkle;
+pub mod epoch;
+pub mod engine;
\ No newline at end of file
@@ -5,6 +5,10 @@ use axum::{
 };
 use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
 use crate::types::website::{AddWebsiteInput, AddWebsiteResponse};
+use crate::scheduler::{MAX_CHECK_INTERVAL_SECS, MIN_CHECK_INTERVAL_SECS};
+use chrono::{Duration, Utc};
+
+const DEFAULT_CHECK_INTERVAL_SECS: i32 = 5 * 60;
 
 
 pub fn add_website_router() -> Router<AppState> {
@@ -22,6 +26,17 @@ async fn website_to_add(
     let user_id  = website_data.user_id;
     let db = state.db;
 
+    let check_interval_secs = website_data.check_interval_secs.unwrap_or(DEFAULT_CHECK_INTERVAL_SECS);
+    if !(MIN_CHECK_INTERVAL_SECS..=MAX_CHECK_INTERVAL_SECS).contains(&check_interval_secs) {
+        return Json(AddWebsiteResponse {
+            status_code: 400,
+            message: format!(
+                "check interval must be between {} and {} seconds",
+                MIN_CHECK_INTERVAL_SECS, MAX_CHECK_INTERVAL_SECS
+            ),
+        });
+    }
+
     let existing_url = website_register::Entity::find()
         .filter(website_register::Column::WebsiteUrl.eq(&url))
         .one(&db)
@@ -41,9 +56,12 @@ async fn website_to_add(
         });
     }
 
+    // it is announced right away below, the scheduler takes over from the next interval
     let new_url = website_register::ActiveModel {
         website_url: Set(url.clone()),
         user_id : Set(user_id),
+        check_interval_secs: Set(check_interval_secs),
+        next_check_at: Set(Some((Utc::now() + Duration::seconds(check_interval_secs as i64)).fixed_offset())),
         ..Default::default()
     };
 
@@ -88,7 +106,12 @@ async fn website_to_add(
             
             println!("Publishing notifciation to validators via redis pubsub...");
 
-            let server_message = ServerMessage { url: url.clone(), id: website_details.id.to_string() };
+            let server_message = ServerMessage {
+                url: url.clone(),
+                id: website_details.id.to_string(),
+                run_id: None,
+                validator_ids: Vec::new(),
+            };
 
             match state.pubsub.publish_to_validators(server_message).await{
                 Ok(_) => {
@@ -0,0 +1,544 @@
+use crate::entities::{custody_epoch, custody_key, share_holder};
+use crate::middleware::auth::jwt_auth_middleware;
+use crate::shamir_secret::custody::{
+    check_holder_set, create_custody_key, current_shares, holder_share_rows, key_commitments, open_ceremony_key,
+    open_submitted_share, parse_encryption_key, recover_keypair, refresh_custody_key, reshare_custody_key, seal,
+    seal_ceremony_key,
+};
+use crate::types::custody::{
+    Ceremony, CeremonyResponse, CreateKeyRequest, CustodyEpochRecord, CustodyEpochsResponse, CustodyKeyResponse,
+    CustodyState, HolderResponse,
+    HolderShare, HolderShareResponse, RecoveredKeyResponse, RegisterHolderRequest, StartCeremonyRequest,
+    SubmitShareRequest, SubmitShareResponse,
+};
+use crate::redis::shares_manager::CEREMONY_TTL_SECS;
+use crate::utils::jwt_extractor::AuthenticatedUser;
+use axum::{
+    debug_handler,
+    extract::{Extension, Path, State},
+    middleware,
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{Duration, Utc};
+use crypto_box::{aead::OsRng, SecretKey};
+use sea_orm::{sea_query::OnConflict, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use uuid::Uuid;
+
+pub fn custody_router() -> Router<CustodyState> {
+    Router::new()
+        .route("/holders", post(register_holder))
+        .route("/keys", post(create_key))
+        .route("/keys/{public_key}", get(get_key))
+        .route("/keys/{public_key}/refresh", post(refresh_key))
+        .route("/keys/{public_key}/epochs", get(get_key_epochs))
+        .route("/shares/{public_key}", get(get_holder_share))
+        .route("/ceremonies", post(start_ceremony))
+        .route("/ceremonies/{ceremony_id}/shares", post(submit_share))
+        .route("/ceremonies/{ceremony_id}/key", get(take_recovered_key))
+        .layer(middleware::from_fn(jwt_auth_middleware))
+}
+
+async fn find_key(state: &CustodyState, public_key: &str) -> Result<Option<custody_key::Model>, sea_orm::DbErr> {
+    custody_key::Entity::find()
+        .filter(custody_key::Column::PublicKey.eq(public_key))
+        .one(&state.db)
+        .await
+}
+
+fn key_response(status_code: u32, message: String, key: Option<custody_key::Model>) -> Json<CustodyKeyResponse> {
+    match key {
+        Some(key) => Json(CustodyKeyResponse {
+            status_code,
+            message,
+            key_id: Some(key.id),
+            threshold: Some(key.threshold as u8),
+            epoch: Some(key.current_epoch),
+            scheme: Some(key.scheme),
+            commitments: serde_json::from_value(key.commitments).ok(),
+            public_key: Some(key.public_key),
+        }),
+        None => Json(CustodyKeyResponse {
+            status_code,
+            message,
+            key_id: None,
+            public_key: None,
+            threshold: None,
+            epoch: None,
+            scheme: None,
+            commitments: None,
+        }),
+    }
+}
+
+async fn find_holder(state: &CustodyState, user_id: Uuid) -> Result<Option<share_holder::Model>, sea_orm::DbErr> {
+    share_holder::Entity::find()
+        .filter(share_holder::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await
+}
+
+// Registers (or rotates) the key the caller's shares get sealed to. Rotating only affects keys
+// split afterwards, shares already issued stay sealed to the old key.
+#[debug_handler]
+async fn register_holder(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Json(request): Json<RegisterHolderRequest>,
+) -> Json<HolderResponse> {
+    if parse_encryption_key(&request.encryption_public_key).is_err() {
+        return Json(HolderResponse {
+            status_code: 400,
+            message: "encryption_public_key must be a base64 X25519 public key".to_string(),
+            holder_id: None,
+        });
+    }
+
+    let holder = share_holder::ActiveModel {
+        user_id: Set(user_id),
+        encryption_public_key: Set(request.encryption_public_key),
+        ..Default::default()
+    };
+    let result = share_holder::Entity::insert(holder)
+        .on_conflict(
+            OnConflict::column(share_holder::Column::UserId)
+                .update_column(share_holder::Column::EncryptionPublicKey)
+                .to_owned(),
+        )
+        .exec_with_returning(&state.db)
+        .await;
+
+    match result {
+        Ok(holder) => Json(HolderResponse {
+            status_code: 200,
+            message: "Share holder registered".to_string(),
+            holder_id: Some(holder.id),
+        }),
+        Err(e) => Json(HolderResponse {
+            status_code: 500,
+            message: format!("Database error occured : {}", e),
+            holder_id: None,
+        }),
+    }
+}
+
+#[debug_handler]
+async fn create_key(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Json(request): Json<CreateKeyRequest>,
+) -> Json<CustodyKeyResponse> {
+    if !state.admins.contains(&user_id) {
+        return key_response(403, "Only custody admins can create keys".to_string(), None);
+    }
+
+    let scheme = request.scheme.as_deref().unwrap_or(custody_key::SCHEME_SEED);
+    match create_custody_key(&state.db, &request.holder_ids, request.threshold, scheme, user_id).await {
+        Ok(key) => key_response(
+            200,
+            format!("Key split into {} shares, {} needed to recover", key.total_shares, key.threshold),
+            Some(key),
+        ),
+        Err(e) => key_response(400, format!("Error creating custody key : {}", e), None),
+    }
+}
+
+// Public record of a custody key, the commitments let any holder check their share on receipt
+#[debug_handler]
+async fn get_key(
+    State(state): State<CustodyState>,
+    Path(public_key): Path<String>,
+) -> Json<CustodyKeyResponse> {
+    match find_key(&state, &public_key).await {
+        Ok(Some(key)) => key_response(200, format!("Custody key {}", public_key), Some(key)),
+        Ok(None) => key_response(404, format!("No custody key {}", public_key), None),
+        Err(e) => key_response(500, format!("Database error occured : {}", e), None),
+    }
+}
+
+// Starts a new epoch with rerandomized shares, holders pick up their delta from /shares
+#[debug_handler]
+async fn refresh_key(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(public_key): Path<String>,
+) -> Json<CustodyKeyResponse> {
+    if !state.admins.contains(&user_id) {
+        return key_response(403, "Only custody admins can refresh keys".to_string(), None);
+    }
+
+    let key = match find_key(&state, &public_key).await {
+        Ok(Some(key)) => key,
+        Ok(None) => return key_response(404, format!("No custody key {}", public_key), None),
+        Err(e) => return key_response(500, format!("Database error occured : {}", e), None),
+    };
+    match refresh_custody_key(&state.db, &key, user_id).await {
+        Ok(key) => key_response(200, format!("Shares of {} refreshed to epoch {}", public_key, key.current_epoch), Some(key)),
+        Err(e) => key_response(400, format!("Error refreshing custody key : {}", e), None),
+    }
+}
+
+#[debug_handler]
+async fn get_key_epochs(
+    State(state): State<CustodyState>,
+    Path(public_key): Path<String>,
+) -> Json<CustodyEpochsResponse> {
+    let key = match find_key(&state, &public_key).await {
+        Ok(Some(key)) => key,
+        Ok(None) => {
+            return Json(CustodyEpochsResponse {
+                status_code: 404,
+                message: format!("No custody key {}", public_key),
+                epochs: Vec::new(),
+            })
+        }
+        Err(e) => {
+            return Json(CustodyEpochsResponse {
+                status_code: 500,
+                message: format!("Database error occured : {}", e),
+                epochs: Vec::new(),
+            })
+        }
+    };
+
+    match custody_epoch::Entity::find()
+        .filter(custody_epoch::Column::KeyId.eq(key.id))
+        .order_by_asc(custody_epoch::Column::Epoch)
+        .all(&state.db)
+        .await
+    {
+        Ok(epochs) => Json(CustodyEpochsResponse {
+            status_code: 200,
+            message: format!("{} epochs of {}", epochs.len(), public_key),
+            epochs: epochs
+                .into_iter()
+                .map(|epoch| CustodyEpochRecord {
+                    epoch: epoch.epoch,
+                    kind: epoch.kind,
+                    threshold: epoch.threshold as u8,
+                    holder_ids: serde_json::from_value(epoch.holder_ids).unwrap_or_default(),
+                    commitments: serde_json::from_value(epoch.commitments).unwrap_or_default(),
+                    initiated_by: epoch.initiated_by,
+                    created_at: epoch.created_at.into(),
+                })
+                .collect(),
+        }),
+        Err(e) => Json(CustodyEpochsResponse {
+            status_code: 500,
+            message: format!("Database error occured : {}", e),
+            epochs: Vec::new(),
+        }),
+    }
+}
+
+#[debug_handler]
+async fn get_holder_share(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(public_key): Path<String>,
+) -> Json<HolderShareResponse> {
+    let lookup = async {
+        let Some(holder) = find_holder(&state, user_id).await? else {
+            return Ok(None);
+        };
+        let Some(key) = find_key(&state, &public_key).await? else {
+            return Ok(None);
+        };
+        let rows = holder_share_rows(&state.db, &key, holder.id).await?;
+        Ok::<_, anyhow::Error>(rows.map(|rows| (key, rows)))
+    };
+
+    match lookup.await {
+        Ok(Some((key, mut rows))) => {
+            let base = rows.remove(0);
+            Json(HolderShareResponse {
+                status_code: 200,
+                message: format!("Share of {}", public_key),
+                share: Some(HolderShare {
+                    public_key: key.public_key,
+                    share_index: base.share_index as u8,
+                    threshold: key.threshold as u8,
+                    epoch: key.current_epoch,
+                    ciphertext: base.ciphertext,
+                    deltas: rows.into_iter().map(|row| row.ciphertext).collect(),
+                    commitments: serde_json::from_value(key.commitments).unwrap_or_default(),
+                }),
+            })
+        }
+        Ok(None) => Json(HolderShareResponse {
+            status_code: 404,
+            message: format!("No share of {} for this holder", public_key),
+            share: None,
+        }),
+        Err(e) => Json(HolderShareResponse {
+            status_code: 500,
+            message: format!("Database error occured : {}", e),
+            share: None,
+        }),
+    }
+}
+
+// Opens a recovery for a key. Holders decrypt their share locally and re-seal it to the returned
+// ceremony key, whose secret half is only stored sealed to the server's seal key. A recovered key
+// is sealed to the starting admin's registered encryption key, so they must be registered first.
+#[debug_handler]
+async fn start_ceremony(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Json(request): Json<StartCeremonyRequest>,
+) -> Json<CeremonyResponse> {
+    if !state.admins.contains(&user_id) {
+        return Json(CeremonyResponse {
+            status_code: 403,
+            message: "Only custody admins can start a recovery".to_string(),
+            ceremony: None,
+        });
+    }
+
+    let key = match find_key(&state, &request.public_key).await {
+        Ok(Some(key)) => key,
+        Ok(None) => {
+            return Json(CeremonyResponse {
+                status_code: 404,
+                message: format!("No custody key {}", request.public_key),
+                ceremony: None,
+            })
+        }
+        Err(e) => {
+            return Json(CeremonyResponse {
+                status_code: 500,
+                message: format!("Database error occured : {}", e),
+                ceremony: None,
+            })
+        }
+    };
+
+    // rebuilding a threshold signing key would undo the reason it was split
+    if key.scheme != custody_key::SCHEME_SEED {
+        return Json(CeremonyResponse {
+            status_code: 400,
+            message: format!("{} is a {} key, it signs by threshold and is never reconstructed", key.public_key, key.scheme),
+            ceremony: None,
+        });
+    }
+
+    if let Some(target) = &request.reshare {
+        if let Err(e) = check_holder_set(&state.db, &target.holder_ids, target.threshold).await {
+            return Json(CeremonyResponse {
+                status_code: 400,
+                message: format!("Invalid reshare target : {}", e),
+                ceremony: None,
+            });
+        }
+    } else {
+        match find_holder(&state, user_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return Json(CeremonyResponse {
+                    status_code: 400,
+                    message: "Register an encryption key at /custody/holders first, the recovered key is sealed to it".to_string(),
+                    ceremony: None,
+                })
+            }
+            Err(e) => {
+                return Json(CeremonyResponse {
+                    status_code: 500,
+                    message: format!("Database error occured : {}", e),
+                    ceremony: None,
+                })
+            }
+        }
+    }
+
+    let secret_key = SecretKey::generate(&mut OsRng);
+    let sealed_key = match seal_ceremony_key(&state.seal_key, &secret_key) {
+        Ok(sealed_key) => sealed_key,
+        Err(e) => {
+            return Json(CeremonyResponse {
+                status_code: 500,
+                message: format!("Error sealing ceremony key : {}", e),
+                ceremony: None,
+            })
+        }
+    };
+    let ceremony = Ceremony {
+        ceremony_id: Uuid::new_v4().to_string(),
+        key_id: key.id,
+        public_key: key.public_key,
+        threshold: key.threshold as u8,
+        ceremony_public_key: STANDARD.encode(secret_key.public_key().as_bytes()),
+        started_by: user_id,
+        expires_at: Utc::now() + Duration::seconds(CEREMONY_TTL_SECS as i64),
+        epoch: key.current_epoch,
+        reshare: request.reshare,
+    };
+
+    // the key goes in first, a ceremony is never visible without it
+    let started = match state.share_storage.store_ceremony_key(&ceremony.ceremony_id, &sealed_key).await {
+        Ok(_) => state.share_storage.start_ceremony(&ceremony).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = started {
+        return Json(CeremonyResponse {
+            status_code: 500,
+            message: format!("Error starting ceremony : {}", e),
+            ceremony: None,
+        });
+    }
+
+    Json(CeremonyResponse {
+        status_code: 200,
+        message: match ceremony.reshare {
+            Some(_) => format!("Reshare started, {} shares needed", ceremony.threshold),
+            None => format!("Recovery started, {} shares needed", ceremony.threshold),
+        },
+        ceremony: Some(ceremony),
+    })
+}
+
+#[debug_handler]
+async fn submit_share(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(ceremony_id): Path<String>,
+    Json(request): Json<SubmitShareRequest>,
+) -> Json<SubmitShareResponse> {
+    let response = |status_code: u32, message: String, collected: usize, threshold: u8, recovered: bool| {
+        Json(SubmitShareResponse { status_code, message, collected, threshold, recovered, rejected_holders: Vec::new() })
+    };
+
+    let ceremony = match state.share_storage.get_ceremony(&ceremony_id).await {
+        Ok(Some(ceremony)) => ceremony,
+        Ok(None) => return response(404, "Ceremony not found or expired".to_string(), 0, 0, false),
+        Err(e) => return response(500, format!("Error loading ceremony : {}", e), 0, 0, false),
+    };
+    let ceremony_key = match state.share_storage.get_ceremony_key(&ceremony_id).await {
+        Ok(Some(sealed_key)) => match open_ceremony_key(&state.seal_key, &sealed_key) {
+            Ok(ceremony_key) => ceremony_key,
+            Err(e) => return response(500, format!("Error opening ceremony key : {}", e), 0, ceremony.threshold, false),
+        },
+        Ok(None) => return response(404, "Ceremony not found or expired".to_string(), 0, ceremony.threshold, false),
+        Err(e) => return response(500, format!("Error loading ceremony : {}", e), 0, ceremony.threshold, false),
+    };
+
+    // a refresh or reshare since the ceremony started invalidates the shares it collects
+    let key = match custody_key::Entity::find_by_id(ceremony.key_id).one(&state.db).await {
+        Ok(Some(key)) if key.current_epoch == ceremony.epoch => key,
+        Ok(Some(_)) => return response(409, "Key moved to a new epoch, start a new ceremony".to_string(), 0, ceremony.threshold, false),
+        Ok(None) => return response(404, "Custody key no longer exists".to_string(), 0, ceremony.threshold, false),
+        Err(e) => return response(500, format!("Database error occured : {}", e), 0, ceremony.threshold, false),
+    };
+
+    // only current holders of the key may contribute, once each
+    let holder_shares = match current_shares(&state.db, &key).await {
+        Ok(shares) => shares,
+        Err(e) => return response(500, format!("Database error occured : {}", e), 0, ceremony.threshold, false),
+    };
+    let (holder, holder_share) = match find_holder(&state, user_id).await {
+        Ok(Some(holder)) => match holder_shares.iter().find(|share| share.holder_id == holder.id) {
+            Some(holder_share) => (holder, holder_share),
+            None => return response(403, "Caller holds no share of this key".to_string(), 0, ceremony.threshold, false),
+        },
+        Ok(None) => return response(403, "Caller holds no share of this key".to_string(), 0, ceremony.threshold, false),
+        Err(e) => return response(500, format!("Database error occured : {}", e), 0, ceremony.threshold, false),
+    };
+
+    // a bad share is turned away on receipt, before it can count towards the threshold
+    let verified = key_commitments(&key)
+        .and_then(|commitments| open_submitted_share(&ceremony_key, &request.ciphertext, holder_share, &commitments));
+    if let Err(e) = verified {
+        println!("Rejected share of holder {} in ceremony {} : {}", holder.id, ceremony_id, e);
+        return response(400, format!("Share failed verification : {}", e), 0, ceremony.threshold, false);
+    }
+
+    let collected = match state
+        .share_storage
+        .store_share(&ceremony_id, &holder.id.to_string(), &request.ciphertext)
+        .await
+    {
+        Ok(collected) => collected,
+        Err(e) => return response(500, format!("Error storing share : {}", e), 0, ceremony.threshold, false),
+    };
+    if collected < ceremony.threshold as usize {
+        return response(200, "Share received".to_string(), collected, ceremony.threshold, false);
+    }
+
+    let submitted = match state.share_storage.retrieve_shares(&ceremony_id).await {
+        Ok(submitted) => submitted,
+        Err(e) => return response(500, format!("Error loading shares : {}", e), collected, ceremony.threshold, false),
+    };
+    // the ceremony stays open so a holder can resubmit a share that was sealed wrongly
+    let recovery = match recover_keypair(&ceremony_key, &submitted, &holder_shares, &key) {
+        Ok(recovery) => recovery,
+        Err(e) => return response(400, format!("Recovery failed : {}", e), collected, ceremony.threshold, false),
+    };
+
+    // a reshare only needs the key long enough to deal it again, it is never kept
+    let message = match &ceremony.reshare {
+        Some(target) => {
+            match reshare_custody_key(&state.db, &key, &recovery.keypair, &target.holder_ids, target.threshold, ceremony.started_by).await {
+                Ok(key) => format!("Reshared {} as {}-of-{} in epoch {}", key.public_key, key.threshold, key.total_shares, key.current_epoch),
+                Err(e) => return response(500, format!("Reshare failed : {}", e), collected, ceremony.threshold, false),
+            }
+        }
+        None => {
+            // only the admin who started the recovery can open it, and only until they pick it up
+            let sealed = match find_holder(&state, ceremony.started_by).await {
+                Ok(Some(admin)) => parse_encryption_key(&admin.encryption_public_key)
+                    .and_then(|admin_key| seal(&admin_key, &recovery.keypair.to_bytes())),
+                Ok(None) => Err(anyhow::anyhow!("admin {} has no registered encryption key", ceremony.started_by)),
+                Err(e) => Err(e.into()),
+            };
+            let stored = match sealed {
+                Ok(sealed) => state.share_storage.store_recovered_key(&ceremony_id, &ceremony.started_by.to_string(), &sealed).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = stored {
+                return response(500, format!("Error handing over recovered key : {}", e), collected, ceremony.threshold, false);
+            }
+            format!("Recovered {}, the starting admin can fetch it sealed to their key", key.public_key)
+        }
+    };
+
+    if let Err(e) = state.share_storage.close_ceremony(&ceremony_id).await {
+        eprintln!("Failed to close ceremony {} : {}", ceremony_id, e);
+    }
+    let recovered = ceremony.reshare.is_none();
+    println!("{} in ceremony {}", message, ceremony_id);
+    Json(SubmitShareResponse {
+        status_code: 200,
+        message,
+        collected,
+        threshold: ceremony.threshold,
+        recovered,
+        rejected_holders: recovery.rejected_holders,
+    })
+}
+
+// Hands a recovered key to the admin who started its recovery, sealed to their encryption key.
+// It can be fetched once, the server keeps no copy afterwards.
+#[debug_handler]
+async fn take_recovered_key(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(ceremony_id): Path<String>,
+) -> Json<RecoveredKeyResponse> {
+    match state.share_storage.take_recovered_key(&ceremony_id, &user_id.to_string()).await {
+        Ok(Some(ciphertext)) => Json(RecoveredKeyResponse {
+            status_code: 200,
+            message: format!("Recovered key of ceremony {}", ceremony_id),
+            ciphertext: Some(ciphertext),
+        }),
+        Ok(None) => Json(RecoveredKeyResponse {
+            status_code: 404,
+            message: "No recovered key for this caller, it was already fetched or expired".to_string(),
+            ciphertext: None,
+        }),
+        Err(e) => Json(RecoveredKeyResponse {
+            status_code: 500,
+            message: format!("Error loading recovered key : {}", e),
+            ciphertext: None,
+        }),
+    }
+}
@@ -0,0 +1,79 @@
+use crate::entities::reward_epoch;
+use crate::middleware::auth::jwt_auth_middleware;
+use crate::rewards::epoch::{build_epoch, epoch_proof};
+use crate::types::epoch::{BuildEpochRequest, BuildEpochResponse, BuiltEpochSummary, EpochProofResponse};
+use crate::utils::jwt_extractor::{is_reward_admin, AuthenticatedUser};
+use axum::{
+    extract::{Extension, Path, State},
+    middleware,
+    routing::{get, post},
+    Json, Router,
+};
+use sea_orm::{DatabaseConnection, EntityTrait, QueryOrder};
+use solana_sdk::hash::Hash;
+
+pub fn epoch_router() -> Router<DatabaseConnection> {
+    Router::new()
+        .route("/build", post(build_next_epoch).layer(middleware::from_fn(jwt_auth_middleware)))
+        .route("/{epoch_id}/proof/{wallet_address}", get(get_epoch_proof))
+}
+
+// Collects the pending rewards into a new epoch, admins only. The response carries what the
+// operator posts with `post_epoch`.
+#[axum::debug_handler]
+async fn build_next_epoch(
+    State(db): State<DatabaseConnection>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Json(request): Json<BuildEpochRequest>,
+) -> Json<BuildEpochResponse> {
+    let response = |status_code: u32, message: String| Json(BuildEpochResponse { status_code, message, epoch: None });
+    if !is_reward_admin(user_id) {
+        return response(403, "Only reward admins can build epochs".to_string());
+    }
+
+    let epoch_id = match request.epoch_id {
+        Some(epoch_id) => epoch_id,
+        None => match reward_epoch::Entity::find().order_by_desc(reward_epoch::Column::EpochId).one(&db).await {
+            Ok(latest) => latest.map(|epoch| epoch.epoch_id as u64 + 1).unwrap_or_default(),
+            Err(db_err) => return response(500, format!("Database error occured : {}", db_err)),
+        },
+    };
+    match build_epoch(&db, epoch_id, request.expiry_slot).await {
+        Ok(built) => Json(BuildEpochResponse {
+            status_code: 200,
+            message: format!("Epoch {} built with {} leaves", built.epoch_id, built.leaves.len()),
+            epoch: Some(BuiltEpochSummary {
+                epoch_id: built.epoch_id,
+                merkle_root: Hash::new_from_array(built.merkle_root).to_string(),
+                total_amount: built.total_amount,
+                leaf_count: built.leaves.len() as u32,
+                expiry_slot: built.expiry_slot,
+            }),
+        }),
+        Err(e) => response(400, format!("Error building epoch : {}", e)),
+    }
+}
+
+#[axum::debug_handler]
+async fn get_epoch_proof(
+    State(db): State<DatabaseConnection>,
+    Path((epoch_id, wallet_address)): Path<(u64, String)>,
+) -> Json<EpochProofResponse> {
+    match epoch_proof(&db, epoch_id, &wallet_address).await {
+        Ok(Some(proof)) => Json(EpochProofResponse {
+            status_code: 200,
+            message: format!("Proof for {} in epoch {}", wallet_address, epoch_id),
+            proof: Some(proof),
+        }),
+        Ok(None) => Json(EpochProofResponse {
+            status_code: 404,
+            message: format!("No reward for {} in epoch {}", wallet_address, epoch_id),
+            proof: None,
+        }),
+        Err(e) => Json(EpochProofResponse {
+            status_code: 500,
+            message: format!("Error building proof : {}", e),
+            proof: None,
+        }),
+    }
+}
\ No newline at end of file
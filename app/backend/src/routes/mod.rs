@@ -3,4 +3,8 @@ pub mod add_website;
 pub mod validator;
 pub mod website_performace;
 pub mod notification;
-pub mod sse;
\ No newline at end of file
+pub mod sse;
+pub mod epoch;
+pub mod custody;
+pub mod threshold;
+pub mod rewards;
@@ -0,0 +1,223 @@
+use crate::entities::{reward, reward_summary, validator};
+use crate::middleware::auth::jwt_auth_middleware;
+use crate::rewards::engine::{RewardConfig, RewardEngine};
+use crate::types::reward::{
+    DailyEarning, DailyEarningsQuery, DailyEarningsResponse, RewardHistoryItem, RewardHistoryQuery,
+    RewardHistoryResponse, RewardPreviewResponse, RewardSummaryResponse, RewardTypeCount, StatusTotal,
+};
+use crate::utils::jwt_extractor::{is_reward_admin, AuthenticatedUser};
+use axum::{
+    extract::{Extension, Query, State},
+    middleware,
+    routing::get,
+    Json, Router,
+};
+use chrono::{Duration, Utc};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+pub fn rewards_router() -> Router<DatabaseConnection> {
+    Router::new()
+        .route("/preview", get(preview_rewards))
+        .route("/summary", get(get_summary))
+        .route("/daily", get(get_daily_earnings))
+        .route("/history", get(get_history))
+        .layer(middleware::from_fn(jwt_auth_middleware))
+}
+
+// Rewards are credited to the validator's user, the JWT has to carry the validator it belongs to
+async fn validator_user(db: &DatabaseConnection, authenticated_user: &AuthenticatedUser) -> Result<validator::Model, (u32, String)> {
+    let Some(validator_id) = authenticated_user.validator_id else {
+        return Err((403, "Token is not bound to a validator".to_string()));
+    };
+    match validator::Entity::find_by_id(validator_id).one(db).await {
+        Ok(Some(validator)) if validator.user_id == authenticated_user.user_id => Ok(validator),
+        Ok(_) => Err((404, "Validator not found".to_string())),
+        Err(db_err) => Err((500, format!("Database error occured : {}", db_err))),
+    }
+}
+
+async fn summary_rows(db: &DatabaseConnection, user_id: Uuid) -> Result<Vec<reward_summary::Model>, sea_orm::DbErr> {
+    reward_summary::Entity::find()
+        .filter(reward_summary::Column::UserId.eq(user_id))
+        .all(db)
+        .await
+}
+
+// Totals by status and counts by reward type for the caller's validator
+#[axum::debug_handler]
+async fn get_summary(
+    State(db): State<DatabaseConnection>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+) -> Json<RewardSummaryResponse> {
+    let response = |status_code: u32, message: String| {
+        Json(RewardSummaryResponse { status_code, message, validator_id: None, total_amount: 0, by_status: Vec::new(), by_type: Vec::new() })
+    };
+
+    let validator = match validator_user(&db, &authenticated_user).await {
+        Ok(validator) => validator,
+        Err((status_code, message)) => return response(status_code, message),
+    };
+    let rows = match summary_rows(&db, validator.user_id).await {
+        Ok(rows) => rows,
+        Err(db_err) => return response(500, format!("Database error occured : {}", db_err)),
+    };
+
+    // the statuses validators care about are always listed, even at zero
+    let mut by_status: BTreeMap<String, (i64, i64)> = [reward::STATUS_PENDING, reward::STATUS_PAID, reward::STATUS_FAILED]
+        .into_iter()
+        .map(|status| (status.to_string(), (0, 0)))
+        .collect();
+    let mut by_type: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+    for row in &rows {
+        let status = by_status.entry(row.status.clone()).or_default();
+        status.0 += row.reward_count;
+        status.1 += row.total_amount;
+        let reward_type = by_type.entry(row.reward_type.clone()).or_default();
+        reward_type.0 += row.reward_count;
+        reward_type.1 += row.total_amount;
+    }
+
+    Json(RewardSummaryResponse {
+        status_code: 200,
+        message: format!("Reward summary for validator {}", validator.id),
+        validator_id: Some(validator.id),
+        total_amount: rows.iter().map(|row| row.total_amount).sum(),
+        by_status: by_status
+            .into_iter()
+            .map(|(status, (reward_count, total_amount))| StatusTotal { status, reward_count, total_amount })
+            .collect(),
+        by_type: by_type
+            .into_iter()
+            .map(|(reward_type, (reward_count, total_amount))| RewardTypeCount { reward_type, reward_count, total_amount })
+            .collect(),
+    })
+}
+
+// Everything credited per day over the last `days` days, whatever its payout status
+#[axum::debug_handler]
+async fn get_daily_earnings(
+    State(db): State<DatabaseConnection>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Query(query): Query<DailyEarningsQuery>,
+) -> Json<DailyEarningsResponse> {
+    let response = |status_code: u32, message: String| Json(DailyEarningsResponse { status_code, message, days: Vec::new() });
+
+    let validator = match validator_user(&db, &authenticated_user).await {
+        Ok(validator) => validator,
+        Err((status_code, message)) => return response(status_code, message),
+    };
+    let days = query.days.unwrap_or(30).clamp(1, 366);
+    let since = (Utc::now() - Duration::days(days as i64 - 1)).date_naive();
+
+    let rows = match reward_summary::Entity::find()
+        .filter(reward_summary::Column::UserId.eq(validator.user_id))
+        .filter(reward_summary::Column::Day.gte(since))
+        .all(&db)
+        .await
+    {
+        Ok(rows) => rows,
+        Err(db_err) => return response(500, format!("Database error occured : {}", db_err)),
+    };
+
+    let mut per_day: BTreeMap<chrono::NaiveDate, (i64, i64)> = BTreeMap::new();
+    for row in rows {
+        let day = per_day.entry(row.day).or_default();
+        day.0 += row.reward_count;
+        day.1 += row.total_amount;
+    }
+
+    Json(DailyEarningsResponse {
+        status_code: 200,
+        message: format!("Earnings for the last {} days", days),
+        days: per_day
+            .into_iter()
+            .map(|(day, (reward_count, total_amount))| DailyEarning { day, reward_count, total_amount })
+            .collect(),
+    })
+}
+
+// The caller's rewards, newest first
+#[axum::debug_handler]
+async fn get_history(
+    State(db): State<DatabaseConnection>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Query(query): Query<RewardHistoryQuery>,
+) -> Json<RewardHistoryResponse> {
+    let page = query.page.unwrap_or(0);
+    let per_page = query.per_page.unwrap_or(20).clamp(1, 100);
+    let response = |status_code: u32, message: String| {
+        Json(RewardHistoryResponse { status_code, message, page, per_page, total_items: 0, total_pages: 0, rewards: Vec::new() })
+    };
+
+    let validator = match validator_user(&db, &authenticated_user).await {
+        Ok(validator) => validator,
+        Err((status_code, message)) => return response(status_code, message),
+    };
+
+    let paginator = reward::Entity::find()
+        .filter(reward::Column::UserId.eq(validator.user_id))
+        .order_by_desc(reward::Column::CreatedAt)
+        .order_by_desc(reward::Column::Id)
+        .paginate(&db, per_page);
+    let totals = match paginator.num_items_and_pages().await {
+        Ok(totals) => totals,
+        Err(db_err) => return response(500, format!("Database error occured : {}", db_err)),
+    };
+    let rewards = match paginator.fetch_page(page).await {
+        Ok(rewards) => rewards,
+        Err(db_err) => return response(500, format!("Database error occured : {}", db_err)),
+    };
+
+    Json(RewardHistoryResponse {
+        status_code: 200,
+        message: format!("Page {} of {}", page, totals.number_of_pages),
+        page,
+        per_page,
+        total_items: totals.number_of_items,
+        total_pages: totals.number_of_pages,
+        rewards: rewards
+            .into_iter()
+            .map(|reward| RewardHistoryItem {
+                id: reward.id,
+                amount: reward.amount,
+                reward_type: reward.reward_type,
+                contribution_id: reward.contribution_id,
+                status: reward.status,
+                transaction_hash: reward.transaction_hash,
+                epoch_id: reward.epoch_id,
+                created_at: reward.created_at,
+                processed_at: reward.processed_at,
+            })
+            .collect(),
+    })
+}
+
+// Dry run of the reward engine, reports what its next run would credit without writing anything.
+// The report covers every validator, so only reward admins get it.
+#[axum::debug_handler]
+async fn preview_rewards(
+    State(db): State<DatabaseConnection>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+) -> Json<RewardPreviewResponse> {
+    if !is_reward_admin(user_id) {
+        return Json(RewardPreviewResponse {
+            status_code: 403,
+            message: "Only reward admins can preview reward runs".to_string(),
+            run: None,
+        });
+    }
+    match RewardEngine::new(db, RewardConfig::from_env()).run_once(true).await {
+        Ok(run) => Json(RewardPreviewResponse {
+            status_code: 200,
+            message: format!("{} rewards totalling {} lamports would be credited", run.credits.len(), run.total_amount),
+            run: Some(run),
+        }),
+        Err(e) => Json(RewardPreviewResponse {
+            status_code: 500,
+            message: format!("Error running reward engine : {}", e),
+            run: None,
+        }),
+    }
+}
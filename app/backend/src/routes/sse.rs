@@ -55,6 +55,13 @@ async fn create_notification_stream(
 
     let redis_stream = pubsub.subscribe_to_notifications().await?;
 
+    // scheduled runs only go to the validators they were assigned to
+    let validator_key = validator_id.to_string();
+    let redis_stream = redis_stream.filter(move |server_message| {
+        let for_validator = server_message.validator_ids.is_empty() || server_message.validator_ids.contains(&validator_key);
+        std::future::ready(for_validator)
+    });
+
     let sse_stream = redis_stream.map(move |server_message| {
         println!(
             "Forwarding notification {:?} to validator {}",
@@ -0,0 +1,797 @@
+use crate::entities::{custody_key, key_share, share_holder};
+use crate::middleware::auth::jwt_auth_middleware;
+use crate::payout::instruction::{withdrawal_instructions_with_signatures, WithdrawalMessage};
+use crate::redis::shares_manager::CEREMONY_TTL_SECS;
+use crate::shamir_secret::custody::{check_holder_set, current_shares, key_commitments, store_dkg_key};
+use crate::shamir_secret::frost::{self, parse_commitment, parse_signature_share, AggregateError};
+use crate::types::custody::CustodyState;
+use crate::utils::jwt_extractor::AuthenticatedUser;
+use crate::types::threshold::{
+    ConfirmDkgRequest, DkgParticipant, DkgRound1Entry, DkgSession, DkgSessionResponse, DkgStepResponse,
+    ReceivedSubShare, Round1PackageData, SigningCommitmentData, SigningSession, SigningSessionResponse,
+    SigningStepResponse, StartDkgRequest, StartSigningRequest, SubmitCommitmentRequest, SubmitRound2Request,
+    SubmitSignatureShareRequest, WithdrawalRequest,
+};
+use axum::{
+    debug_handler,
+    extract::{Extension, Path, State},
+    middleware,
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{Duration, Utc};
+use frost_ed25519::keys::dkg::round1::Package as Round1Package;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::str::FromStr;
+use uuid::Uuid;
+
+// Holders sign and generate keys on their own devices, these routes only relay their messages,
+// check what can be checked publicly and aggregate the result.
+pub fn threshold_router() -> Router<CustodyState> {
+    Router::new()
+        .route("/signing", post(start_signing))
+        .route("/signing/{session_id}", get(get_signing))
+        .route("/signing/{session_id}/commitments", post(submit_commitment))
+        .route("/signing/{session_id}/shares", post(submit_signature_share))
+        .route("/payouts/{public_key}", get(get_payout_signing))
+        .route("/dkg", post(start_dkg))
+        .route("/dkg/{session_id}", get(get_dkg))
+        .route("/dkg/{session_id}/round1", post(submit_round1))
+        .route("/dkg/{session_id}/round2", post(submit_round2))
+        .route("/dkg/{session_id}/confirm", post(confirm_dkg))
+        .layer(middleware::from_fn(jwt_auth_middleware))
+}
+
+const SIGNING: &str = "signing";
+const DKG: &str = "dkg";
+pub const PAYOUT: &str = "payout";
+
+fn withdrawal_message(withdrawal: &WithdrawalRequest) -> Result<WithdrawalMessage, anyhow::Error> {
+    Ok(WithdrawalMessage {
+        vault: Pubkey::from_str(&withdrawal.vault)?,
+        recipient: Pubkey::from_str(&withdrawal.recipient)?,
+        amount: withdrawal.amount,
+        nonce: withdrawal.nonce,
+        expiry_slot: withdrawal.expiry_slot,
+    })
+}
+
+// The ed25519 check carrying the aggregated signature followed by the vault withdrawal, as base64
+// bincode. Whoever pays the fee wraps them in a transaction, the group key signs nothing else.
+fn withdrawal_instructions(state: &CustodyState, session: &SigningSession, signature: &Signature) -> Result<String, anyhow::Error> {
+    let signer = Pubkey::from_str(&session.public_key)?;
+    let fee_account = Pubkey::from_str(&session.withdrawal.fee_account)?;
+    let message = withdrawal_message(&session.withdrawal)?;
+    let signature: [u8; 64] = signature.as_ref().try_into()?;
+    let instructions = withdrawal_instructions_with_signatures(&state.program_id, &[(signer, signature)], &fee_account, &message)
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(STANDARD.encode(bincode::serialize(&instructions)?))
+}
+
+// The caller's share row for the key's current epoch, if it holds one
+async fn caller_share(
+    state: &CustodyState,
+    user_id: Uuid,
+    key: &custody_key::Model,
+) -> Result<Option<key_share::Model>, anyhow::Error> {
+    let Some(holder) = share_holder::Entity::find()
+        .filter(share_holder::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    Ok(current_shares(&state.db, key).await?.into_iter().find(|share| share.holder_id == holder.id))
+}
+
+// Loads a signing session together with its key, failing once the key has moved to another epoch
+async fn load_signing(
+    state: &CustodyState,
+    session_id: &str,
+) -> Result<Option<(SigningSession, custody_key::Model)>, anyhow::Error> {
+    let Some(session) = state.share_storage.get_session::<SigningSession>(SIGNING, session_id).await? else {
+        return Ok(None);
+    };
+    let key = custody_key::Entity::find_by_id(session.key_id)
+        .one(&state.db)
+        .await?
+        .filter(|key| key.current_epoch == session.epoch);
+    Ok(key.map(|key| (session, key)))
+}
+
+async fn signing_commitments(state: &CustodyState, session_id: &str) -> Result<Vec<SigningCommitmentData>, anyhow::Error> {
+    let mut commitments: Vec<SigningCommitmentData> = state
+        .share_storage
+        .entries(SIGNING, session_id, "commitments")
+        .await?
+        .into_values()
+        .map(|json| serde_json::from_str(&json))
+        .collect::<Result<_, _>>()?;
+    commitments.sort_by_key(|commitment| commitment.index);
+    Ok(commitments)
+}
+
+async fn signing_signers(state: &CustodyState, session_id: &str) -> Result<Vec<u8>, anyhow::Error> {
+    match state.share_storage.get_value(SIGNING, session_id, "signers").await? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+// Opens a signing round for a withdrawal under a FROST custody key. Only withdrawal messages can be
+// signed, so the session can't be used to get arbitrary bytes signed by the operator key.
+#[debug_handler]
+async fn start_signing(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Json(request): Json<StartSigningRequest>,
+) -> Json<SigningSessionResponse> {
+    let response = |status_code: u32, message: String, session: Option<SigningSession>| {
+        Json(SigningSessionResponse {
+            status_code,
+            message,
+            session,
+            signers: Vec::new(),
+            commitments: Vec::new(),
+            signature: None,
+            instructions: None,
+        })
+    };
+
+    if !state.admins.contains(&user_id) {
+        return response(403, "Only custody admins can start signing".to_string(), None);
+    }
+
+    let key = match custody_key::Entity::find()
+        .filter(custody_key::Column::PublicKey.eq(&request.public_key))
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(key)) if key.scheme == custody_key::SCHEME_FROST => key,
+        Ok(Some(_)) => return response(400, format!("{} is not a threshold signing key", request.public_key), None),
+        Ok(None) => return response(404, format!("No custody key {}", request.public_key), None),
+        Err(e) => return response(500, format!("Database error occured : {}", e), None),
+    };
+    let message = match withdrawal_message(&request.withdrawal).and_then(|message| {
+        Pubkey::from_str(&request.withdrawal.fee_account)?;
+        Ok(borsh::to_vec(&message)?)
+    }) {
+        Ok(message) => message,
+        Err(e) => return response(400, format!("Invalid withdrawal : {}", e), None),
+    };
+
+    match open_signing_session(&state, key, request.withdrawal, &message, user_id).await {
+        Ok(session) => response(200, format!("Signing started, {} signers needed", session.threshold), Some(session)),
+        Err(e) => response(500, format!("Error starting signing : {}", e), None),
+    }
+}
+
+// Stores a signing session over message, the borsh bytes of withdrawal, for the key's holders to
+// pick up. The payout service opens its sessions through here as well.
+pub async fn open_signing_session(
+    state: &CustodyState,
+    key: custody_key::Model,
+    withdrawal: WithdrawalRequest,
+    message: &[u8],
+    started_by: Uuid,
+) -> Result<SigningSession, anyhow::Error> {
+    let session = SigningSession {
+        session_id: Uuid::new_v4().to_string(),
+        key_id: key.id,
+        public_key: key.public_key,
+        threshold: key.threshold as u8,
+        epoch: key.current_epoch,
+        withdrawal,
+        message: STANDARD.encode(message),
+        started_by,
+        expires_at: Utc::now() + Duration::seconds(CEREMONY_TTL_SECS as i64),
+    };
+    state.share_storage.put_session(SIGNING, &session.session_id, &session).await?;
+    println!("Started signing session {} for {}", session.session_id, session.public_key);
+    Ok(session)
+}
+
+// The session and its aggregated signature once the signers are done, None if it expired
+pub async fn signing_result(
+    state: &CustodyState,
+    session_id: &str,
+) -> Result<Option<(SigningSession, Option<Signature>)>, anyhow::Error> {
+    let Some((session, _)) = load_signing(state, session_id).await? else {
+        return Ok(None);
+    };
+    let signature = match state.share_storage.get_value(SIGNING, session_id, "signature").await? {
+        Some(signature) => Some(Signature::from_str(&signature)?),
+        None => None,
+    };
+    Ok(Some((session, signature)))
+}
+
+#[debug_handler]
+async fn get_signing(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(session_id): Path<String>,
+) -> Json<SigningSessionResponse> {
+    signing_response(&state, user_id, &session_id).await
+}
+
+// The signing session the payout service has open for a withdrawal under the key, so its holders
+// can find it
+#[debug_handler]
+async fn get_payout_signing(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(public_key): Path<String>,
+) -> Json<SigningSessionResponse> {
+    match state.share_storage.get_session::<String>(PAYOUT, &public_key).await {
+        Ok(Some(session_id)) => signing_response(&state, user_id, &session_id).await,
+        Ok(None) => signing_error(404, "No payout waiting for signatures".to_string()),
+        Err(e) => signing_error(500, format!("Error loading payout signing : {}", e)),
+    }
+}
+
+fn signing_error(status_code: u32, message: String) -> Json<SigningSessionResponse> {
+    Json(SigningSessionResponse {
+        status_code,
+        message,
+        session: None,
+        signers: Vec::new(),
+        commitments: Vec::new(),
+        signature: None,
+        instructions: None,
+    })
+}
+
+async fn signing_response(state: &CustodyState, user_id: Uuid, session_id: &str) -> Json<SigningSessionResponse> {
+    let (session, key) = match load_signing(state, session_id).await {
+        Ok(Some(loaded)) => loaded,
+        Ok(None) => return signing_error(404, "Signing session not found or expired".to_string()),
+        Err(e) => return signing_error(500, format!("Error loading signing session : {}", e)),
+    };
+    // only the key's holders and whoever started the session get to see it
+    if session.started_by != user_id {
+        match caller_share(state, user_id, &key).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return signing_error(403, "Caller is not part of this signing session".to_string()),
+            Err(e) => return signing_error(500, format!("Database error occured : {}", e)),
+        }
+    }
+
+    let lookup = async {
+        let signers = signing_signers(state, session_id).await?;
+        let commitments = signing_commitments(state, session_id)
+            .await?
+            .into_iter()
+            .filter(|commitment| signers.contains(&commitment.index))
+            .collect();
+        let signature = state.share_storage.get_value(SIGNING, session_id, "signature").await?;
+        let instructions = match &signature {
+            Some(signature) => Some(withdrawal_instructions(state, &session, &Signature::from_str(signature)?)?),
+            None => None,
+        };
+        Ok::<_, anyhow::Error>((signers, commitments, signature, instructions))
+    };
+
+    match lookup.await {
+        Ok((signers, commitments, signature, instructions)) => Json(SigningSessionResponse {
+            status_code: 200,
+            message: match (&signature, signers.is_empty()) {
+                (Some(_), _) => "Signature aggregated".to_string(),
+                (None, true) => "Waiting for signer commitments".to_string(),
+                (None, false) => "Waiting for signature shares".to_string(),
+            },
+            session: Some(session),
+            signers,
+            commitments,
+            signature,
+            instructions,
+        }),
+        Err(e) => signing_error(500, format!("Error loading signing session : {}", e)),
+    }
+}
+
+// Round 1. The first threshold holders to commit become the signing set, later commitments are
+// turned away since their nonces would never be used.
+#[debug_handler]
+async fn submit_commitment(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(session_id): Path<String>,
+    Json(request): Json<SubmitCommitmentRequest>,
+) -> Json<SigningStepResponse> {
+    let response = |status_code: u32, message: String, collected: usize, threshold: u8| {
+        Json(SigningStepResponse { status_code, message, collected, threshold, signature: None, instructions: None, rejected_signers: Vec::new() })
+    };
+
+    let (session, key) = match load_signing(&state, &session_id).await {
+        Ok(Some(loaded)) => loaded,
+        Ok(None) => return response(404, "Signing session not found or expired".to_string(), 0, 0),
+        Err(e) => return response(500, format!("Error loading signing session : {}", e), 0, 0),
+    };
+    let share = match caller_share(&state, user_id, &key).await {
+        Ok(Some(share)) => share,
+        Ok(None) => return response(403, "Caller holds no share of this key".to_string(), 0, session.threshold),
+        Err(e) => return response(500, format!("Database error occured : {}", e), 0, session.threshold),
+    };
+    let index = share.share_index as u8;
+    if let Err(e) = parse_commitment(&request.hiding, &request.binding) {
+        return response(400, format!("Invalid commitment : {}", e), 0, session.threshold);
+    }
+
+    match signing_signers(&state, &session_id).await {
+        Ok(signers) if !signers.is_empty() => {
+            return response(409, "Signing set is already complete".to_string(), signers.len(), session.threshold)
+        }
+        Ok(_) => {}
+        Err(e) => return response(500, format!("Error loading signing session : {}", e), 0, session.threshold),
+    }
+
+    let commitment = SigningCommitmentData { index, hiding: request.hiding, binding: request.binding };
+    let stored = match serde_json::to_string(&commitment) {
+        Ok(json) => state.share_storage.put_entry_once(SIGNING, &session_id, "commitments", &index.to_string(), &json).await,
+        Err(e) => Err(e.into()),
+    };
+    let collected = match stored {
+        Ok(Some(collected)) => collected,
+        Ok(None) => return response(409, "Commitment already submitted, nonces can't be replaced".to_string(), 0, session.threshold),
+        Err(e) => return response(500, format!("Error storing commitment : {}", e), 0, session.threshold),
+    };
+    if collected < session.threshold as usize {
+        return response(200, "Commitment received".to_string(), collected, session.threshold);
+    }
+
+    // whichever request sees the threshold first fixes the signing set, the rest keep it
+    let fixed = match signing_commitments(&state, &session_id).await {
+        Ok(commitments) => {
+            let signers: Vec<u8> = commitments.iter().take(session.threshold as usize).map(|commitment| commitment.index).collect();
+            match serde_json::to_string(&signers) {
+                Ok(json) => state.share_storage.set_once(SIGNING, &session_id, "signers", &json).await,
+                Err(e) => Err(e.into()),
+            }
+        }
+        Err(e) => Err(e),
+    };
+    match fixed {
+        Ok(_) => response(200, "Signing set complete, signers can submit signature shares".to_string(), collected, session.threshold),
+        Err(e) => response(500, format!("Error fixing signing set : {}", e), collected, session.threshold),
+    }
+}
+
+// Round 2. Once every signer's share is in, each is checked against the signer's verifying share
+// and the aggregate is checked as a plain ed25519 signature under the key.
+#[debug_handler]
+async fn submit_signature_share(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(session_id): Path<String>,
+    Json(request): Json<SubmitSignatureShareRequest>,
+) -> Json<SigningStepResponse> {
+    let response = |status_code: u32, message: String, collected: usize, threshold: u8| {
+        Json(SigningStepResponse { status_code, message, collected, threshold, signature: None, instructions: None, rejected_signers: Vec::new() })
+    };
+
+    let (session, key) = match load_signing(&state, &session_id).await {
+        Ok(Some(loaded)) => loaded,
+        Ok(None) => return response(404, "Signing session not found or expired".to_string(), 0, 0),
+        Err(e) => return response(500, format!("Error loading signing session : {}", e), 0, 0),
+    };
+    let signers = match signing_signers(&state, &session_id).await {
+        Ok(signers) => signers,
+        Err(e) => return response(500, format!("Error loading signing session : {}", e), 0, session.threshold),
+    };
+    let share = match caller_share(&state, user_id, &key).await {
+        Ok(Some(share)) if signers.contains(&(share.share_index as u8)) => share,
+        Ok(_) => return response(403, "Caller is not in the signing set".to_string(), 0, session.threshold),
+        Err(e) => return response(500, format!("Database error occured : {}", e), 0, session.threshold),
+    };
+    if let Err(e) = parse_signature_share(&request.signature_share) {
+        return response(400, format!("Invalid signature share : {}", e), 0, session.threshold);
+    }
+
+    let collected = match state
+        .share_storage
+        .put_entry(SIGNING, &session_id, "shares", &share.share_index.to_string(), &request.signature_share)
+        .await
+    {
+        Ok(collected) => collected,
+        Err(e) => return response(500, format!("Error storing signature share : {}", e), 0, session.threshold),
+    };
+    if collected < signers.len() {
+        return response(200, "Signature share received".to_string(), collected, session.threshold);
+    }
+
+    let aggregated = async {
+        let commitments = signing_commitments(&state, &session_id)
+            .await?
+            .into_iter()
+            .filter(|commitment| signers.contains(&commitment.index))
+            .map(|commitment| Ok::<_, anyhow::Error>((commitment.index, parse_commitment(&commitment.hiding, &commitment.binding)?)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let shares = state
+            .share_storage
+            .entries(SIGNING, &session_id, "shares")
+            .await?
+            .into_iter()
+            .map(|(index, share)| Ok::<_, anyhow::Error>((index.parse::<u8>()?, parse_signature_share(&share)?)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let message = STANDARD.decode(&session.message)?;
+        Ok::<_, anyhow::Error>((commitments, shares, message, key_commitments(&key)?))
+    };
+    let (commitments, shares, message, key_commitments) = match aggregated.await {
+        Ok(loaded) => loaded,
+        Err(e) => return response(500, format!("Error loading signature shares : {}", e), collected, session.threshold),
+    };
+
+    match frost::aggregate(&commitments, &shares, &key_commitments, &message) {
+        Ok(signature) => {
+            let signature = Signature::from(signature);
+            let verified = Pubkey::from_str(&key.public_key)
+                .map(|public_key| signature.verify(public_key.as_ref(), &message))
+                .unwrap_or(false);
+            if !verified {
+                return response(500, "Aggregated signature does not verify".to_string(), collected, session.threshold);
+            }
+            let instructions = match withdrawal_instructions(&state, &session, &signature) {
+                Ok(instructions) => instructions,
+                Err(e) => return response(500, format!("Error building withdrawal : {}", e), collected, session.threshold),
+            };
+            if let Err(e) = state.share_storage.set_once(SIGNING, &session_id, "signature", &signature.to_string()).await {
+                return response(500, format!("Error storing signature : {}", e), collected, session.threshold);
+            }
+            println!("Aggregated threshold signature for session {} under {}", session_id, key.public_key);
+            Json(SigningStepResponse {
+                status_code: 200,
+                message: "Signature aggregated".to_string(),
+                collected,
+                threshold: session.threshold,
+                signature: Some(signature.to_string()),
+                instructions: Some(instructions),
+                rejected_signers: Vec::new(),
+            })
+        }
+        // the nonces are spent, a new session without the culprits has to be started
+        Err(AggregateError::InvalidShares(rejected)) => {
+            if let Err(e) = state.share_storage.close_session(SIGNING, &session_id, &["commitments", "signers", "shares"]).await {
+                eprintln!("Failed to close signing session {} : {}", session_id, e);
+            }
+            println!("Signing session {} failed, invalid shares from {:?}", session_id, rejected);
+            Json(SigningStepResponse {
+                status_code: 400,
+                message: "Invalid signature shares, start a new session without these signers".to_string(),
+                collected,
+                threshold: session.threshold,
+                signature: None,
+                instructions: None,
+                rejected_signers: rejected,
+            })
+        }
+        Err(e) => response(500, format!("Aggregation failed : {}", e), collected, session.threshold),
+    }
+}
+
+fn dkg_participant(session: &DkgSession, holder_id: Uuid) -> Option<&DkgParticipant> {
+    session.participants.iter().find(|participant| participant.holder_id == holder_id)
+}
+
+// The DKG session and the caller's place in it
+async fn load_dkg(
+    state: &CustodyState,
+    session_id: &str,
+    user_id: Uuid,
+) -> Result<Option<(DkgSession, Option<DkgParticipant>)>, anyhow::Error> {
+    let Some(session) = state.share_storage.get_session::<DkgSession>(DKG, session_id).await? else {
+        return Ok(None);
+    };
+    let holder = share_holder::Entity::find()
+        .filter(share_holder::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await?;
+    let participant = holder.and_then(|holder| dkg_participant(&session, holder.id).cloned());
+    Ok(Some((session, participant)))
+}
+
+async fn round1_packages(state: &CustodyState, session: &DkgSession) -> Result<Vec<DkgRound1Entry>, anyhow::Error> {
+    let entries = state.share_storage.entries(DKG, &session.session_id, "round1").await?;
+    let mut packages = Vec::with_capacity(entries.len());
+    for (holder_id, json) in entries {
+        let holder_id = Uuid::parse_str(&holder_id)?;
+        let participant = dkg_participant(session, holder_id).ok_or_else(|| anyhow::anyhow!("unknown participant {}", holder_id))?;
+        packages.push(DkgRound1Entry { holder_id, index: participant.index, package: serde_json::from_str(&json)? });
+    }
+    packages.sort_by_key(|entry| entry.index);
+    Ok(packages)
+}
+
+fn parse_round1(package: &Round1PackageData, threshold: u8) -> Result<Round1Package, anyhow::Error> {
+    frost::dkg::decode_round1(&STANDARD.decode(&package.package)?, threshold)
+}
+
+// Starts a DKG among registered holders, as an alternative to a dealer splitting a key
+#[debug_handler]
+async fn start_dkg(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Json(request): Json<StartDkgRequest>,
+) -> Json<DkgSessionResponse> {
+    let response = |status_code: u32, message: String, session: Option<DkgSession>| {
+        Json(DkgSessionResponse { status_code, message, session, round1: Vec::new(), round2: Vec::new() })
+    };
+
+    if !state.admins.contains(&user_id) {
+        return response(403, "Only custody admins can start a key generation".to_string(), None);
+    }
+    if let Err(e) = check_holder_set(&state.db, &request.holder_ids, request.threshold).await {
+        return response(400, format!("Invalid holder set : {}", e), None);
+    }
+
+    let session = DkgSession {
+        session_id: Uuid::new_v4().to_string(),
+        threshold: request.threshold,
+        participants: request
+            .holder_ids
+            .iter()
+            .zip(1u8..)
+            .map(|(holder_id, index)| DkgParticipant { holder_id: *holder_id, index })
+            .collect(),
+        started_by: user_id,
+        expires_at: Utc::now() + Duration::seconds(CEREMONY_TTL_SECS as i64),
+    };
+    if let Err(e) = state.share_storage.put_session(DKG, &session.session_id, &session).await {
+        return response(500, format!("Error starting key generation : {}", e), None);
+    }
+
+    println!("Started DKG session {} among {} holders", session.session_id, session.participants.len());
+    response(
+        200,
+        format!("Key generation started, {}-of-{}", session.threshold, session.participants.len()),
+        Some(session),
+    )
+}
+
+#[debug_handler]
+async fn get_dkg(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(session_id): Path<String>,
+) -> Json<DkgSessionResponse> {
+    let lookup = async {
+        let Some((session, participant)) = load_dkg(&state, &session_id, user_id).await? else {
+            return Ok(None);
+        };
+        let round1 = round1_packages(&state, &session).await?;
+        let round2 = match &participant {
+            Some(participant) => state
+                .share_storage
+                .entries(DKG, &session_id, "round2")
+                .await?
+                .into_iter()
+                .filter_map(|(field, ciphertext)| {
+                    let (from, to) = field.split_once(':')?;
+                    (to == participant.holder_id.to_string())
+                        .then(|| Uuid::parse_str(from).ok())
+                        .flatten()
+                        .map(|from_holder_id| ReceivedSubShare { from_holder_id, ciphertext })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        Ok::<_, anyhow::Error>(Some((session, round1, round2)))
+    };
+
+    match lookup.await {
+        Ok(Some((session, round1, round2))) => Json(DkgSessionResponse {
+            status_code: 200,
+            message: format!("{} of {} round 1 packages in", round1.len(), session.participants.len()),
+            session: Some(session),
+            round1,
+            round2,
+        }),
+        Ok(None) => Json(DkgSessionResponse {
+            status_code: 404,
+            message: "Key generation not found or expired".to_string(),
+            session: None,
+            round1: Vec::new(),
+            round2: Vec::new(),
+        }),
+        Err(e) => Json(DkgSessionResponse {
+            status_code: 500,
+            message: format!("Error loading key generation : {}", e),
+            session: None,
+            round1: Vec::new(),
+            round2: Vec::new(),
+        }),
+    }
+}
+
+// Round 1: commitments to the participant's polynomial and a proof of knowledge of its constant
+// term. Participants check the proofs in dkg::part2. A proof is bound to the participant's index
+// but not to the run, so a package is only accepted once, in one run, and can't be replaced.
+#[debug_handler]
+async fn submit_round1(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(session_id): Path<String>,
+    Json(request): Json<Round1PackageData>,
+) -> Json<DkgStepResponse> {
+    let response = |status_code: u32, message: String, collected: usize, expected: usize| {
+        Json(DkgStepResponse { status_code, message, collected, expected, public_key: None })
+    };
+
+    let (session, participant) = match load_dkg(&state, &session_id, user_id).await {
+        Ok(Some((session, Some(participant)))) => (session, participant),
+        Ok(Some(_)) => return response(403, "Caller is not part of this key generation".to_string(), 0, 0),
+        Ok(None) => return response(404, "Key generation not found or expired".to_string(), 0, 0),
+        Err(e) => return response(500, format!("Error loading key generation : {}", e), 0, 0),
+    };
+    let expected = session.participants.len();
+
+    if let Err(e) = parse_round1(&request, session.threshold) {
+        return response(400, format!("Invalid round 1 package : {}", e), 0, expected);
+    }
+    match state.share_storage.remember(DKG, &request.package).await {
+        Ok(true) => {}
+        Ok(false) => return response(409, "Round 1 package was already used".to_string(), 0, expected),
+        Err(e) => return response(500, format!("Error storing round 1 package : {}", e), 0, expected),
+    }
+
+    let stored = match serde_json::to_string(&request) {
+        Ok(json) => state.share_storage.put_entry_once(DKG, &session_id, "round1", &participant.holder_id.to_string(), &json).await,
+        Err(e) => Err(e.into()),
+    };
+    match stored {
+        Ok(Some(collected)) => response(200, "Round 1 package received".to_string(), collected, expected),
+        Ok(None) => response(409, "Round 1 package already submitted, it can't be replaced".to_string(), 0, expected),
+        Err(e) => response(500, format!("Error storing round 1 package : {}", e), 0, expected),
+    }
+}
+
+// Round 2: the participant's sub shares, each sealed to its recipient. Only the recipient can open
+// and check one, against the sender's round 1 commitments.
+#[debug_handler]
+async fn submit_round2(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(session_id): Path<String>,
+    Json(request): Json<SubmitRound2Request>,
+) -> Json<DkgStepResponse> {
+    let response = |status_code: u32, message: String, collected: usize, expected: usize| {
+        Json(DkgStepResponse { status_code, message, collected, expected, public_key: None })
+    };
+
+    let (session, participant) = match load_dkg(&state, &session_id, user_id).await {
+        Ok(Some((session, Some(participant)))) => (session, participant),
+        Ok(Some(_)) => return response(403, "Caller is not part of this key generation".to_string(), 0, 0),
+        Ok(None) => return response(404, "Key generation not found or expired".to_string(), 0, 0),
+        Err(e) => return response(500, format!("Error loading key generation : {}", e), 0, 0),
+    };
+    let participants = session.participants.len();
+    let expected = participants * (participants - 1);
+
+    match state.share_storage.entries(DKG, &session_id, "round1").await {
+        Ok(round1) if round1.len() == participants => {}
+        Ok(_) => return response(409, "Round 1 isn't complete yet".to_string(), 0, expected),
+        Err(e) => return response(500, format!("Error loading round 1 : {}", e), 0, expected),
+    }
+
+    let recipients: Vec<Uuid> = session
+        .participants
+        .iter()
+        .filter(|other| other.holder_id != participant.holder_id)
+        .map(|other| other.holder_id)
+        .collect();
+    let covers_all = request.shares.len() == recipients.len()
+        && recipients.iter().all(|recipient| request.shares.iter().any(|share| share.holder_id == *recipient));
+    if !covers_all {
+        return response(400, "Round 2 needs exactly one sub share for every other participant".to_string(), 0, expected);
+    }
+
+    let mut collected = 0;
+    for share in &request.shares {
+        let field = format!("{}:{}", participant.holder_id, share.holder_id);
+        collected = match state.share_storage.put_entry(DKG, &session_id, "round2", &field, &share.ciphertext).await {
+            Ok(collected) => collected,
+            Err(e) => return response(500, format!("Error storing sub share : {}", e), 0, expected),
+        };
+    }
+    response(200, "Round 2 sub shares received".to_string(), collected, expected)
+}
+
+// Final step: each participant checks its sub shares, sums them into its signing share and stores
+// that sealed to itself. Once everyone confirmed the key is recorded like any other custody key.
+#[debug_handler]
+async fn confirm_dkg(
+    State(state): State<CustodyState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Path(session_id): Path<String>,
+    Json(request): Json<ConfirmDkgRequest>,
+) -> Json<DkgStepResponse> {
+    let response = |status_code: u32, message: String, collected: usize, expected: usize| {
+        Json(DkgStepResponse { status_code, message, collected, expected, public_key: None })
+    };
+    let parts = ["round1", "round2", "confirmed", "finalized"];
+
+    let (session, participant) = match load_dkg(&state, &session_id, user_id).await {
+        Ok(Some((session, Some(participant)))) => (session, participant),
+        Ok(Some(_)) => return response(403, "Caller is not part of this key generation".to_string(), 0, 0),
+        Ok(None) => return response(404, "Key generation not found or expired".to_string(), 0, 0),
+        Err(e) => return response(500, format!("Error loading key generation : {}", e), 0, 0),
+    };
+    let expected = session.participants.len();
+
+    // a sub share that didn't match its sender's commitments ends the run, nobody gets a key
+    if !request.complaints.is_empty() {
+        if let Err(e) = state.share_storage.close_session(DKG, &session_id, &parts).await {
+            eprintln!("Failed to close key generation {} : {}", session_id, e);
+        }
+        println!("Key generation {} aborted, {} accused {:?}", session_id, participant.holder_id, request.complaints);
+        return response(400, format!("Key generation aborted, invalid sub shares from {:?}", request.complaints), 0, expected);
+    }
+
+    match state.share_storage.entries(DKG, &session_id, "round2").await {
+        Ok(round2) if round2.len() == expected * (expected - 1) => {}
+        Ok(_) => return response(409, "Round 2 isn't complete yet".to_string(), 0, expected),
+        Err(e) => return response(500, format!("Error loading round 2 : {}", e), 0, expected),
+    }
+    if STANDARD.decode(&request.ciphertext).is_err() {
+        return response(400, "ciphertext must be base64".to_string(), 0, expected);
+    }
+
+    let collected = match state
+        .share_storage
+        .put_entry(DKG, &session_id, "confirmed", &participant.holder_id.to_string(), &request.ciphertext)
+        .await
+    {
+        Ok(collected) => collected,
+        Err(e) => return response(500, format!("Error storing confirmation : {}", e), 0, expected),
+    };
+    if collected < expected {
+        return response(200, "Confirmation received".to_string(), collected, expected);
+    }
+    match state.share_storage.set_once(DKG, &session_id, "finalized", "1").await {
+        Ok(true) => {}
+        Ok(false) => return response(200, "Key generation is being finalized".to_string(), collected, expected),
+        Err(e) => return response(500, format!("Error finalizing key generation : {}", e), collected, expected),
+    }
+
+    let finalized = async {
+        let packages = round1_packages(&state, &session)
+            .await?
+            .iter()
+            .map(|entry| parse_round1(&entry.package, session.threshold))
+            .collect::<Result<Vec<_>, _>>()?;
+        let commitments = frost::dkg::group_commitments(&packages)?;
+        let confirmed = state.share_storage.entries(DKG, &session_id, "confirmed").await?;
+        let holder_ids: Vec<Uuid> = session.participants.iter().map(|participant| participant.holder_id).collect();
+        let sealed_shares = session
+            .participants
+            .iter()
+            .map(|participant| {
+                confirmed
+                    .get(&participant.holder_id.to_string())
+                    .map(|ciphertext| (participant.index, ciphertext.clone()))
+                    .ok_or_else(|| anyhow::anyhow!("{} has not confirmed", participant.holder_id))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        store_dkg_key(&state.db, &holder_ids, &sealed_shares, &commitments, session.started_by).await
+    };
+
+    let result = finalized.await;
+    if let Err(e) = state.share_storage.close_session(DKG, &session_id, &parts).await {
+        eprintln!("Failed to close key generation {} : {}", session_id, e);
+    }
+    match result {
+        Ok(key) => {
+            println!("Key generation {} produced {}", session_id, key.public_key);
+            Json(DkgStepResponse {
+                status_code: 200,
+                message: format!("Generated {} as {}-of-{}", key.public_key, key.threshold, key.total_shares),
+                collected,
+                expected,
+                public_key: Some(key.public_key),
+            })
+        }
+        Err(e) => response(500, format!("Error storing generated key : {}", e), collected, expected),
+    }
+}
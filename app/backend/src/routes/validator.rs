@@ -1,34 +1,91 @@
 use crate::entities::validator;
 use crate::middleware::auth::jwt_auth_middleware;
-use crate::types::redis::AppState;
+use crate::types::redis::{AppState, WalletChallenge};
+use crate::redis::challenge_manager::CHALLENGE_TTL_SECS;
 use crate::types::user::{
-    ValidatorData, ValidatorInput, VerifySignatureRequest, VerifyValidatorResponse,
+    LoginResponse, RegistrationChallengeRequest, UserData, ValidatorData, ValidatorInput, VerifySignatureRequest,
+    VerifyValidatorResponse, WalletChallengeRequest, WalletChallengeResponse, WalletRebindResponse,
+};
+use crate::utils::jwt_extractor::{create_jwt, AuthenticatedUser};
+use crate::utils::siws::{
+    get_siws_domain, rebind_statement, registration_statement, sign_in_statement, SiwsMessage, PURPOSE_REBIND,
+    PURPOSE_REGISTER, PURPOSE_SIGN_IN,
 };
-use crate::utils::jwt_extractor::create_jwt;
 use axum::extract::State;
-use axum::http::StatusCode;
 use axum::middleware;
-use axum::response::IntoResponse;
 use axum::{debug_handler, extract::Extension, routing::post, Json, Router};
 use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
-use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
-use uuid::Uuid;
 
 pub fn validator_router() -> Router<AppState> {
     Router::new()
-        .route("/wallet", post(handle_connection))
+        .route("/wallet/challenge", post(wallet_challenge))
+        .route("/wallet/verify", post(wallet_verify))
+        .route(
+            "/registration-challenge",
+            post(registration_challenge).layer(middleware::from_fn(jwt_auth_middleware)))
         .route(
             "/verify-validator",
             post(verify_validator).layer(middleware::from_fn(jwt_auth_middleware)))
+        .route(
+            "/wallet/rebind-challenge",
+            post(rebind_challenge).layer(middleware::from_fn(jwt_auth_middleware)))
+        .route(
+            "/wallet/rebind",
+            post(rebind_wallet).layer(middleware::from_fn(jwt_auth_middleware)))
 }
 
 #[debug_handler]
 async fn verify_validator(
     State(app_state): State<AppState>,
-    Extension(user_id): Extension<Uuid>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
     Json(validator_data): Json<ValidatorInput>,
 ) -> Json<VerifyValidatorResponse> {
+    // the wallet has to sign a registration challenge issued for this user and device
+    let challenge = match app_state
+        .challenge_store
+        .redeem(PURPOSE_REGISTER, &validator_data.wallet_address, &validator_data.signature, &validator_data.message)
+        .await
+    {
+        Ok(challenge) => challenge,
+        Err((status_code, message)) => {
+            return Json(VerifyValidatorResponse { status_code, message, validator_data: None, token: None });
+        }
+    };
+    if challenge.user_id != Some(user_id) || challenge.device_id.as_deref() != Some(validator_data.device_id.as_str()) {
+        return Json(VerifyValidatorResponse {
+            status_code: 401,
+            message: "Registration challenge was issued for another user or device".to_string(),
+            validator_data: None,
+            token: None,
+        });
+    }
+
+    match validator::Entity::find()
+        .filter(validator::Column::WalletAddress.eq(&challenge.wallet))
+        .one(&app_state.db)
+        .await
+    {
+        Ok(Some(_)) => {
+            return Json(VerifyValidatorResponse {
+                status_code: 409,
+                message: "Wallet is already bound to a validator".to_string(),
+                validator_data: None,
+                token: None,
+            });
+        }
+        Ok(None) => {}
+        Err(db_err) => {
+            return Json(VerifyValidatorResponse {
+                status_code: 500,
+                message: format!("Database error occured : {}", db_err),
+                validator_data: None,
+                token: None,
+            });
+        }
+    }
+
     let device_id = validator_data.device_id;
     let proximity_range = 0.001;
 
@@ -61,7 +118,7 @@ async fn verify_validator(
 
     let new_validator = validator::ActiveModel {
         user_id: Set(user_id),
-        wallet_address: Set(validator_data.wallet_address),
+        wallet_address: Set(challenge.wallet),
         latitude: Set(Some(validator_data.latitude)),
         longitude: Set(Some(validator_data.longitude)),
         device_id: Set(device_id),
@@ -110,35 +167,231 @@ async fn verify_validator(
     }
 }
 
+fn challenge_response(challenge: Result<WalletChallenge, anyhow::Error>) -> Json<WalletChallengeResponse> {
+    match challenge {
+        Ok(challenge) => Json(WalletChallengeResponse {
+            status_code: 200,
+            message: format!("Sign the message within {} seconds", CHALLENGE_TTL_SECS),
+            sign_in_message: Some(SiwsMessage::from_challenge(get_siws_domain(), &challenge).to_message()),
+            nonce: Some(challenge.nonce),
+            expires_at: Some(challenge.expires_at),
+        }),
+        Err(e) => Json(WalletChallengeResponse {
+            status_code: 500,
+            message: format!("Error creating challenge : {}", e),
+            sign_in_message: None,
+            nonce: None,
+            expires_at: None,
+        }),
+    }
+}
+
+fn invalid_wallet(public_key: &str) -> Option<Json<WalletChallengeResponse>> {
+    Pubkey::from_str(public_key).err().map(|parse_error| {
+        Json(WalletChallengeResponse {
+            status_code: 400,
+            message: format!("could not parse due to {}", parse_error),
+            sign_in_message: None,
+            nonce: None,
+            expires_at: None,
+        })
+    })
+}
+
+// Hands out a Sign-In-With-Solana message with a fresh single use nonce for the wallet to sign
 #[debug_handler]
-async fn handle_connection(
-    Json(verification_data): Json<VerifySignatureRequest>,
-) -> impl IntoResponse {
-    let pubkey_bytes = match Pubkey::from_str(&verification_data.public_key) {
-        // here base58 encoded publickey is decoded into bytes (Vec of Bytes).
-        Ok(pk) => pk,
-        Err(parse_error) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                format!("could not parse due to {}", parse_error),
+async fn wallet_challenge(
+    State(app_state): State<AppState>,
+    Json(challenge_request): Json<WalletChallengeRequest>,
+) -> Json<WalletChallengeResponse> {
+    if let Some(response) = invalid_wallet(&challenge_request.public_key) {
+        return response;
+    }
+
+    challenge_response(
+        app_state
+            .challenge_store
+            .create_challenge(&challenge_request.public_key, PURPOSE_SIGN_IN, sign_in_statement(), None, None)
+            .await,
+    )
+}
+
+// The challenge a wallet signs to be registered for this user's validator on the given device
+#[debug_handler]
+async fn registration_challenge(
+    State(app_state): State<AppState>,
+    Extension(AuthenticatedUser { user_id, .. }): Extension<AuthenticatedUser>,
+    Json(challenge_request): Json<RegistrationChallengeRequest>,
+) -> Json<WalletChallengeResponse> {
+    if let Some(response) = invalid_wallet(&challenge_request.public_key) {
+        return response;
+    }
+
+    challenge_response(
+        app_state
+            .challenge_store
+            .create_challenge(
+                &challenge_request.public_key,
+                PURPOSE_REGISTER,
+                registration_statement(user_id, &challenge_request.device_id),
+                Some(user_id),
+                Some(challenge_request.device_id),
             )
-                .into_response();
+            .await,
+    )
+}
+
+// The challenge the new wallet signs when a validator rotates its payout wallet
+#[debug_handler]
+async fn rebind_challenge(
+    State(app_state): State<AppState>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Json(challenge_request): Json<WalletChallengeRequest>,
+) -> Json<WalletChallengeResponse> {
+    if let Some(response) = invalid_wallet(&challenge_request.public_key) {
+        return response;
+    }
+
+    let validator = match caller_validator(&app_state, &authenticated_user).await {
+        Ok(validator) => validator,
+        Err((status_code, message)) => {
+            return Json(WalletChallengeResponse { status_code, message, sign_in_message: None, nonce: None, expires_at: None });
         }
     };
 
-    let signature_bytes = match Signature::from_str(&verification_data.signature) {
-        Ok(sig) => sig,
-        Err(parse_error) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                format!("could not parse due to {}", parse_error),
+    challenge_response(
+        app_state
+            .challenge_store
+            .create_challenge(
+                &challenge_request.public_key,
+                PURPOSE_REBIND,
+                rebind_statement(validator.user_id, validator.id),
+                Some(validator.user_id),
+                Some(validator.device_id),
             )
-                .into_response();
+            .await,
+    )
+}
+
+// Moves the caller's validator to the wallet that signed the rebind challenge
+#[debug_handler]
+async fn rebind_wallet(
+    State(app_state): State<AppState>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Json(verification_data): Json<VerifySignatureRequest>,
+) -> Json<WalletRebindResponse> {
+    let response = |status_code: u32, message: String| {
+        Json(WalletRebindResponse { status_code, message, validator_id: None, wallet_address: None })
+    };
+
+    let validator = match caller_validator(&app_state, &authenticated_user).await {
+        Ok(validator) => validator,
+        Err((status_code, message)) => return response(status_code, message),
+    };
+    let challenge = match app_state
+        .challenge_store
+        .redeem(PURPOSE_REBIND, &verification_data.public_key, &verification_data.signature, &verification_data.message)
+        .await
+    {
+        Ok(challenge) => challenge,
+        Err((status_code, message)) => return response(status_code, message),
+    };
+    if challenge.user_id != Some(validator.user_id) || challenge.device_id.as_deref() != Some(validator.device_id.as_str()) {
+        return response(401, "Rebind challenge was issued for another validator".to_string());
+    }
+
+    match validator::Entity::find()
+        .filter(validator::Column::WalletAddress.eq(&challenge.wallet))
+        .one(&app_state.db)
+        .await
+    {
+        Ok(Some(other)) if other.id != validator.id => {
+            return response(409, "Wallet is already bound to another validator".to_string())
+        }
+        Ok(_) => {}
+        Err(db_err) => return response(500, format!("Database error occured : {}", db_err)),
+    }
+
+    let old_wallet = validator.wallet_address.clone();
+    let mut active_validator: validator::ActiveModel = validator.into();
+    active_validator.wallet_address = Set(challenge.wallet.clone());
+    match active_validator.update(&app_state.db).await {
+        Ok(validator) => {
+            println!("🔁 Validator {} moved from wallet {} to {}", validator.id, old_wallet, validator.wallet_address);
+            Json(WalletRebindResponse {
+                status_code: 200,
+                message: "Wallet rebound".to_string(),
+                validator_id: Some(validator.id),
+                wallet_address: Some(validator.wallet_address),
+            })
         }
+        Err(db_err) => response(500, format!("Db error occured : {}", db_err)),
+    }
+}
+
+// The validator the request's JWT was issued for. Validators.user_id is unique, so this is the
+// user's one validator; the JWT's validator_id has to match it, a token minted for a validator that
+// was re-registered or belongs to another user is refused.
+async fn caller_validator(app_state: &AppState, authenticated_user: &AuthenticatedUser) -> Result<validator::Model, (u32, String)> {
+    let Some(validator_id) = authenticated_user.validator_id else {
+        return Err((403, "This session isn't bound to a validator, sign in again".to_string()));
     };
+    match validator::Entity::find_by_id(validator_id)
+        .filter(validator::Column::UserId.eq(authenticated_user.user_id))
+        .one(&app_state.db)
+        .await
+    {
+        Ok(Some(validator)) => Ok(validator),
+        Ok(None) => Err((404, "No validator registered for this user".to_string())),
+        Err(db_err) => Err((500, format!("Database error occured : {}", db_err))),
+    }
+}
 
-    let message_bytes = verification_data.message.into_bytes();
+// Redeems a signed challenge for a JWT of the validator registered with that wallet
+#[debug_handler]
+async fn wallet_verify(
+    State(app_state): State<AppState>,
+    Json(verification_data): Json<VerifySignatureRequest>,
+) -> Json<LoginResponse> {
+    let response = |status_code: u32, message: String| {
+        Json(LoginResponse { status_code, message, user_data: None, token: None })
+    };
+
+    let challenge = match app_state
+        .challenge_store
+        .redeem(PURPOSE_SIGN_IN, &verification_data.public_key, &verification_data.signature, &verification_data.message)
+        .await
+    {
+        Ok(challenge) => challenge,
+        Err((status_code, message)) => return response(status_code, message),
+    };
+
+    let validator = match validator::Entity::find()
+        .filter(validator::Column::WalletAddress.eq(&challenge.wallet))
+        .one(&app_state.db)
+        .await
+    {
+        Ok(Some(validator)) => validator,
+        Ok(None) => return response(404, "No validator registered with this wallet".to_string()),
+        Err(db_err) => return response(500, format!("Database error occured : {}", db_err)),
+    };
 
-    let is_valid = signature_bytes.verify(pubkey_bytes.as_ref(), &message_bytes);
-    Json(is_valid).into_response()
+    match create_jwt(validator.user_id, Some(validator.id)) {
+        Ok(token) => {
+            println!("🔑 Wallet {} signed in as validator {}", challenge.wallet, validator.id);
+            Json(LoginResponse {
+                status_code: 200,
+                message: "Signed in with wallet".to_string(),
+                user_data: Some(UserData {
+                    user_id: validator.user_id,
+                    validator_id: Some(validator.id),
+                }),
+                token: Some(token),
+            })
+        }
+        Err(e) => {
+            println!("❌ Failed to create JWT for validator: {}", e);
+            response(500, "Failed to create JWT token".to_string())
+        }
+    }
 }
@@ -1,30 +1,64 @@
 use axum::{
-    extract::State,
+    extract::{Extension, State},
+    middleware,
     routing::post,
     Json, Router,
 };
-use sea_orm::{ActiveModelTrait, Set, DatabaseConnection};
-use crate::entities::website_performance;
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set, DatabaseConnection};
+use crate::entities::{validator, website_performance};
+use crate::middleware::auth::jwt_auth_middleware;
 use crate::types::performance_data::{PerformanceOutput, PerfomanceDataInput};
+use crate::utils::jwt_extractor::AuthenticatedUser;
 
 pub fn performance_router() -> Router<DatabaseConnection> {
     Router::new().route(
         "/add",
         post(add_performance_data)
     )
+    .layer(middleware::from_fn(jwt_auth_middleware))
 }
 
+// Results are recorded for the validator the JWT was issued for and stamped with the time they
+// arrive, rewards are paid by both so neither comes from the request body.
 #[axum::debug_handler]
 async fn add_performance_data(
     State(db): State<DatabaseConnection>,
+    Extension(AuthenticatedUser { user_id, validator_id }): Extension<AuthenticatedUser>,
     Json(input): Json<PerfomanceDataInput>,
 ) -> Json<PerformanceOutput> {
     let db = db.clone();
 
+    let Some(validator_id) = validator_id else {
+        return Json(PerformanceOutput {
+            status_code: 403,
+            message: "Token is not bound to a validator".to_string(),
+        });
+    };
+    match validator::Entity::find_by_id(validator_id)
+        .filter(validator::Column::UserId.eq(user_id))
+        .one(&db)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return Json(PerformanceOutput {
+                status_code: 404,
+                message: "Validator not found".to_string(),
+            })
+        }
+        Err(err) => {
+            return Json(PerformanceOutput {
+                status_code: 500,
+                message: format!("Database error occured : {}", err),
+            })
+        }
+    }
+
     let performance = website_performance::ActiveModel {
-        validator_id: Set(input.validator_id),
+        validator_id: Set(validator_id.to_string()),
         website_id: Set(input.website_id),
-        timestamp: Set(input.timestamp),
+        timestamp: Set(Utc::now().fixed_offset()),
         http_status_code: Set(input.http_status_code),
         dns_resolution_ms: Set(input.dns_resolution_ms),
         connection_time_ms: Set(input.connection_time_ms),
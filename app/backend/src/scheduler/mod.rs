@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use rand::{seq::SliceRandom, Rng};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, Set,
+    TransactionTrait,
+};
+use uuid::Uuid;
+
+use crate::entities::{scheduled_check, validator, website_performance, website_register};
+use crate::indexer::BoxError;
+use crate::redis::pubsub_manager::RedisPubSub;
+use crate::types::redis::ServerMessage;
+
+// Bounds for the interval a website owner can ask for
+pub const MIN_CHECK_INTERVAL_SECS: i32 = 60;
+pub const MAX_CHECK_INTERVAL_SECS: i32 = 24 * 60 * 60;
+
+pub struct SchedulerConfig {
+    pub validators_per_check: usize, // how many validators each run is assigned to
+    pub jitter: f64,                 // next run lands within +/- this fraction of the interval
+    pub result_grace_secs: i64,      // how long assigned validators have to report a result
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            validators_per_check: 3,
+            jitter: 0.1,
+            result_grace_secs: 120,
+        }
+    }
+}
+
+// Announces due website checks to a few validators each and records the runs it expects, so
+// results that never arrive show up as missed checks.
+pub struct CheckScheduler {
+    db: DatabaseConnection,
+    pubsub: Arc<RedisPubSub>,
+    config: SchedulerConfig,
+}
+
+impl CheckScheduler {
+    pub fn new(db: DatabaseConnection, pubsub: Arc<RedisPubSub>, config: SchedulerConfig) -> Self {
+        Self { db, pubsub, config }
+    }
+
+    pub async fn run_once(&self) -> Result<(), BoxError> {
+        self.schedule_due().await?;
+        self.settle_overdue().await
+    }
+
+    pub fn spawn(self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.run_once().await {
+                    eprintln!("Check scheduler run failed : {}", e);
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    // Next run time, the jitter keeps websites added together from being checked in lockstep
+    fn next_check_at(&self, from: DateTime<Utc>, interval_secs: i32) -> DateTime<FixedOffset> {
+        let spread = interval_secs as f64 * self.config.jitter;
+        let jitter = if spread > 0.0 { rand::thread_rng().gen_range(-spread..=spread) } else { 0.0 };
+        let delay = (interval_secs as f64 + jitter).max(MIN_CHECK_INTERVAL_SECS as f64);
+        (from + chrono::Duration::milliseconds((delay * 1000.0) as i64)).fixed_offset()
+    }
+
+    // Claims the website's due run by moving next_check_at on from the value it was read with. An
+    // instance that lost the race finds it already moved and gets false.
+    async fn claim_run(&self, website: &website_register::Model, now: DateTime<Utc>) -> Result<bool, BoxError> {
+        let mut claim = website_register::Entity::update_many()
+            .col_expr(
+                website_register::Column::NextCheckAt,
+                Expr::value(self.next_check_at(now, website.check_interval_secs)),
+            )
+            .filter(website_register::Column::Id.eq(website.id));
+        claim = match website.next_check_at {
+            Some(next_check_at) => claim.filter(website_register::Column::NextCheckAt.eq(next_check_at)),
+            None => claim.filter(website_register::Column::NextCheckAt.is_null()),
+        };
+        Ok(claim.exec(&self.db).await?.rows_affected > 0)
+    }
+
+    async fn schedule_due(&self) -> Result<(), BoxError> {
+        let now = Utc::now();
+        let due = website_register::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(website_register::Column::NextCheckAt.is_null())
+                    .add(website_register::Column::NextCheckAt.lte(now.fixed_offset())),
+            )
+            .all(&self.db)
+            .await?;
+        if due.is_empty() {
+            return Ok(());
+        }
+        let validators = validator::Entity::find().all(&self.db).await?;
+
+        for website in due {
+            if !self.claim_run(&website, now).await? {
+                continue;
+            }
+
+            let assigned: Vec<&validator::Model> = validators
+                .choose_multiple(&mut rand::thread_rng(), self.config.validators_per_check)
+                .collect();
+            if assigned.is_empty() {
+                println!("No validators to check {}", website.website_url);
+                continue;
+            }
+
+            let run_id = Uuid::new_v4();
+            let due_by = now + chrono::Duration::seconds(self.config.result_grace_secs);
+            let txn = self.db.begin().await?;
+            for validator in &assigned {
+                scheduled_check::ActiveModel {
+                    run_id: Set(run_id),
+                    website_id: Set(website.id),
+                    validator_id: Set(validator.id),
+                    scheduled_for: Set(now.fixed_offset()),
+                    due_by: Set(due_by.fixed_offset()),
+                    status: Set(scheduled_check::STATUS_SCHEDULED.to_string()),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await?;
+            }
+            txn.commit().await?;
+
+            let server_message = ServerMessage {
+                url: website.website_url.clone(),
+                id: website.id.to_string(),
+                run_id: Some(run_id.to_string()),
+                validator_ids: assigned.iter().map(|validator| validator.id.to_string()).collect(),
+            };
+            if let Err(e) = self.pubsub.publish_to_validators(server_message).await {
+                // the run stays recorded, its checks will be settled as missed
+                eprintln!("Failed to announce check run {} for {} : {}", run_id, website.website_url, e);
+            }
+        }
+        Ok(())
+    }
+
+    // Settles runs past their deadline: completed by the validator's first report of the website
+    // within the run's window, missed otherwise. The reward engine pays completed runs only, for
+    // the result recorded here.
+    async fn settle_overdue(&self) -> Result<(), BoxError> {
+        let overdue = scheduled_check::Entity::find()
+            .filter(scheduled_check::Column::Status.eq(scheduled_check::STATUS_SCHEDULED))
+            .filter(scheduled_check::Column::DueBy.lt(Utc::now().fixed_offset()))
+            .all(&self.db)
+            .await?;
+        let (Some(from), Some(to)) = (
+            overdue.iter().map(|check| check.scheduled_for).min(),
+            overdue.iter().map(|check| check.due_by).max(),
+        ) else {
+            return Ok(());
+        };
+
+        let mut results: HashMap<(String, String), Vec<website_performance::Model>> = HashMap::new();
+        for result in website_performance::Entity::find()
+            .filter(website_performance::Column::Timestamp.gte(from))
+            .filter(website_performance::Column::Timestamp.lte(to))
+            .all(&self.db)
+            .await?
+        {
+            results.entry((result.website_id.clone(), result.validator_id.clone())).or_default().push(result);
+        }
+
+        let (mut completed, mut missed) = (0, 0);
+        for check in overdue {
+            let result = results
+                .get(&(check.website_id.to_string(), check.validator_id.to_string()))
+                .and_then(|results| {
+                    results
+                        .iter()
+                        .filter(|result| result.timestamp >= check.scheduled_for && result.timestamp <= check.due_by)
+                        .min_by_key(|result| (result.timestamp, result.id))
+                });
+            let mut settled: scheduled_check::ActiveModel = check.into();
+            match result {
+                Some(result) => {
+                    settled.status = Set(scheduled_check::STATUS_COMPLETED.to_string());
+                    settled.performance_id = Set(Some(result.id));
+                    completed += 1;
+                }
+                None => {
+                    settled.status = Set(scheduled_check::STATUS_MISSED.to_string());
+                    missed += 1;
+                }
+            }
+            settled.update(&self.db).await?;
+        }
+
+        println!("Settled scheduled checks : {} completed, {} missed", completed, missed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, Database, Schema};
+
+    async fn in_memory_scheduler(config: SchedulerConfig) -> CheckScheduler {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        // users, validators and websites the rows point at aren't created
+        db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+        let backend = db.get_database_backend();
+        let schema = Schema::new(backend);
+        for table in [
+            schema.create_table_from_entity(website_register::Entity),
+            schema.create_table_from_entity(scheduled_check::Entity),
+            schema.create_table_from_entity(website_performance::Entity),
+        ] {
+            // the uuid keys are generated by postgres, sqlite only takes AUTOINCREMENT on integers
+            let mut statement = backend.build(&table);
+            statement.sql = statement.sql.replace(" AUTOINCREMENT", "");
+            db.execute(statement).await.unwrap();
+        }
+        // nothing listens in tests, announcements fail and are only logged
+        let pubsub = Arc::new(RedisPubSub::new(redis::Client::open("redis://127.0.0.1:1/").unwrap()));
+        CheckScheduler::new(db, pubsub, config)
+    }
+
+    // Rows are written without RETURNING, sea-orm can't read a uuid key back from sqlite
+    async fn website(db: &DatabaseConnection, check_interval_secs: i32) -> website_register::Model {
+        let id = Uuid::new_v4();
+        let website = website_register::ActiveModel {
+            id: Set(id),
+            website_url: Set(format!("https://{}.example", Uuid::new_v4())),
+            user_id: Set(Uuid::new_v4()),
+            timestamp: Set(Utc::now().fixed_offset()),
+            check_interval_secs: Set(check_interval_secs),
+            next_check_at: Set(None),
+        };
+        website_register::Entity::insert(website).exec_without_returning(db).await.unwrap();
+        website_register::Entity::find_by_id(id).one(db).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn next_check_at_stays_within_jitter() {
+        let scheduler = in_memory_scheduler(SchedulerConfig { jitter: 0.1, ..SchedulerConfig::default() }).await;
+        let from = Utc::now();
+        let delays: Vec<i64> = (0..200)
+            .map(|_| (scheduler.next_check_at(from, 600) - from.fixed_offset()).num_milliseconds())
+            .collect();
+        assert!(delays.iter().all(|delay| (540_000..=660_000).contains(delay)));
+        assert!(delays.iter().any(|delay| *delay < 600_000) && delays.iter().any(|delay| *delay > 600_000));
+
+        // jitter never brings a run closer than the minimum interval
+        let scheduler = in_memory_scheduler(SchedulerConfig { jitter: 0.5, ..SchedulerConfig::default() }).await;
+        assert!((0..200).all(|_| {
+            (scheduler.next_check_at(from, MIN_CHECK_INTERVAL_SECS) - from.fixed_offset()).num_seconds() >= MIN_CHECK_INTERVAL_SECS as i64
+        }));
+    }
+
+    #[tokio::test]
+    async fn only_one_instance_claims_a_due_run() {
+        let scheduler = in_memory_scheduler(SchedulerConfig::default()).await;
+        let website = website(&scheduler.db, 300).await;
+        let now = Utc::now();
+
+        // two instances read the website as due, the second one to claim it skips the run
+        assert!(scheduler.claim_run(&website, now).await.unwrap());
+        assert!(!scheduler.claim_run(&website, now).await.unwrap());
+
+        let claimed = website_register::Entity::find_by_id(website.id).one(&scheduler.db).await.unwrap().unwrap();
+        assert!(claimed.next_check_at.is_some_and(|next_check_at| next_check_at > now.fixed_offset()));
+        // the next run is claimed from the moved value
+        assert!(scheduler.claim_run(&claimed, now).await.unwrap());
+        assert!(!scheduler.claim_run(&claimed, now).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn settle_overdue_marks_completed_and_missed() {
+        let scheduler = in_memory_scheduler(SchedulerConfig::default()).await;
+        let (website_id, run_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let scheduled_for = Utc::now() - chrono::Duration::minutes(10);
+        let due_by = scheduled_for + chrono::Duration::minutes(2);
+
+        let assign = |validator_id: Uuid, due_by: DateTime<Utc>| scheduled_check::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            run_id: Set(run_id),
+            website_id: Set(website_id),
+            validator_id: Set(validator_id),
+            scheduled_for: Set(scheduled_for.fixed_offset()),
+            due_by: Set(due_by.fixed_offset()),
+            status: Set(scheduled_check::STATUS_SCHEDULED.to_string()),
+            performance_id: Set(None),
+            created_at: Set(scheduled_for.fixed_offset()),
+        };
+        let report = |validator_id: Uuid, at: DateTime<Utc>| website_performance::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            validator_id: Set(validator_id.to_string()),
+            website_id: Set(website_id.to_string()),
+            timestamp: Set(at.fixed_offset()),
+            http_status_code: Set(Some(200.0)),
+            total_time_ms: Set(Some(80.0)),
+            ..Default::default()
+        };
+
+        let (on_time, late, silent, pending) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let assignments = [(on_time, due_by), (late, due_by), (silent, due_by), (pending, Utc::now() + chrono::Duration::minutes(1))]
+            .map(|(validator_id, due_by)| assign(validator_id, due_by));
+        scheduled_check::Entity::insert_many(assignments).exec_without_returning(&scheduler.db).await.unwrap();
+        let first = report(on_time, scheduled_for + chrono::Duration::seconds(20));
+        let first_id = first.id.clone().unwrap();
+        let reports = [
+            first,
+            report(on_time, scheduled_for + chrono::Duration::seconds(40)),
+            report(late, due_by + chrono::Duration::seconds(30)),
+        ];
+        website_performance::Entity::insert_many(reports).exec_without_returning(&scheduler.db).await.unwrap();
+
+        scheduler.settle_overdue().await.unwrap();
+
+        let settled: HashMap<Uuid, scheduled_check::Model> = scheduled_check::Entity::find()
+            .all(&scheduler.db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|check| (check.validator_id, check))
+            .collect();
+        assert_eq!(settled[&on_time].status, scheduled_check::STATUS_COMPLETED);
+        assert_eq!(settled[&on_time].performance_id, Some(first_id));
+        assert_eq!(settled[&late].status, scheduled_check::STATUS_MISSED);
+        assert_eq!(settled[&late].performance_id, None);
+        assert_eq!(settled[&silent].status, scheduled_check::STATUS_MISSED);
+        assert_eq!(settled[&pending].status, scheduled_check::STATUS_SCHEDULED);
+    }
+}
@@ -0,0 +1,601 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crypto_box::{aead::OsRng, PublicKey, SecretKey};
+use curve25519_dalek::scalar::Scalar;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use solana_sdk::signature::{Keypair, Signer};
+use uuid::Uuid;
+
+use crate::entities::{custody_epoch, custody_key, key_share, share_holder};
+use crate::shamir_secret::secret::SecretConfig;
+use crate::shamir_secret::frost;
+use crate::shamir_secret::vss::{self, Commitments, VerifiableShare};
+
+// Shares are sealed boxes (X25519 + XSalsa20-Poly1305) to the holder's encryption key, so the
+// server only ever stores ciphertexts it cannot open.
+
+pub fn parse_encryption_key(encoded: &str) -> Result<PublicKey, anyhow::Error> {
+    let bytes = STANDARD.decode(encoded)?;
+    PublicKey::from_slice(&bytes).map_err(|_| anyhow!("encryption key must be 32 bytes"))
+}
+
+// Base64 sealed box of `plaintext`, only the holder of the recipient's secret key can open it
+pub fn seal(recipient: &PublicKey, plaintext: &[u8]) -> Result<String, anyhow::Error> {
+    let ciphertext = recipient
+        .seal(&mut OsRng, plaintext)
+        .map_err(|_| anyhow!("failed to seal"))?;
+    Ok(STANDARD.encode(ciphertext))
+}
+
+pub fn unseal(secret_key: &SecretKey, ciphertext: &str) -> Result<Vec<u8>, anyhow::Error> {
+    let sealed = STANDARD.decode(ciphertext)?;
+    secret_key
+        .unseal(&sealed)
+        .map_err(|_| anyhow!("ciphertext was not sealed to this key"))
+}
+
+pub fn encrypt_share(holder_key: &PublicKey, share: &VerifiableShare) -> Result<String, anyhow::Error> {
+    seal(holder_key, &share.to_bytes())
+}
+
+pub fn decrypt_share(secret_key: &SecretKey, ciphertext: &str) -> Result<VerifiableShare, anyhow::Error> {
+    let plaintext = unseal(secret_key, ciphertext).map_err(|_| anyhow!("share was not sealed to this key"))?;
+    VerifiableShare::from_bytes(&plaintext)
+}
+
+// Ceremony secrets are kept in redis sealed to the server's CUSTODY_SEAL_KEY, so a restart or
+// another instance can still open the shares submitted to a running ceremony
+pub fn seal_ceremony_key(seal_key: &SecretKey, ceremony_key: &SecretKey) -> Result<String, anyhow::Error> {
+    seal(&seal_key.public_key(), &ceremony_key.to_bytes())
+}
+
+pub fn open_ceremony_key(seal_key: &SecretKey, sealed: &str) -> Result<SecretKey, anyhow::Error> {
+    let bytes = unseal(seal_key, sealed)?;
+    SecretKey::from_slice(&bytes).map_err(|_| anyhow!("ceremony key must be 32 bytes"))
+}
+
+pub fn key_commitments(key: &custody_key::Model) -> Result<Commitments, anyhow::Error> {
+    let encoded: Vec<String> = serde_json::from_value(key.commitments.clone())?;
+    if encoded.is_empty() {
+        return Err(anyhow!("key {} was split without commitments, split it again", key.public_key));
+    }
+    Commitments::decode(&encoded)
+}
+
+// Loads the holders in the order given, every one of them registered and listed once
+async fn load_holders<C: ConnectionTrait>(db: &C, holder_ids: &[Uuid]) -> Result<Vec<share_holder::Model>, anyhow::Error> {
+    let holders = share_holder::Entity::find()
+        .filter(share_holder::Column::Id.is_in(holder_ids.to_vec()))
+        .all(db)
+        .await?;
+    if holders.len() != holder_ids.len() {
+        return Err(anyhow!("every holder must be registered exactly once"));
+    }
+    holder_ids
+        .iter()
+        .map(|id| {
+            holders
+                .iter()
+                .find(|holder| holder.id == *id)
+                .cloned()
+                .ok_or_else(|| anyhow!("holder {} is not registered", id))
+        })
+        .collect()
+}
+
+// Seals shares[i] to holders[i] and stores them as the holders' rows for the epoch
+async fn issue_shares<C: ConnectionTrait>(
+    txn: &C,
+    key_id: Uuid,
+    epoch: i32,
+    holders: &[share_holder::Model],
+    shares: &[VerifiableShare],
+) -> Result<(), anyhow::Error> {
+    for (holder, share) in holders.iter().zip(shares.iter()) {
+        let holder_key = parse_encryption_key(&holder.encryption_public_key)?;
+        key_share::ActiveModel {
+            key_id: Set(key_id),
+            holder_id: Set(holder.id),
+            share_index: Set(share.index as i16),
+            ciphertext: Set(encrypt_share(&holder_key, share)?),
+            epoch: Set(epoch),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn record_epoch<C: ConnectionTrait>(
+    txn: &C,
+    key_id: Uuid,
+    epoch: i32,
+    kind: &str,
+    threshold: u8,
+    holder_ids: &[Uuid],
+    commitments: &Commitments,
+    initiated_by: Uuid,
+) -> Result<(), anyhow::Error> {
+    custody_epoch::ActiveModel {
+        key_id: Set(key_id),
+        epoch: Set(epoch),
+        kind: Set(kind.to_string()),
+        threshold: Set(threshold as i16),
+        total_shares: Set(holder_ids.len() as i16),
+        holder_ids: Set(serde_json::to_value(holder_ids)?),
+        commitments: Set(serde_json::to_value(commitments.encode())?),
+        initiated_by: Set(Some(initiated_by)),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await?;
+    println!("Custody key {} entered epoch {} ({})", key_id, epoch, kind);
+    Ok(())
+}
+
+// Moves the key to its next epoch, failing if someone else advanced it first
+async fn advance_key<C: ConnectionTrait>(
+    txn: &C,
+    key: &custody_key::Model,
+    threshold: u8,
+    total_shares: usize,
+    commitments: &Commitments,
+) -> Result<custody_key::Model, anyhow::Error> {
+    let updated = custody_key::Entity::update_many()
+        .col_expr(custody_key::Column::Threshold, (threshold as i16).into())
+        .col_expr(custody_key::Column::TotalShares, (total_shares as i16).into())
+        .col_expr(custody_key::Column::Commitments, serde_json::to_value(commitments.encode())?.into())
+        .col_expr(custody_key::Column::CurrentEpoch, (key.current_epoch + 1).into())
+        .filter(custody_key::Column::Id.eq(key.id))
+        .filter(custody_key::Column::CurrentEpoch.eq(key.current_epoch))
+        .exec(txn)
+        .await?;
+    if updated.rows_affected != 1 {
+        return Err(anyhow!("key {} moved to another epoch meanwhile", key.public_key));
+    }
+    custody_key::Entity::find_by_id(key.id)
+        .one(txn)
+        .await?
+        .ok_or_else(|| anyhow!("key {} no longer exists", key.public_key))
+}
+
+// Generates a key, splits it and stores one sealed share per holder along with the commitments.
+// The secret never leaves this function, afterwards only the holders together can use it.
+pub async fn create_custody_key(
+    db: &DatabaseConnection,
+    holder_ids: &[Uuid],
+    threshold: u8,
+    scheme: &str,
+    initiated_by: Uuid,
+) -> Result<custody_key::Model, anyhow::Error> {
+    let total_shares = u8::try_from(holder_ids.len()).map_err(|_| anyhow!("too many holders"))?;
+    let holders = load_holders(db, holder_ids).await?;
+
+    let config = SecretConfig { threshold, shares: total_shares };
+    let split = match scheme {
+        custody_key::SCHEME_SEED => config.generate_key_and_split()?,
+        custody_key::SCHEME_FROST => config.generate_threshold_key_and_split()?,
+        _ => return Err(anyhow!("unknown key scheme {}", scheme)),
+    };
+
+    let txn = db.begin().await?;
+    let key = custody_key::ActiveModel {
+        public_key: Set(split.pubkey.to_string()),
+        threshold: Set(threshold as i16),
+        total_shares: Set(total_shares as i16),
+        commitments: Set(serde_json::to_value(split.commitments.encode())?),
+        current_epoch: Set(0),
+        scheme: Set(scheme.to_string()),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    issue_shares(&txn, key.id, 0, &holders, &split.shares).await?;
+    record_epoch(&txn, key.id, 0, custody_epoch::KIND_SPLIT, threshold, holder_ids, &split.commitments, initiated_by).await?;
+    txn.commit().await?;
+
+    println!("Stored {} sealed shares for custody key {}", total_shares, key.public_key);
+    Ok(key)
+}
+
+// Rows of the holders taking part in the key's current epoch
+pub async fn current_shares<C: ConnectionTrait>(db: &C, key: &custody_key::Model) -> Result<Vec<key_share::Model>, anyhow::Error> {
+    Ok(key_share::Entity::find()
+        .filter(key_share::Column::KeyId.eq(key.id))
+        .filter(key_share::Column::Epoch.eq(key.current_epoch))
+        .all(db)
+        .await?)
+}
+
+// A holder's current share is the one dealt in the last split or reshare plus every refresh delta
+// issued since. Returns those rows oldest first, or None if the holder has no current share.
+pub async fn holder_share_rows<C: ConnectionTrait>(
+    db: &C,
+    key: &custody_key::Model,
+    holder_id: Uuid,
+) -> Result<Option<Vec<key_share::Model>>, anyhow::Error> {
+    let base_epoch = custody_epoch::Entity::find()
+        .filter(custody_epoch::Column::KeyId.eq(key.id))
+        .filter(custody_epoch::Column::Kind.ne(custody_epoch::KIND_REFRESH))
+        .order_by_desc(custody_epoch::Column::Epoch)
+        .one(db)
+        .await?
+        .map(|epoch| epoch.epoch)
+        .unwrap_or_default();
+
+    let rows = key_share::Entity::find()
+        .filter(key_share::Column::KeyId.eq(key.id))
+        .filter(key_share::Column::HolderId.eq(holder_id))
+        .filter(key_share::Column::Epoch.gte(base_epoch))
+        .order_by_asc(key_share::Column::Epoch)
+        .all(db)
+        .await?;
+    Ok(complete_share_rows(rows, base_epoch, key.current_epoch))
+}
+
+// The rows, oldest first, only if there is exactly one for every epoch from base to current
+fn complete_share_rows(rows: Vec<key_share::Model>, base_epoch: i32, current_epoch: i32) -> Option<Vec<key_share::Model>> {
+    let complete = rows.len() as i32 == current_epoch - base_epoch + 1
+        && rows.iter().zip(base_epoch..).all(|(row, epoch)| row.epoch == epoch);
+    complete.then_some(rows)
+}
+
+// What a holder does with the rows /shares returns: opens the base share and every refresh delta
+// and adds them up into the share for the current epoch
+pub fn combine_share(secret_key: &SecretKey, ciphertext: &str, deltas: &[String]) -> Result<VerifiableShare, anyhow::Error> {
+    let mut share = decrypt_share(secret_key, ciphertext)?;
+    for delta in deltas {
+        let delta = decrypt_share(secret_key, delta)?;
+        if delta.index != share.index {
+            return Err(anyhow!("delta for share {} can't be added to share {}", delta.index, share.index));
+        }
+        share.value += delta.value;
+    }
+    Ok(share)
+}
+
+// Rerandomizes every share without touching the key: each holder gets a share of a fresh
+// polynomial with zero constant term to add to theirs, and the commitments absorb the delta's.
+// Shares leaked before the refresh can't be combined with shares taken after it.
+pub async fn refresh_custody_key(
+    db: &DatabaseConnection,
+    key: &custody_key::Model,
+    initiated_by: Uuid,
+) -> Result<custody_key::Model, anyhow::Error> {
+    let commitments = key_commitments(key)?;
+    let current = current_shares(db, key).await?;
+    let holder_ids: Vec<Uuid> = current.iter().map(|share| share.holder_id).collect();
+    let indexes: Vec<u8> = current.iter().map(|share| share.share_index as u8).collect();
+    let holders = load_holders(db, &holder_ids).await?;
+
+    // the delta polynomial is dropped as soon as it is sealed, the server never keeps it
+    let (deltas, delta_commitments) = vss::deal(&Scalar::ZERO, key.threshold as u8, &indexes);
+    let refreshed = commitments.add(&delta_commitments)?;
+    let epoch = key.current_epoch + 1;
+
+    let txn = db.begin().await?;
+    let key = advance_key(&txn, key, key.threshold as u8, holder_ids.len(), &refreshed).await?;
+    issue_shares(&txn, key.id, epoch, &holders, &deltas).await?;
+    record_epoch(&txn, key.id, epoch, custody_epoch::KIND_REFRESH, key.threshold as u8, &holder_ids, &refreshed, initiated_by).await?;
+    txn.commit().await?;
+    Ok(key)
+}
+
+// Deals the key's seed to a new holder set under a new threshold. Needs the recovered keypair, so
+// it only runs at the end of a reshare ceremony, the seed is dropped right after.
+pub async fn reshare_custody_key(
+    db: &DatabaseConnection,
+    key: &custody_key::Model,
+    keypair: &Keypair,
+    holder_ids: &[Uuid],
+    threshold: u8,
+    initiated_by: Uuid,
+) -> Result<custody_key::Model, anyhow::Error> {
+    let total_shares = u8::try_from(holder_ids.len()).map_err(|_| anyhow!("too many holders"))?;
+    let holders = load_holders(db, holder_ids).await?;
+
+    let (shares, commitments) = split_keypair(keypair, threshold, total_shares)?;
+    let epoch = key.current_epoch + 1;
+
+    let txn = db.begin().await?;
+    let key = advance_key(&txn, key, threshold, holder_ids.len(), &commitments).await?;
+    issue_shares(&txn, key.id, epoch, &holders, &shares).await?;
+    record_epoch(&txn, key.id, epoch, custody_epoch::KIND_RESHARE, threshold, holder_ids, &commitments, initiated_by).await?;
+    txn.commit().await?;
+    Ok(key)
+}
+
+// Splits the seed of a recovered keypair again, under a new threshold and share count
+fn split_keypair(keypair: &Keypair, threshold: u8, total_shares: u8) -> Result<(Vec<VerifiableShare>, Commitments), anyhow::Error> {
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&keypair.to_bytes()[..32]);
+    let seed = Option::<Scalar>::from(Scalar::from_canonical_bytes(seed))
+        .ok_or_else(|| anyhow!("key {} was not generated for verifiable sharing", keypair.pubkey()))?;
+    SecretConfig { threshold, shares: total_shares }.split_secret(&seed)
+}
+
+// Stores a key the holders generated among themselves. Each holder uploads its own signing share
+// sealed to itself, the server only ever sees the public commitments.
+pub async fn store_dkg_key(
+    db: &DatabaseConnection,
+    holder_ids: &[Uuid],
+    sealed_shares: &[(u8, String)], // (share index, ciphertext) per holder, same order as holder_ids
+    commitments: &Commitments,
+    initiated_by: Uuid,
+) -> Result<custody_key::Model, anyhow::Error> {
+    let threshold = commitments.threshold() as u8;
+    let public_key = solana_sdk::pubkey::Pubkey::from(frost::group_key(commitments).compress().to_bytes());
+
+    let txn = db.begin().await?;
+    let key = custody_key::ActiveModel {
+        public_key: Set(public_key.to_string()),
+        threshold: Set(threshold as i16),
+        total_shares: Set(holder_ids.len() as i16),
+        commitments: Set(serde_json::to_value(commitments.encode())?),
+        current_epoch: Set(0),
+        scheme: Set(custody_key::SCHEME_FROST.to_string()),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    for (holder_id, (index, ciphertext)) in holder_ids.iter().zip(sealed_shares.iter()) {
+        key_share::ActiveModel {
+            key_id: Set(key.id),
+            holder_id: Set(*holder_id),
+            share_index: Set(*index as i16),
+            ciphertext: Set(ciphertext.clone()),
+            epoch: Set(0),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+    }
+    record_epoch(&txn, key.id, 0, custody_epoch::KIND_DKG, threshold, holder_ids, commitments, initiated_by).await?;
+    txn.commit().await?;
+
+    println!("Stored distributed key {} held by {} holders", key.public_key, holder_ids.len());
+    Ok(key)
+}
+
+// Checks a holder set and threshold before holders start contributing to a reshare or a DKG
+pub async fn check_holder_set(db: &DatabaseConnection, holder_ids: &[Uuid], threshold: u8) -> Result<(), anyhow::Error> {
+    let total_shares = u8::try_from(holder_ids.len()).map_err(|_| anyhow!("too many holders"))?;
+    if threshold < 2 || threshold > total_shares {
+        return Err(anyhow!("threshold must be between 2 and the number of holders"));
+    }
+    load_holders(db, holder_ids).await?;
+    Ok(())
+}
+
+// Opens a share a holder re-sealed to the ceremony key and checks it is the holder's own and
+// consistent with the published commitments
+pub fn open_submitted_share(
+    ceremony_key: &SecretKey,
+    ciphertext: &str,
+    holder_share: &key_share::Model,
+    commitments: &Commitments,
+) -> Result<VerifiableShare, anyhow::Error> {
+    let share = decrypt_share(ceremony_key, ciphertext)?;
+    if share.index as i16 != holder_share.share_index {
+        return Err(anyhow!("share index {} isn't the one issued to this holder", share.index));
+    }
+    if !commitments.verify_share(&share) {
+        return Err(anyhow!("share does not match the key's commitments"));
+    }
+    Ok(share)
+}
+
+pub struct CustodyRecovery {
+    pub keypair: Keypair,
+    pub rejected_holders: Vec<String>,
+}
+
+// Rebuilds the keypair from the shares submitted to a ceremony. Shares that can't be opened or
+// fail verification are excluded and their holders reported, recovery still succeeds as long as
+// threshold valid shares remain.
+pub fn recover_keypair(
+    ceremony_key: &SecretKey,
+    submitted: &HashMap<String, String>,
+    holder_shares: &[key_share::Model],
+    key: &custody_key::Model,
+) -> Result<CustodyRecovery, anyhow::Error> {
+    let commitments = key_commitments(key)?;
+
+    let mut shares = Vec::with_capacity(submitted.len());
+    let mut rejected_holders = Vec::new();
+    for (holder_id, ciphertext) in submitted {
+        let opened = holder_shares
+            .iter()
+            .find(|share| share.holder_id.to_string() == *holder_id)
+            .ok_or_else(|| anyhow!("holder has no share of this key"))
+            .and_then(|holder_share| open_submitted_share(ceremony_key, ciphertext, holder_share, &commitments));
+        match opened {
+            Ok(share) => shares.push(share),
+            Err(e) => {
+                println!("Excluding share of holder {} : {}", holder_id, e);
+                rejected_holders.push(holder_id.clone());
+            }
+        }
+    }
+
+    let recovered = SecretConfig::reconstruct_secret(&shares, &commitments)?;
+    if recovered.keypair.pubkey().to_string() != key.public_key {
+        return Err(anyhow!("reconstructed key does not match {}", key.public_key));
+    }
+    for index in recovered.rejected {
+        if let Some(share) = holder_shares.iter().find(|share| share.share_index == index as i16) {
+            rejected_holders.push(share.holder_id.to_string());
+        }
+    }
+    Ok(CustodyRecovery { keypair: recovered.keypair, rejected_holders })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custody_key(public_key: String, commitments: &Commitments) -> custody_key::Model {
+        custody_key::Model {
+            id: Uuid::new_v4(),
+            public_key,
+            threshold: commitments.threshold() as i16,
+            total_shares: 3,
+            commitments: serde_json::to_value(commitments.encode()).unwrap(),
+            current_epoch: 0,
+            scheme: custody_key::SCHEME_SEED.to_string(),
+            created_at: chrono::Utc::now().fixed_offset(),
+        }
+    }
+
+    fn share_row(key_id: Uuid, holder_key: &SecretKey, share: &VerifiableShare) -> key_share::Model {
+        key_share::Model {
+            id: Uuid::new_v4(),
+            key_id,
+            holder_id: Uuid::new_v4(),
+            share_index: share.index as i16,
+            ciphertext: encrypt_share(&holder_key.public_key(), share).unwrap(),
+            epoch: 0,
+            created_at: chrono::Utc::now().fixed_offset(),
+        }
+    }
+
+    fn delta_row(base: &key_share::Model, holder_key: &SecretKey, delta: &VerifiableShare, epoch: i32) -> key_share::Model {
+        key_share::Model {
+            epoch,
+            ..share_row(base.key_id, holder_key, delta)
+        }
+    }
+
+    #[test]
+    fn refreshed_shares_add_up_to_the_same_key() {
+        let split = SecretConfig { threshold: 3, shares: 5 }.generate_key_and_split().unwrap();
+        let holder_key = SecretKey::generate(&mut OsRng);
+        let indexes: Vec<u8> = split.shares.iter().map(|share| share.index).collect();
+        let base = share_row(Uuid::new_v4(), &holder_key, &split.shares[1]);
+
+        // two refreshes, each deals a sharing of zero the same way refresh_custody_key does
+        let mut commitments = split.commitments.clone();
+        let mut current: Vec<VerifiableShare> = split.shares.clone();
+        let mut rows = vec![base.clone()];
+        for epoch in 1..=2 {
+            let (deltas, delta_commitments) = vss::deal(&Scalar::ZERO, 3, &indexes);
+            commitments = commitments.add(&delta_commitments).unwrap();
+            for (share, delta) in current.iter_mut().zip(deltas.iter()) {
+                share.value += delta.value;
+            }
+            rows.push(delta_row(&base, &holder_key, &deltas[1], epoch));
+        }
+        assert_ne!(commitments, split.commitments);
+        assert_eq!(commitments.0[0], split.commitments.0[0]);
+
+        let rows = complete_share_rows(rows, 0, 2).unwrap();
+        let deltas: Vec<String> = rows[1..].iter().map(|row| row.ciphertext.clone()).collect();
+        let combined = combine_share(&holder_key, &rows[0].ciphertext, &deltas).unwrap();
+        assert_eq!(combined, current[1]);
+        assert!(commitments.verify_share(&combined));
+        assert!(!commitments.verify_share(&split.shares[1]));
+
+        let recovered = SecretConfig::reconstruct_secret(&current[2..], &commitments).unwrap();
+        assert_eq!(recovered.keypair.pubkey(), split.pubkey);
+        // pre-refresh shares mixed with refreshed ones are rejected, not silently combined
+        let mixed = [split.shares[0].clone(), split.shares[1].clone(), current[2].clone()];
+        assert!(SecretConfig::reconstruct_secret(&mixed, &commitments).is_err());
+    }
+
+    #[test]
+    fn share_rows_need_every_epoch_since_base() {
+        let holder_key = SecretKey::generate(&mut OsRng);
+        let (shares, _) = vss::split(&Scalar::random(&mut OsRng), 2, 3);
+        let base = key_share::Model { epoch: 2, ..share_row(Uuid::new_v4(), &holder_key, &shares[0]) };
+        let deltas = [3, 4].map(|epoch| delta_row(&base, &holder_key, &shares[0], epoch));
+
+        let all = vec![base.clone(), deltas[0].clone(), deltas[1].clone()];
+        assert_eq!(complete_share_rows(all, 2, 4).map(|rows| rows.len()), Some(3));
+        assert!(complete_share_rows(vec![base.clone(), deltas[1].clone()], 2, 4).is_none());
+        assert!(complete_share_rows(vec![base.clone(), deltas[0].clone()], 2, 4).is_none());
+
+        // a delta issued for another share index can't be folded in
+        let (other, _) = vss::split(&Scalar::ZERO, 2, 3);
+        let foreign = encrypt_share(&holder_key.public_key(), &other[1]).unwrap();
+        assert!(combine_share(&holder_key, &base.ciphertext, &[foreign]).is_err());
+    }
+
+    #[test]
+    fn reshare_moves_key_to_four_of_seven() {
+        let split = SecretConfig { threshold: 3, shares: 5 }.generate_key_and_split().unwrap();
+        let recovered = SecretConfig::reconstruct_secret(&split.shares[..3], &split.commitments).unwrap();
+
+        let (shares, commitments) = split_keypair(&recovered.keypair, 4, 7).unwrap();
+        assert_eq!((shares.len(), commitments.threshold()), (7, 4));
+        assert_eq!(commitments.0[0], split.commitments.0[0]);
+        assert!(shares.iter().all(|share| commitments.verify_share(share)));
+
+        assert_eq!(SecretConfig::reconstruct_secret(&shares[3..], &commitments).unwrap().keypair.pubkey(), split.pubkey);
+        assert!(SecretConfig::reconstruct_secret(&shares[..3], &commitments).is_err());
+        // the old holder set's shares don't fit the new polynomial
+        assert!(!commitments.verify_share(&split.shares[0]));
+    }
+
+    #[test]
+    fn sealed_data_opens_only_with_recipient_key() {
+        let (recipient, other) = (SecretKey::generate(&mut OsRng), SecretKey::generate(&mut OsRng));
+        let sealed = seal(&recipient.public_key(), b"share").unwrap();
+        assert_eq!(unseal(&recipient, &sealed).unwrap(), b"share");
+        assert!(unseal(&other, &sealed).is_err());
+        assert!(unseal(&recipient, "not base64!").is_err());
+
+        let ceremony_key = SecretKey::generate(&mut OsRng);
+        let sealed_key = seal_ceremony_key(&recipient, &ceremony_key).unwrap();
+        assert_eq!(open_ceremony_key(&recipient, &sealed_key).unwrap().to_bytes(), ceremony_key.to_bytes());
+        assert!(open_ceremony_key(&other, &sealed_key).is_err());
+    }
+
+    #[test]
+    fn ceremony_recovers_key_and_reports_bad_shares() {
+        let split = SecretConfig { threshold: 2, shares: 3 }.generate_key_and_split().unwrap();
+        let key = custody_key(split.pubkey.to_string(), &split.commitments);
+        let holder_keys: Vec<SecretKey> = (0..3).map(|_| SecretKey::generate(&mut OsRng)).collect();
+        let rows: Vec<key_share::Model> = holder_keys
+            .iter()
+            .zip(split.shares.iter())
+            .map(|(holder_key, share)| share_row(key.id, holder_key, share))
+            .collect();
+
+        // the ceremony key round trips through its sealed form, as it does through redis
+        let seal_key = SecretKey::generate(&mut OsRng);
+        let ceremony_key = open_ceremony_key(&seal_key, &seal_ceremony_key(&seal_key, &SecretKey::generate(&mut OsRng)).unwrap()).unwrap();
+
+        // holders open their share and re-seal it to the ceremony key, the last one tampers with it
+        let mut submitted = HashMap::new();
+        for (index, (holder_key, row)) in holder_keys.iter().zip(rows.iter()).enumerate() {
+            let mut share = decrypt_share(holder_key, &row.ciphertext).unwrap();
+            if index == 2 {
+                share.value += Scalar::ONE;
+            }
+            submitted.insert(row.holder_id.to_string(), encrypt_share(&ceremony_key.public_key(), &share).unwrap());
+        }
+
+        let recovery = recover_keypair(&ceremony_key, &submitted, &rows, &key).unwrap();
+        assert_eq!(recovery.keypair.pubkey(), split.pubkey);
+        assert_eq!(recovery.rejected_holders, [rows[2].holder_id.to_string()]);
+
+        // a holder can't submit someone else's share as its own
+        let commitments = key_commitments(&key).unwrap();
+        let first = &submitted[&rows[0].holder_id.to_string()];
+        assert!(open_submitted_share(&ceremony_key, first, &rows[0], &commitments).is_ok());
+        assert!(open_submitted_share(&ceremony_key, first, &rows[1], &commitments).is_err());
+
+        // below threshold once the bad share is dropped
+        submitted.remove(&rows[0].holder_id.to_string());
+        assert!(recover_keypair(&ceremony_key, &submitted, &rows, &key).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::anyhow;
+use curve25519_dalek::edwards::EdwardsPoint;
+use frost_ed25519::{
+    keys::{KeyPackage, PublicKeyPackage, SecretShare, SigningShare, VerifiableSecretSharingCommitment},
+    round1::{self, NonceCommitment, SigningCommitments, SigningNonces},
+    round2::{self, SignatureShare},
+    Identifier, SigningPackage,
+};
+use rand::rngs::OsRng;
+
+use crate::shamir_secret::vss::{Commitments, VerifiableShare};
+
+// FROST(Ed25519, SHA-512) from RFC 9591, through the frost-ed25519 crate. Custody keys are still
+// shared with the Feldman commitments in vss: a share's index is its FROST identifier and the
+// commitments are the crate's VSS commitment, so a stored share signs as is. The aggregate is a
+// plain Ed25519 signature under the group key, so the vault's ed25519 check accepts it without
+// anyone ever rebuilding the secret.
+
+pub fn identifier(index: u8) -> Result<Identifier, anyhow::Error> {
+    Identifier::try_from(index as u16).map_err(|e| anyhow!("invalid signer index {} : {}", index, e))
+}
+
+fn decode_bytes(encoded: &str) -> Result<[u8; 32], anyhow::Error> {
+    bs58::decode(encoded)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| anyhow!("{} must be 32 bytes", encoded))
+}
+
+// The group key is the commitment to the constant term, the secret itself is never formed
+pub fn group_key(commitments: &Commitments) -> EdwardsPoint {
+    commitments.0[0]
+}
+
+fn vss_commitment(commitments: &Commitments) -> Result<VerifiableSecretSharingCommitment, anyhow::Error> {
+    Ok(VerifiableSecretSharingCommitment::deserialize(
+        commitments.0.iter().map(|point| point.compress().to_bytes()),
+    )?)
+}
+
+fn signing_share(share: &VerifiableShare) -> Result<SigningShare, anyhow::Error> {
+    Ok(SigningShare::deserialize(share.value.as_bytes())?)
+}
+
+// What a holder signs with, the share checked against the key's commitments
+pub fn key_package(share: &VerifiableShare, commitments: &Commitments) -> Result<KeyPackage, anyhow::Error> {
+    let secret_share = SecretShare::new(identifier(share.index)?, signing_share(share)?, vss_commitment(commitments)?);
+    Ok(KeyPackage::try_from(secret_share)?)
+}
+
+// A signer's round 1 commitment as relayed by the routes, base58 compressed points
+pub fn parse_commitment(hiding: &str, binding: &str) -> Result<SigningCommitments, anyhow::Error> {
+    Ok(SigningCommitments::new(
+        NonceCommitment::deserialize(&decode_bytes(hiding)?)?,
+        NonceCommitment::deserialize(&decode_bytes(binding)?)?,
+    ))
+}
+
+pub fn parse_signature_share(encoded: &str) -> Result<SignatureShare, anyhow::Error> {
+    Ok(SignatureShare::deserialize(&decode_bytes(encoded)?)?)
+}
+
+fn signing_package(commitments: &[(u8, SigningCommitments)], message: &[u8]) -> Result<SigningPackage, anyhow::Error> {
+    let commitments = commitments
+        .iter()
+        .map(|(index, commitment)| Ok((identifier(*index)?, *commitment)))
+        .collect::<Result<BTreeMap<_, _>, anyhow::Error>>()?;
+    Ok(SigningPackage::new(commitments, message))
+}
+
+// Round 1, on the holder's device: fresh nonces for a single signature, they must never be reused
+pub fn commit(share: &VerifiableShare) -> Result<(SigningNonces, SigningCommitments), anyhow::Error> {
+    Ok(round1::commit(&signing_share(share)?, &mut OsRng))
+}
+
+// Round 2, on the holder's device: the signature share over message, given every signer's commitment
+pub fn sign(
+    key_package: &KeyPackage,
+    nonces: &SigningNonces,
+    commitments: &[(u8, SigningCommitments)],
+    message: &[u8],
+) -> Result<SignatureShare, anyhow::Error> {
+    Ok(round2::sign(&signing_package(commitments, message)?, nonces, key_package)?)
+}
+
+pub enum AggregateError {
+    // signature shares that don't verify, by signer index, so the culprits can be excluded
+    InvalidShares(Vec<u8>),
+    Other(anyhow::Error),
+}
+
+impl std::fmt::Display for AggregateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AggregateError::InvalidShares(indexes) => write!(f, "invalid signature shares from signers {:?}", indexes),
+            AggregateError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+// Checks each signature share against the signer's verifying share from the key's commitments and
+// combines them into an Ed25519 signature (R || z)
+pub fn aggregate(
+    commitments: &[(u8, SigningCommitments)],
+    signature_shares: &[(u8, SignatureShare)],
+    key_commitments: &Commitments,
+    message: &[u8],
+) -> Result<[u8; 64], AggregateError> {
+    let signers: Vec<u8> = commitments.iter().map(|(index, _)| *index).collect();
+    if signature_shares.len() != signers.len() || signature_shares.iter().any(|(index, _)| !signers.contains(index)) {
+        return Err(AggregateError::Other(anyhow!("need exactly one signature share per committed signer")));
+    }
+
+    let prepared = (|| {
+        let identifiers = signers.iter().map(|index| identifier(*index)).collect::<Result<BTreeSet<_>, _>>()?;
+        let public_keys = PublicKeyPackage::from_commitment(&identifiers, &vss_commitment(key_commitments)?)?;
+        let shares = signature_shares
+            .iter()
+            .map(|(index, share)| Ok((identifier(*index)?, *share)))
+            .collect::<Result<BTreeMap<_, _>, anyhow::Error>>()?;
+        Ok::<_, anyhow::Error>((signing_package(commitments, message)?, shares, public_keys))
+    })();
+    let (package, shares, public_keys) = prepared.map_err(AggregateError::Other)?;
+
+    match frost_ed25519::aggregate(&package, &shares, &public_keys) {
+        Ok(signature) => {
+            let bytes = signature.serialize().map_err(|e| AggregateError::Other(e.into()))?;
+            bytes.try_into().map_err(|_| AggregateError::Other(anyhow!("signature must be 64 bytes")))
+        }
+        Err(frost_ed25519::Error::InvalidSignatureShare { culprit }) => Err(AggregateError::InvalidShares(
+            signers.into_iter().filter(|index| identifier(*index).ok() == Some(culprit)).collect(),
+        )),
+        Err(e) => Err(AggregateError::Other(e.into())),
+    }
+}
+
+// Distributed key generation is frost-ed25519's keys::dkg, run by the holders on their own devices.
+// The coordinator only relays round 1 packages, checks their shape and derives the group
+// commitments from them once every participant has finished part3, which verifies every proof of
+// knowledge and every sub share.
+pub mod dkg {
+    use super::*;
+    use frost_ed25519::keys::dkg::round1::Package;
+
+    pub fn decode_round1(bytes: &[u8], threshold: u8) -> Result<Package, anyhow::Error> {
+        let package = Package::deserialize(bytes)?;
+        if package.commitment().serialize()?.len() != threshold as usize {
+            return Err(anyhow!("round 1 package must commit to {} coefficients", threshold));
+        }
+        Ok(package)
+    }
+
+    // The package commitments summed, the coordinator's view of the generated key
+    pub fn group_commitments(packages: &[Package]) -> Result<Commitments, anyhow::Error> {
+        let mut group: Option<Commitments> = None;
+        for package in packages {
+            let encoded: Vec<String> = package
+                .commitment()
+                .serialize()?
+                .iter()
+                .map(|point| bs58::encode(point).into_string())
+                .collect();
+            let commitments = Commitments::decode(&encoded)?;
+            group = Some(match group {
+                Some(group) => group.add(&commitments)?,
+                None => commitments,
+            });
+        }
+        group.ok_or_else(|| anyhow!("no round 1 packages"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payout::instruction::{withdrawal_instructions_with_signatures, WithdrawalMessage};
+    use crate::shamir_secret::vss;
+    use curve25519_dalek::scalar::Scalar;
+    use frost_ed25519::keys::dkg as frost_dkg;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn sign_with(shares: &[VerifiableShare], key_commitments: &Commitments, message: &[u8]) -> Result<[u8; 64], AggregateError> {
+        let (nonces, commitments): (Vec<_>, Vec<_>) = shares
+            .iter()
+            .map(|share| {
+                let (nonces, commitment) = commit(share).unwrap();
+                (nonces, (share.index, commitment))
+            })
+            .unzip();
+        let signature_shares: Vec<(u8, SignatureShare)> = shares
+            .iter()
+            .zip(nonces)
+            .map(|(share, nonces)| {
+                let key_package = key_package(share, key_commitments).unwrap();
+                (share.index, sign(&key_package, &nonces, &commitments, message).unwrap())
+            })
+            .collect();
+        aggregate(&commitments, &signature_shares, key_commitments, message)
+    }
+
+    fn verifies(signature: &[u8; 64], key_commitments: &Commitments, message: &[u8]) -> bool {
+        let public_key = ed25519_dalek::PublicKey::from_bytes(group_key(key_commitments).compress().as_bytes()).unwrap();
+        let signature = ed25519_dalek::Signature::from_bytes(signature).unwrap();
+        public_key.verify_strict(message, &signature).is_ok()
+    }
+
+    #[test]
+    fn aggregate_is_an_ed25519_signature() {
+        let (shares, key_commitments) = vss::split(&Scalar::random(&mut OsRng), 2, 3);
+        let message = WithdrawalMessage {
+            vault: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            amount: 5_000_000,
+            nonce: 7,
+            expiry_slot: 1_000,
+        };
+        let message_bytes = borsh::to_vec(&message).unwrap();
+
+        let signature = sign_with(&[shares[2].clone(), shares[0].clone()], &key_commitments, &message_bytes).ok().unwrap();
+        assert!(verifies(&signature, &key_commitments, &message_bytes));
+        assert!(!verifies(&signature, &key_commitments, b"another message"));
+
+        // the vault's ed25519 check carries the aggregate and the group key as the operator
+        let operator = Pubkey::new_from_array(group_key(&key_commitments).compress().to_bytes());
+        let instructions =
+            withdrawal_instructions_with_signatures(&Pubkey::new_unique(), &[(operator, signature)], &Pubkey::new_unique(), &message).unwrap();
+        assert_eq!(instructions.len(), 2);
+        let data = &instructions[0].data;
+        assert!(data.windows(64).any(|window| window == signature));
+        assert!(data.windows(32).any(|window| window == operator.as_ref()));
+        assert!(data.ends_with(&message_bytes));
+    }
+
+    #[test]
+    fn dkg_round_trip_signs_under_group_key() {
+        let (threshold, indexes) = (2u8, [1u8, 2, 3]);
+        let (secrets, packages): (Vec<_>, Vec<_>) = indexes
+            .iter()
+            .map(|&index| frost_dkg::part1(identifier(index).unwrap(), indexes.len() as u16, threshold as u16, OsRng).unwrap())
+            .unzip();
+        // what the coordinator relays and checks, packages travel serialized
+        let relayed: Vec<_> = packages
+            .iter()
+            .map(|package| dkg::decode_round1(&package.serialize().unwrap(), threshold).unwrap())
+            .collect();
+        assert!(dkg::decode_round1(&packages[0].serialize().unwrap(), threshold + 1).is_err());
+
+        let others = |me: usize| -> BTreeMap<Identifier, frost_dkg::round1::Package> {
+            indexes
+                .iter()
+                .zip(&relayed)
+                .enumerate()
+                .filter(|(position, _)| *position != me)
+                .map(|(_, (index, package))| (identifier(*index).unwrap(), package.clone()))
+                .collect()
+        };
+        let round2: Vec<_> = secrets
+            .into_iter()
+            .enumerate()
+            .map(|(me, secret)| frost_dkg::part2(secret, &others(me)).unwrap())
+            .collect();
+        let key_packages: Vec<KeyPackage> = round2
+            .iter()
+            .enumerate()
+            .map(|(me, (secret, _))| {
+                let received: BTreeMap<Identifier, frost_dkg::round2::Package> = round2
+                    .iter()
+                    .enumerate()
+                    .filter(|(sender, _)| *sender != me)
+                    .map(|(sender, (_, sent))| (identifier(indexes[sender]).unwrap(), sent[&identifier(indexes[me]).unwrap()].clone()))
+                    .collect();
+                frost_dkg::part3(secret, &others(me), &received).unwrap().0
+            })
+            .collect();
+
+        // every participant and the coordinator agree on the key, and no one ever held it
+        let key_commitments = dkg::group_commitments(&relayed).unwrap();
+        let shares: Vec<VerifiableShare> = indexes
+            .iter()
+            .zip(&key_packages)
+            .map(|(&index, key_package)| {
+                let value: [u8; 32] = key_package.signing_share().serialize().try_into().unwrap();
+                VerifiableShare { index, value: Scalar::from_canonical_bytes(value).unwrap() }
+            })
+            .collect();
+        assert!(key_packages
+            .iter()
+            .all(|key_package| key_package.verifying_key().serialize().unwrap() == group_key(&key_commitments).compress().to_bytes()));
+        assert!(shares.iter().all(|share| key_commitments.verify_share(share)));
+
+        let signature = sign_with(&shares[1..], &key_commitments, b"withdrawal").ok().unwrap();
+        assert!(verifies(&signature, &key_commitments, b"withdrawal"));
+    }
+
+    #[test]
+    fn invalid_signature_shares_are_flagged() {
+        let (shares, key_commitments) = vss::split(&Scalar::random(&mut OsRng), 2, 3);
+        let signers = [shares[0].clone(), shares[1].clone()];
+        let (nonces, commitments): (Vec<_>, Vec<_>) = signers
+            .iter()
+            .map(|share| {
+                let (nonces, commitment) = commit(share).unwrap();
+                (nonces, (share.index, commitment))
+            })
+            .unzip();
+        let mut signature_shares: Vec<(u8, SignatureShare)> = signers
+            .iter()
+            .zip(&nonces)
+            .map(|(share, nonces)| {
+                let key_package = key_package(share, &key_commitments).unwrap();
+                (share.index, sign(&key_package, nonces, &commitments, b"withdrawal").unwrap())
+            })
+            .collect();
+        // a share over another message is a valid scalar that doesn't verify here
+        let other_message = sign(&key_package(&signers[1], &key_commitments).unwrap(), &nonces[1], &commitments, b"other").unwrap();
+        signature_shares[1].1 = other_message;
+
+        match aggregate(&commitments, &signature_shares, &key_commitments, b"withdrawal") {
+            Err(AggregateError::InvalidShares(rejected)) => assert_eq!(rejected, vec![signers[1].index]),
+            Err(e) => panic!("unexpected error : {}", e),
+            Ok(_) => panic!("tampered share was aggregated"),
+        }
+
+        // a share signed by someone outside the committed set isn't accepted either
+        signature_shares[1].0 = shares[2].index;
+        assert!(matches!(
+            aggregate(&commitments, &signature_shares, &key_commitments, b"withdrawal"),
+            Err(AggregateError::Other(_))
+        ));
+
+        // nor is a share that isn't on the holder's key
+        let foreign = VerifiableShare { index: shares[0].index, value: shares[0].value + Scalar::ONE };
+        assert!(key_package(&foreign, &key_commitments).is_err());
+    }
+}
@@ -1 +1,4 @@
-pub mod secret;
\ No newline at end of file
+pub mod secret;
+pub mod custody;
+pub mod vss;
+pub mod frost;
@@ -1,7 +1,24 @@
-use sharks::{Share, Sharks};
-use solana_sdk::{signature::{Keypair,Signer}, signer::SeedDerivable};
+use curve25519_dalek::scalar::Scalar;
+use rand::rngs::OsRng;
+use solana_sdk::{pubkey::Pubkey, signature::{Keypair,Signer}, signer::SeedDerivable};
 use anyhow::anyhow;
 
+use crate::shamir_secret::frost;
+use crate::shamir_secret::vss::{self, Commitments, VerifiableShare};
+
+// A freshly generated key, only its public half, the shares of its seed and the commitments
+// needed to verify those shares survive the split
+pub struct SplitKey{
+    pub pubkey : Pubkey,
+    pub shares : Vec<VerifiableShare>,
+    pub commitments : Commitments
+}
+
+pub struct RecoveredKey{
+    pub keypair : Keypair,
+    pub rejected : Vec<u8> // indexes of the shares that failed verification and were left out
+}
+
 pub struct SecretConfig{
     pub threshold : u8,
     pub shares : u8
@@ -12,17 +29,29 @@ impl SecretConfig {
         Self { threshold: 3, shares: 5 }
     }
 
-    pub fn generate_key_and_split(&self) -> Result<Vec<Share>, anyhow::Error>{
-        let keypair = Keypair::new();
+    // The seed is drawn as a canonical scalar so it can be shared over the scalar field, the
+    // keypair itself is still derived from it like any other ed25519 seed
+    pub fn generate_key_and_split(&self) -> Result<SplitKey, anyhow::Error>{
+        let seed = Scalar::random(&mut OsRng);
+        let keypair = Keypair::from_seed(seed.as_bytes())
+            .map_err(|e| anyhow!("error creating keypair from seed : {}", e))?;
         let pubkey = keypair.pubkey();
 
-        let secret_bytes = keypair.to_bytes();
-        let shares = self.split_secret(&secret_bytes[0..32])?;
+        let (shares, commitments) = self.split_secret(&seed)?;
         println!("created keypair and pubkey is : {}", pubkey);
-        Ok(shares)
+        Ok(SplitKey { pubkey, shares, commitments })
     }
 
-    pub fn split_secret(&self, secret : &[u8]) -> Result<Vec<Share>, anyhow::Error>{
+    // A key for FROST threshold signing: the secret is a plain scalar whose public point is the
+    // key itself, so the shares can sign but can't be turned back into a solana Keypair
+    pub fn generate_threshold_key_and_split(&self) -> Result<SplitKey, anyhow::Error>{
+        let (shares, commitments) = self.split_secret(&Scalar::random(&mut OsRng))?;
+        let pubkey = Pubkey::from(frost::group_key(&commitments).compress().to_bytes());
+        println!("created threshold key and pubkey is : {}", pubkey);
+        Ok(SplitKey { pubkey, shares, commitments })
+    }
+
+    pub fn split_secret(&self, secret : &Scalar) -> Result<(Vec<VerifiableShare>, Commitments), anyhow::Error>{
         if self.threshold > self.shares{
             return Err(anyhow!("threshold cannot be greater than total shares"));
         }
@@ -31,31 +60,26 @@ impl SecretConfig {
             return Err(anyhow!("minimum threshold must be 3"));
         }
 
-        let sharks = Sharks(self.threshold);
-        let dealer = sharks.dealer(secret);
-        let shares : Vec<Share> = dealer.take(self.shares as usize).collect();
-
-        Ok(shares)
+        Ok(vss::split(secret, self.threshold, self.shares))
     }
 
-    pub fn reconstruct_secret(shares : &[Share], threshold : u8) -> Result<Keypair, anyhow::Error>{
+    // Shares that don't match the commitments are excluded, so a holder submitting garbage is
+    // identified instead of silently corrupting the key
+    pub fn reconstruct_secret(shares : &[VerifiableShare], commitments : &Commitments) -> Result<RecoveredKey, anyhow::Error>{
         if shares.is_empty(){
             return Err(anyhow!("No shares present"));
         }
 
-        if shares.len() < threshold as usize {
-            return Err(anyhow!("insufficient shares : got {} need {}", shares.len(), threshold));
+        if shares.len() < commitments.threshold() {
+            return Err(anyhow!("insufficient shares : got {} need {}", shares.len(), commitments.threshold()));
         }
 
-        let sharks = Sharks(threshold);
-        let secret = sharks.recover(shares).map_err(|e| anyhow::anyhow!("failed to recover secret : {} ", e))?;
-        if secret.len() != 32 {
-            return Err(anyhow!("Reconstructed secret has invalid length"));
+        let reconstruction = vss::reconstruct(shares, commitments)?;
+        if !reconstruction.rejected.is_empty() {
+            println!("Rejected shares with indexes {:?}", reconstruction.rejected);
         }
-        let mut pvt_key = [0u8 ; 32];
-        pvt_key.copy_from_slice(&secret);
-        
-        let keypair = match Keypair::from_seed(&pvt_key){
+
+        let keypair = match Keypair::from_seed(reconstruction.secret.as_bytes()){
             Ok(keypair) => {
                 keypair
             }
@@ -66,6 +90,6 @@ impl SecretConfig {
 
         println!("Succesfully reconstructed keypair");
         println!("Public for keypair is : {}", keypair.pubkey());
-        Ok(keypair)
+        Ok(RecoveredKey { keypair, rejected: reconstruction.rejected })
     }
-} 
\ No newline at end of file
+}
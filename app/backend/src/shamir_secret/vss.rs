@@ -0,0 +1,225 @@
+use anyhow::anyhow;
+use curve25519_dalek::{
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    traits::Identity,
+};
+use rand::rngs::OsRng;
+
+// Feldman verifiable secret sharing over the ed25519 scalar field. Splitting publishes a commitment
+// a_j*G to every polynomial coefficient, so anyone holding the commitments can check a share
+// (i, f(i)) with f(i)*G == sum(C_j * i^j) without learning anything about the secret.
+
+pub const SHARE_LEN: usize = 33; // 1 byte x-coordinate, 32 byte little endian scalar
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerifiableShare {
+    pub index: u8, // x-coordinate, never 0
+    pub value: Scalar,
+}
+
+impl VerifiableShare {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SHARE_LEN);
+        bytes.push(self.index);
+        bytes.extend_from_slice(self.value.as_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        if bytes.len() != SHARE_LEN || bytes[0] == 0 {
+            return Err(anyhow!("share must be {} bytes with a non zero index", SHARE_LEN));
+        }
+        let mut value = [0u8; 32];
+        value.copy_from_slice(&bytes[1..]);
+        let value = Option::<Scalar>::from(Scalar::from_canonical_bytes(value))
+            .ok_or_else(|| anyhow!("share value is not a canonical scalar"))?;
+        Ok(Self { index: bytes[0], value })
+    }
+}
+
+// Commitments to the coefficients, the first one commits to the secret itself
+#[derive(Clone, Debug, PartialEq)]
+pub struct Commitments(pub Vec<EdwardsPoint>);
+
+impl Commitments {
+    pub fn threshold(&self) -> usize {
+        self.0.len()
+    }
+
+    // base58 compressed points, the encoding published to holders and stored with the key
+    pub fn encode(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|point| bs58::encode(point.compress().as_bytes()).into_string())
+            .collect()
+    }
+
+    pub fn decode(encoded: &[String]) -> Result<Self, anyhow::Error> {
+        let points = encoded
+            .iter()
+            .map(|point| {
+                let bytes: [u8; 32] = bs58::decode(point)
+                    .into_vec()?
+                    .try_into()
+                    .map_err(|_| anyhow!("commitment must be 32 bytes"))?;
+                CompressedEdwardsY(bytes)
+                    .decompress()
+                    .ok_or_else(|| anyhow!("commitment {} is not a curve point", point))
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+        if points.is_empty() {
+            return Err(anyhow!("no commitments"));
+        }
+        Ok(Self(points))
+    }
+
+    // f(i)*G computed from the commitments alone, the public half of share i
+    pub fn verifying_share(&self, index: u8) -> EdwardsPoint {
+        let x = Scalar::from(index as u64);
+        self.0
+            .iter()
+            .rev()
+            .fold(EdwardsPoint::identity(), |acc, commitment| acc * x + commitment)
+    }
+
+    pub fn verify_share(&self, share: &VerifiableShare) -> bool {
+        share.index != 0 && EdwardsPoint::mul_base(&share.value) == self.verifying_share(share.index)
+    }
+
+    pub fn verify_secret(&self, secret: &Scalar) -> bool {
+        EdwardsPoint::mul_base(secret) == self.0[0]
+    }
+
+    // Commitments to the sum of two polynomials of the same degree, used when a refresh adds a
+    // sharing of zero to every share
+    pub fn add(&self, other: &Commitments) -> Result<Commitments, anyhow::Error> {
+        if self.threshold() != other.threshold() {
+            return Err(anyhow!("commitments are for different thresholds"));
+        }
+        Ok(Commitments(self.0.iter().zip(other.0.iter()).map(|(a, b)| a + b).collect()))
+    }
+}
+
+pub fn split(secret: &Scalar, threshold: u8, shares: u8) -> (Vec<VerifiableShare>, Commitments) {
+    deal(secret, threshold, &(1..=shares).collect::<Vec<u8>>())
+}
+
+// Deals shares of secret at the given x-coordinates, which must be distinct and non zero
+pub fn deal(secret: &Scalar, threshold: u8, indexes: &[u8]) -> (Vec<VerifiableShare>, Commitments) {
+    let mut coefficients = vec![*secret];
+    coefficients.extend((1..threshold).map(|_| Scalar::random(&mut OsRng)));
+
+    let commitments = Commitments(coefficients.iter().map(EdwardsPoint::mul_base).collect());
+    let shares = indexes
+        .iter()
+        .map(|&index| {
+            let x = Scalar::from(index as u64);
+            let value = coefficients.iter().rev().fold(Scalar::ZERO, |acc, coefficient| acc * x + coefficient);
+            VerifiableShare { index, value }
+        })
+        .collect();
+    (shares, commitments)
+}
+
+// Lagrange interpolation at 0, the shares must have distinct indexes
+pub fn interpolate(shares: &[VerifiableShare]) -> Scalar {
+    shares.iter().fold(Scalar::ZERO, |secret, share| {
+        let xi = Scalar::from(share.index as u64);
+        let (numerator, denominator) = shares
+            .iter()
+            .filter(|other| other.index != share.index)
+            .fold((Scalar::ONE, Scalar::ONE), |(num, den), other| {
+                let xj = Scalar::from(other.index as u64);
+                (num * xj, den * (xj - xi))
+            });
+        secret + share.value * numerator * denominator.invert()
+    })
+}
+
+pub struct Reconstruction {
+    pub secret: Scalar,
+    pub rejected: Vec<u8>, // indexes of shares that failed verification
+}
+
+// Checks every share against the commitments, drops the bad ones and rebuilds the secret from the
+// valid rest. Fails only when fewer than threshold valid shares remain.
+pub fn reconstruct(shares: &[VerifiableShare], commitments: &Commitments) -> Result<Reconstruction, anyhow::Error> {
+    let mut valid: Vec<VerifiableShare> = Vec::with_capacity(shares.len());
+    let mut rejected = Vec::new();
+    for share in shares {
+        if !commitments.verify_share(share) {
+            rejected.push(share.index);
+        } else if !valid.iter().any(|seen| seen.index == share.index) {
+            valid.push(share.clone());
+        }
+    }
+
+    if valid.len() < commitments.threshold() {
+        return Err(anyhow!(
+            "insufficient valid shares : got {} need {}, rejected {:?}",
+            valid.len(),
+            commitments.threshold(),
+            rejected
+        ));
+    }
+
+    let secret = interpolate(&valid[..commitments.threshold()]);
+    if !commitments.verify_secret(&secret) {
+        return Err(anyhow!("reconstructed secret does not match its commitment"));
+    }
+    Ok(Reconstruction { secret, rejected })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_and_interpolate_round_trip() {
+        let secret = Scalar::random(&mut OsRng);
+        let (shares, commitments) = split(&secret, 3, 5);
+        assert_eq!(commitments.threshold(), 3);
+        assert!(commitments.verify_secret(&secret));
+        assert!(shares.iter().all(|share| commitments.verify_share(share)));
+
+        // any threshold subset gives the secret back, fewer shares give something else
+        assert_eq!(interpolate(&shares[..3]), secret);
+        assert_eq!(interpolate(&[shares[4].clone(), shares[0].clone(), shares[2].clone()]), secret);
+        assert_ne!(interpolate(&shares[..2]), secret);
+
+        let share = VerifiableShare::from_bytes(&shares[1].to_bytes()).unwrap();
+        assert_eq!(share, shares[1]);
+        assert_eq!(Commitments::decode(&commitments.encode()).unwrap(), commitments);
+    }
+
+    #[test]
+    fn verify_share_rejects_tampered_share() {
+        let (shares, commitments) = split(&Scalar::random(&mut OsRng), 2, 3);
+        let mut tampered = shares[0].clone();
+        tampered.value += Scalar::ONE;
+        assert!(!commitments.verify_share(&tampered));
+
+        // a valid value presented under another index fails too
+        let moved = VerifiableShare { index: shares[1].index, value: shares[0].value };
+        assert!(!commitments.verify_share(&moved));
+        assert!(!commitments.verify_share(&VerifiableShare { index: 0, value: shares[0].value }));
+        assert!(VerifiableShare::from_bytes(&[0u8; SHARE_LEN]).is_err());
+    }
+
+    #[test]
+    fn reconstruct_excludes_bad_shares() {
+        let secret = Scalar::random(&mut OsRng);
+        let (mut shares, commitments) = split(&secret, 3, 5);
+        shares[0].value += Scalar::ONE;
+        shares[3].value = Scalar::random(&mut OsRng);
+
+        let reconstruction = reconstruct(&shares, &commitments).unwrap();
+        assert_eq!(reconstruction.secret, secret);
+        assert_eq!(reconstruction.rejected, [1, 4]);
+
+        // a duplicate of a good share doesn't count twice towards the threshold
+        let short = [shares[0].clone(), shares[1].clone(), shares[1].clone(), shares[2].clone()];
+        assert!(reconstruct(&short, &commitments).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use crypto_box::SecretKey;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use uuid::Uuid;
+
+use crate::redis::shares_manager::ShareStorage;
+
+// State behind /custody. Nothing secret is held in memory between requests: ceremony keys sit in
+// redis sealed to `seal_key`, recovered keys sealed to the admin who started the recovery.
+#[derive(Clone)]
+pub struct CustodyState {
+    pub db: sea_orm::DatabaseConnection,
+    pub share_storage: Arc<ShareStorage>,
+    pub seal_key: Arc<SecretKey>, // CUSTODY_SEAL_KEY, every instance serving /custody needs the same one
+    pub admins: Arc<Vec<Uuid>>, // users allowed to create keys and start recoveries
+    pub program_id: Pubkey,     // d-uptime program the threshold withdrawals are built for
+}
+
+// A running recovery, kept in redis until it completes or expires
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ceremony {
+    pub ceremony_id: String,
+    pub key_id: Uuid,
+    pub public_key: String,
+    pub threshold: u8,
+    pub ceremony_public_key: String, // base64 X25519 key holders re-seal their share to
+    pub started_by: Uuid,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default)]
+    pub epoch: i32, // shares are checked against this epoch's commitments
+    #[serde(default)]
+    pub reshare: Option<ReshareTarget>, // set when the ceremony deals the key to a new holder set
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReshareTarget {
+    pub holder_ids: Vec<Uuid>,
+    pub threshold: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterHolderRequest {
+    pub encryption_public_key: String, // base64 X25519 public key
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HolderResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub holder_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateKeyRequest {
+    pub holder_ids: Vec<Uuid>, // one share per holder
+    pub threshold: u8,
+    #[serde(default)]
+    pub scheme: Option<String>, // "seed" (default) or "frost" for a key that only ever signs by threshold
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustodyKeyResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub key_id: Option<Uuid>,
+    pub public_key: Option<String>,
+    pub threshold: Option<u8>,
+    pub epoch: Option<i32>,
+    pub scheme: Option<String>,
+    pub commitments: Option<Vec<String>>, // base58 Feldman commitments holders verify their share against
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustodyEpochRecord {
+    pub epoch: i32,
+    pub kind: String,
+    pub threshold: u8,
+    pub holder_ids: Vec<Uuid>,
+    pub commitments: Vec<String>,
+    pub initiated_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustodyEpochsResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub epochs: Vec<CustodyEpochRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HolderShare {
+    pub public_key: String,
+    pub share_index: u8,
+    pub threshold: u8,
+    pub epoch: i32,
+    pub ciphertext: String, // base64, sealed to the holder's encryption key
+    pub deltas: Vec<String>, // refresh deltas sealed the same way, the current share is ciphertext + sum(deltas)
+    pub commitments: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HolderShareResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub share: Option<HolderShare>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StartCeremonyRequest {
+    pub public_key: String,
+    #[serde(default)]
+    pub reshare: Option<ReshareTarget>, // deal the key to these holders instead of recovering it
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CeremonyResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub ceremony: Option<Ceremony>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitShareRequest {
+    pub ciphertext: String, // base64 share re-sealed to the ceremony key
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveredKeyResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub ciphertext: Option<String>, // base64 64 byte keypair sealed to the caller's encryption key
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitShareResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub collected: usize,
+    pub threshold: u8,
+    pub recovered: bool,
+    pub rejected_holders: Vec<String>, // holders whose share was excluded from the recovery
+}
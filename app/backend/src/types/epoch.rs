@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EpochProof {
+    pub epoch_id: u64,
+    pub leaf_index: u32,
+    pub wallet_address: String,
+    pub amount: u64, // lamports
+    pub merkle_root: String,
+    pub expiry_slot: u64,
+    pub proof: Vec<String>, // base58 sibling hashes, leaf to root
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EpochProofResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub proof: Option<EpochProof>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuildEpochRequest {
+    pub epoch_id: Option<u64>, // defaults to one past the latest stored epoch
+    pub expiry_slot: u64,
+}
+
+// What the operator set signs (OperatorAction::PostEpoch) and posts on-chain with `post_epoch`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuiltEpochSummary {
+    pub epoch_id: u64,
+    pub merkle_root: String, // base58
+    pub total_amount: u64,   // lamports
+    pub leaf_count: u32,
+    pub expiry_slot: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuildEpochResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub epoch: Option<BuiltEpochSummary>,
+}
@@ -2,4 +2,8 @@ pub mod user;
 pub mod website;
 pub mod performance_data;
 pub mod notification;
-pub mod redis;
\ No newline at end of file
+pub mod redis;
+pub mod epoch;
+pub mod custody;
+pub mod threshold;
+pub mod reward;
@@ -1,11 +1,9 @@
-use sea_orm::prelude::DateTimeWithTimeZone;
 use serde::{Deserialize, Serialize};
 
+// The validator and the time come from the caller's JWT and the server clock
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PerfomanceDataInput{
-    pub validator_id : String,
     pub website_id: String,
-    pub timestamp: DateTimeWithTimeZone,
     pub http_status_code: Option<f64>,
     pub dns_resolution_ms: Option<f64>,
     pub connection_time_ms: Option<f64>,
@@ -4,6 +4,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::redis::challenge_manager::ChallengeStore;
 use crate::redis::cookie_manager::SessionStore;
 use crate::redis::pubsub_manager::RedisPubSub;
 // App state that includes database and all the classes manager.
@@ -11,7 +12,8 @@ use crate::redis::pubsub_manager::RedisPubSub;
 pub struct AppState {
     pub db: sea_orm::DatabaseConnection,
     pub session_store: Arc<SessionStore>,
-    pub pubsub : Arc<RedisPubSub>
+    pub pubsub : Arc<RedisPubSub>,
+    pub challenge_store: Arc<ChallengeStore>
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,7 +28,11 @@ pub struct SessionData {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerMessage{
     pub url : String,
-    pub id : String
+    pub id : String,
+    #[serde(default)]
+    pub run_id : Option<String>, // set for scheduled checks
+    #[serde(default)]
+    pub validator_ids : Vec<String> // validators the run is assigned to, empty means everyone
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,4 +52,17 @@ pub struct PerformanceData{
     pub content_download: Option<f64>,
     pub total_duration: f64,
     pub status_code: u32,
-}
\ No newline at end of file
+}
+// A pending Sign-In-With-Solana challenge, kept in redis under its nonce until used or expired.
+// The purpose and the ids it was issued for keep a challenge from being redeemed anywhere else.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletChallenge {
+    pub wallet: String,
+    pub nonce: String,
+    pub purpose: String,
+    pub statement: String,
+    pub user_id: Option<Uuid>,
+    pub device_id: Option<String>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// One reward the engine credits (or would credit, in a dry run) for a contribution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewardCredit {
+    pub contribution_id: Uuid,
+    pub reward_type: String,
+    pub user_id: Uuid,
+    pub validator_id: Uuid,
+    pub amount: u64,        // lamports
+    pub consensus_bps: u64, // weight the peers' agreement earned, 10_000 is 1x
+    pub underserved: bool,  // validator sits in a region with few other validators
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RewardRun {
+    pub dry_run: bool,
+    pub credits: Vec<RewardCredit>,
+    pub already_credited: usize, // contributions that already have a reward of that type
+    pub rejected: usize,         // contributions that weren't accepted for a reward
+    pub inserted: u64,           // rows written, 0 in a dry run
+    pub total_amount: u64,       // lamports
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RewardPreviewResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub run: Option<RewardRun>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatusTotal {
+    pub status: String,
+    pub reward_count: i64,
+    pub total_amount: i64, // lamports
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RewardTypeCount {
+    pub reward_type: String,
+    pub reward_count: i64,
+    pub total_amount: i64, // lamports
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RewardSummaryResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub validator_id: Option<Uuid>,
+    pub total_amount: i64, // lamports
+    pub by_status: Vec<StatusTotal>,
+    pub by_type: Vec<RewardTypeCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DailyEarning {
+    pub day: NaiveDate,
+    pub reward_count: i64,
+    pub total_amount: i64, // lamports
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DailyEarningsQuery {
+    pub days: Option<u32>, // defaults to 30
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DailyEarningsResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub days: Vec<DailyEarning>, // oldest first, days without rewards are left out
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RewardHistoryQuery {
+    pub page: Option<u64>,     // starts at 0
+    pub per_page: Option<u64>, // defaults to 20, at most 100
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RewardHistoryItem {
+    pub id: Uuid,
+    pub amount: i64, // lamports
+    pub reward_type: String,
+    pub contribution_id: Option<Uuid>,
+    pub status: String,
+    pub transaction_hash: Option<String>,
+    pub epoch_id: Option<i64>,
+    pub created_at: DateTime<FixedOffset>,
+    pub processed_at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RewardHistoryResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub page: u64,
+    pub per_page: u64,
+    pub total_items: u64,
+    pub total_pages: u64,
+    pub rewards: Vec<RewardHistoryItem>,
+}
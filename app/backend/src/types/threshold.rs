@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Same fields as payout::instruction::WithdrawalMessage plus the vault's fee account, pubkeys in base58
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawalRequest {
+    pub vault: String,
+    pub recipient: String,
+    pub amount: u64, // lamports
+    pub nonce: u64,
+    pub expiry_slot: u64,
+    pub fee_account: String,
+}
+
+// A FROST signing round over one withdrawal message, kept in redis until it completes or expires
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigningSession {
+    pub session_id: String,
+    pub key_id: Uuid,
+    pub public_key: String,
+    pub threshold: u8,
+    pub epoch: i32,
+    pub withdrawal: WithdrawalRequest,
+    pub message: String, // base64 borsh WithdrawalMessage, the bytes being signed
+    pub started_by: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StartSigningRequest {
+    pub public_key: String,
+    pub withdrawal: WithdrawalRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigningCommitmentData {
+    pub index: u8,
+    pub hiding: String, // base58 compressed points
+    pub binding: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitCommitmentRequest {
+    pub hiding: String,
+    pub binding: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitSignatureShareRequest {
+    pub signature_share: String, // base58 scalar
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SigningSessionResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub session: Option<SigningSession>,
+    pub signers: Vec<u8>,                          // share indexes fixed once threshold commitments are in
+    pub commitments: Vec<SigningCommitmentData>,   // the signers' round 1 commitments
+    pub signature: Option<String>,                 // base58 aggregated ed25519 signature
+    pub instructions: Option<String>,              // base64 bincode ed25519 check and withdrawal, ready to submit
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SigningStepResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub collected: usize,
+    pub threshold: u8,
+    pub signature: Option<String>,
+    pub instructions: Option<String>,
+    pub rejected_signers: Vec<u8>, // share indexes whose signature share didn't verify
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DkgParticipant {
+    pub holder_id: Uuid,
+    pub index: u8,
+}
+
+// A distributed key generation among share holders, relayed through redis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DkgSession {
+    pub session_id: String,
+    pub threshold: u8,
+    pub participants: Vec<DkgParticipant>,
+    pub started_by: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StartDkgRequest {
+    pub holder_ids: Vec<Uuid>,
+    pub threshold: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Round1PackageData {
+    pub package: String, // base64 frost-ed25519 keys::dkg::round1::Package, as serialize() writes it
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DkgRound1Entry {
+    pub holder_id: Uuid,
+    pub index: u8,
+    pub package: Round1PackageData,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SealedSubShare {
+    pub holder_id: Uuid,    // recipient
+    pub ciphertext: String, // base64 share of the sender's polynomial, sealed to the recipient
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitRound2Request {
+    pub shares: Vec<SealedSubShare>, // one for every other participant
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfirmDkgRequest {
+    pub ciphertext: String, // the participant's final signing share, sealed to its own encryption key
+    #[serde(default)]
+    pub complaints: Vec<Uuid>, // senders whose share failed verification, aborts the session
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceivedSubShare {
+    pub from_holder_id: Uuid,
+    pub ciphertext: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DkgSessionResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub session: Option<DkgSession>,
+    pub round1: Vec<DkgRound1Entry>,
+    pub round2: Vec<ReceivedSubShare>, // sub shares addressed to the caller
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DkgStepResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub collected: usize,
+    pub expected: usize,
+    pub public_key: Option<String>, // set once the key is generated
+}
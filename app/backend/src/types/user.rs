@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -37,6 +38,8 @@ pub struct ValidatorInput {
     pub latitude: f64,
     pub longitude: f64,
     pub device_id: String,
+    pub message: String,   // the signed registration challenge
+    pub signature: String, // base58 signature of the message by wallet_address
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -61,6 +64,34 @@ pub struct VerifySignatureRequest {
     pub public_key: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletChallengeRequest {
+    pub public_key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistrationChallengeRequest {
+    pub public_key: String,
+    pub device_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletRebindResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub validator_id: Option<Uuid>,
+    pub wallet_address: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletChallengeResponse {
+    pub status_code: u32,
+    pub message: String,
+    pub sign_in_message: Option<String>, // the exact text the wallet has to sign
+    pub nonce: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SessionStatusResponse {
     pub status_code: u32,
@@ -4,7 +4,9 @@ use uuid::Uuid;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AddWebsiteInput{
     pub user_id: Uuid,
-    pub url_to_monitor : String
+    pub url_to_monitor : String,
+    #[serde(default)]
+    pub check_interval_secs : Option<i32> // defaults to 5 minutes
 }
 
 #[derive(Debug, Serialize, Deserialize)]
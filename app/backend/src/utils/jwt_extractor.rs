@@ -5,6 +5,7 @@ use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header,
 use serde::{Deserialize, Serialize};
 use std::env;
 
+#[derive(Debug, Clone)]
 pub struct AuthenticatedUser {
     pub user_id: Uuid,
     pub validator_id: Option<Uuid>,
@@ -56,6 +57,15 @@ pub fn get_jwt_secret() -> String {
     env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key".to_string())
 }
 
+// REWARD_ADMIN_IDS lists the users allowed to preview reward runs and build reward epochs
+pub fn is_reward_admin(user_id: Uuid) -> bool {
+    env::var("REWARD_ADMIN_IDS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|id| Uuid::parse_str(id.trim()).ok())
+        .any(|admin_id| admin_id == user_id)
+}
+
 pub fn create_jwt(user_id: Uuid, validator_id: Option<Uuid>) -> Result<String, jsonwebtoken::errors::Error> {
     let claims = Claims::new(user_id, validator_id);
     let secret = get_jwt_secret();
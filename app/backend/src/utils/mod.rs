@@ -1,2 +1,4 @@
 pub mod cookie_extractor;
-pub mod jwt_extractor;
\ No newline at end of file
+pub mod jwt_extractor;
+pub mod solana_rpc;
+pub mod siws;
\ No newline at end of file
@@ -0,0 +1,147 @@
+use anyhow::anyhow;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::env;
+use uuid::Uuid;
+
+use crate::types::redis::WalletChallenge;
+
+const SIWS_HEADER: &str = " wants you to sign in with your Solana account:";
+
+// What a challenge can be redeemed for
+pub const PURPOSE_SIGN_IN: &str = "sign_in";
+pub const PURPOSE_REGISTER: &str = "register";
+pub const PURPOSE_REBIND: &str = "rebind";
+
+pub fn sign_in_statement() -> String {
+    "Sign in to the decentralized uptime monitor as a validator.".to_string()
+}
+
+pub fn registration_statement(user_id: Uuid, device_id: &str) -> String {
+    format!("Register this wallet for validator rewards of user {} on device {}.", user_id, device_id)
+}
+
+pub fn rebind_statement(user_id: Uuid, validator_id: Uuid) -> String {
+    format!("Bind this wallet to validator {} of user {}, replacing its current wallet.", validator_id, user_id)
+}
+
+// Domain the sign in messages are issued for, wallets show it to the user before they sign
+pub fn get_siws_domain() -> String {
+    env::var("SIWS_DOMAIN").unwrap_or_else(|_| "localhost:3000".to_string())
+}
+
+// A Sign-In-With-Solana message, the text layout follows the SIWS / EIP-4361 format:
+//
+// {domain} wants you to sign in with your Solana account:
+// {address}
+//
+// {statement}
+//
+// Version: 1
+// Nonce: {nonce}
+// Issued At: {issued_at}
+// Expiration Time: {expiration_time}
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiwsMessage {
+    pub domain: String,
+    pub address: String,
+    pub statement: String,
+    pub version: String,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expiration_time: DateTime<Utc>,
+}
+
+impl SiwsMessage {
+    pub fn from_challenge(domain: String, challenge: &WalletChallenge) -> Self {
+        Self {
+            domain,
+            address: challenge.wallet.clone(),
+            statement: challenge.statement.clone(),
+            version: "1".to_string(),
+            nonce: challenge.nonce.clone(),
+            issued_at: challenge.issued_at,
+            expiration_time: challenge.expires_at,
+        }
+    }
+
+    pub fn to_message(&self) -> String {
+        format!(
+            "{}{}\n{}\n\n{}\n\nVersion: {}\nNonce: {}\nIssued At: {}\nExpiration Time: {}",
+            self.domain,
+            SIWS_HEADER,
+            self.address,
+            self.statement,
+            self.version,
+            self.nonce,
+            self.issued_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            self.expiration_time.to_rfc3339_opts(SecondsFormat::Millis, true),
+        )
+    }
+
+    pub fn parse(message: &str) -> Result<Self, anyhow::Error> {
+        let mut lines = message.lines();
+        let domain = lines
+            .next()
+            .and_then(|line| line.strip_suffix(SIWS_HEADER))
+            .ok_or_else(|| anyhow!("missing sign in header"))?
+            .to_string();
+        let address = lines.next().ok_or_else(|| anyhow!("missing address"))?.trim().to_string();
+
+        let mut statement = String::new();
+        let mut version = None;
+        let mut nonce = None;
+        let mut issued_at = None;
+        let mut expiration_time = None;
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            match line.split_once(": ") {
+                Some(("Version", value)) => version = Some(value.to_string()),
+                Some(("Nonce", value)) => nonce = Some(value.to_string()),
+                Some(("Issued At", value)) => issued_at = Some(parse_time(value)?),
+                Some(("Expiration Time", value)) => expiration_time = Some(parse_time(value)?),
+                _ if version.is_none() && statement.is_empty() => statement = line.to_string(),
+                _ => return Err(anyhow!("unexpected line in message : {}", line)),
+            }
+        }
+
+        Ok(Self {
+            domain,
+            address,
+            statement,
+            version: version.ok_or_else(|| anyhow!("missing version"))?,
+            nonce: nonce.ok_or_else(|| anyhow!("missing nonce"))?,
+            issued_at: issued_at.ok_or_else(|| anyhow!("missing issued at"))?,
+            expiration_time: expiration_time.ok_or_else(|| anyhow!("missing expiration time"))?,
+        })
+    }
+
+    // The signed message has to be exactly what the challenge handed out, for this domain and
+    // still within its validity window
+    pub fn check(&self, domain: &str, challenge: &WalletChallenge, now: DateTime<Utc>) -> Result<(), anyhow::Error> {
+        if self.domain != domain {
+            return Err(anyhow!("message was issued for {}", self.domain));
+        }
+        if self.version != "1" {
+            return Err(anyhow!("unsupported message version {}", self.version));
+        }
+        if self.address != challenge.wallet {
+            return Err(anyhow!("challenge was issued for another wallet"));
+        }
+        if self.statement != challenge.statement {
+            return Err(anyhow!("statement does not match the challenge"));
+        }
+        if self.nonce != challenge.nonce {
+            return Err(anyhow!("nonce does not match the challenge"));
+        }
+        if self.issued_at != challenge.issued_at || self.expiration_time != challenge.expires_at {
+            return Err(anyhow!("timestamps do not match the challenge"));
+        }
+        if now < self.issued_at || now >= self.expiration_time {
+            return Err(anyhow!("message is expired"));
+        }
+        Ok(())
+    }
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>, anyhow::Error> {
+    Ok(DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc))
+}
@@ -0,0 +1,32 @@
+use serde_json::{json, Value};
+
+// Minimal Solana JSON-RPC client over reqwest, shared by the indexer and the payout service.
+#[derive(Clone)]
+pub struct JsonRpcClient {
+    client: reqwest::Client,
+    rpc_url: String,
+}
+
+impl JsonRpcClient {
+    pub fn new(rpc_url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            rpc_url,
+        }
+    }
+
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        let response: Value = self
+            .client
+            .post(&self.rpc_url)
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        if let Some(error) = response.get("error") {
+            return Err(format!("{} failed : {}", method, error).into());
+        }
+        Ok(response["result"].clone())
+    }
+}
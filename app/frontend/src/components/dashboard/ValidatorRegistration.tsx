@@ -8,6 +8,7 @@ import { useWalletModal } from '@solana/wallet-adapter-react-ui';
 import { useAuth } from '../../contexts/AuthContext';
 import { fadeIn, slideUp } from '../../lib/framer-variants';
 import api from '@/lib/axios';
+import bs58 from 'bs58';
 
 interface LocationResult {
   latitude: number;
@@ -294,17 +295,25 @@ const ValidatorRegistration = ({ onComplete }: ValidatorRegistrationProps = {})
       const deviceId = 'device_' + Math.random().toString(36).substring(2, 10);
       console.log('🔧 Generated device ID:', deviceId);
 
-      // 3. Create a validation message
-      const message = `I am registering as a validator with device ${deviceId} at coordinates ${location.latitude.toFixed(6)},${location.longitude.toFixed(6)}`;
+      // 3. Ask the backend for a registration challenge bound to this user and device
+      const walletAddress = publicKey?.toString() || '';
+      const challengeResponse = await api.post('/validator/registration-challenge', {
+        public_key: walletAddress,
+        device_id: deviceId
+      });
+      if (challengeResponse.data?.status_code !== 200 || !challengeResponse.data.sign_in_message) {
+        throw new Error(challengeResponse.data?.message || 'Could not get a registration challenge');
+      }
+      const message: string = challengeResponse.data.sign_in_message;
 
-      // 4. Sign the message with the wallet
+      // 4. Sign the challenge with the wallet to prove we control it
       if (!signMessage) {
         throw new Error("Wallet doesn't support message signing");
       }
 
       console.log('✍️ Signing message with wallet...');
       const messageBytes = new TextEncoder().encode(message);
-      await signMessage(messageBytes);
+      const signature = await signMessage(messageBytes);
       
       // 5. Register as validator with the API
       console.log('📡 Sending registration to backend...');
@@ -317,10 +326,12 @@ const ValidatorRegistration = ({ onComplete }: ValidatorRegistrationProps = {})
 
       const requestPayload = {
         user_id: userId,
-        wallet_address: publicKey?.toString() || '',
+        wallet_address: walletAddress,
         latitude: location.latitude,
         longitude: location.longitude,
-        device_id: deviceId
+        device_id: deviceId,
+        message,
+        signature: bs58.encode(signature)
       };
       
       console.log('📡 Request payload:', requestPayload);
@@ -0,0 +1,11 @@
+// The litesvm tests load the compiled program from target/deploy. Without it they are compiled out
+// (cfg d_uptime_so) and tests/program_not_built.rs reports them as ignored instead of failing.
+use std::path::Path;
+
+fn main() {
+    let program = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy/d_uptime.so");
+    println!("cargo:rerun-if-changed={}", program.display());
+    if program.exists() {
+        println!("cargo:rustc-cfg=d_uptime_so");
+    }
+}
@@ -1,12 +1,14 @@
 use std::str;
 
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{ed25519_program, hash::hashv, sysvar::instructions::{load_current_index_checked, load_instruction_at_checked}};
+use anchor_spl::{associated_token::AssociatedToken, token::{self, CloseAccount, Mint, Token, TokenAccount, TransferChecked}};
 
 declare_id!("UMUmkqXqujVtpUrSKsYb9QcVmJprPPNsGePF89HtH9i");
 
 #[program]
 pub mod d_uptime {
-    use anchor_lang::{solana_program::{ed25519_program, sysvar::instructions::load_instruction_at_checked}, system_program};
+    use anchor_lang::system_program;
 
     use super::*;
 
@@ -15,8 +17,10 @@ pub mod d_uptime {
         let admin_key = ctx.accounts.admin.key();
         if fee_account.admin == Pubkey::default(){
             msg!("Fee Account does not exist...creating new!");
+            fee_account.version = FEE_ACCOUNT_VERSION;
             fee_account.admin = admin_key;
             fee_account.total_fee_amount = 0;
+            fee_account.total_fees_withdrawn = 0;
         }
         else {
             msg!("Fee Account already exist.");
@@ -30,14 +34,38 @@ pub mod d_uptime {
         let vault = &mut ctx.accounts.vault;
         if vault.admin == Pubkey::default(){
             msg!("Vault doesn't exist...created new!");
+            vault.version = VAULT_VERSION;
             vault.admin = ctx.accounts.admin.key();
+            vault.creator = ctx.accounts.admin.key();
             vault.operator = operator;
-            vault.is_paused = false;
+            vault.pending_admin = None;
+            vault.pending_operator = None;
+            vault.operator_activation_slot = 0;
+            vault.operators = Vec::new();
+            vault.operator_threshold = 0;
+            vault.pending_operators = Vec::new();
+            vault.pending_operator_threshold = 0;
+            vault.operator_set_activation_slot = 0;
+            vault.guardian = None;
+            vault.pause_flags = 0;
             vault.fee_account = fee_account;
             vault.last_admin_withdrawal = None;
             vault.withdrawal_fee_bps = 200;
+            vault.pending_withdrawal_fee_bps = None;
+            vault.fee_effective_slot = 0;
             vault.total_deposited = 0;
             vault.total_withdrawn = 0;
+            vault.total_owed = 0;
+            vault.admin_withdrawal_cooldown = DEFAULT_ADMIN_WITHDRAWAL_COOLDOWN;
+            vault.admin_withdrawal_delay = DEFAULT_ADMIN_WITHDRAWAL_DELAY;
+            vault.pending_admin_withdrawal = None;
+            vault.admin_withdrawal_unlock_at = 0;
+            vault.max_outflow_per_epoch = 0;
+            vault.outflow_epoch = 0;
+            vault.epoch_outflow = 0;
+            vault.pending_withdrawal_limits = None;
+            vault.withdrawal_limits_unlock_at = 0;
+            vault.operator_action_counter = 0;
             vault.bump = ctx.bumps.vault;
 
             emit!(VaultInitialized{
@@ -55,7 +83,7 @@ pub mod d_uptime {
 
     pub fn deposit(ctx: Context<Deposit>, amount : u64) -> Result<()>{
         let vault = &mut ctx.accounts.vault;
-        require!(!vault.is_paused , VaultError::VaultPaused);
+        require!(!vault.is_paused(PAUSE_DEPOSITS) , VaultError::VaultPaused);
         require!(amount > 0 , VaultError::InvalidAmount);
 
         let cpi_context = CpiContext::new(
@@ -70,193 +98,2270 @@ pub mod d_uptime {
         Ok(())
     }
 
-    pub fn withdrawal(ctx : Context<Withdraw>, amount : u64, _operator_key : String, signed_message : String) -> Result<()>{
+    pub fn withdrawal(ctx : Context<Withdraw>, amount : u64, nonce : u64, expiry_slot : u64) -> Result<()>{
         let vault = &mut ctx.accounts.vault;
-        let vault_admin = vault.admin;
-        require!(!vault.is_paused, VaultError::VaultPaused);
+        require!(!vault.is_paused(PAUSE_WITHDRAWALS), VaultError::VaultPaused);
         require!(amount > 0, VaultError::InvalidAmount);
         require!(vault.total_deposited > amount, VaultError::InsufficientBalance);
-        let operator_pubkey = vault.operator;
-        let ix = load_instruction_at_checked(0, &ctx.accounts.instruction_sysvar)?;
-        require!(ix.program_id == ed25519_program::ID, VaultError::ProgramMissing);
-        let is_message = ix.data.windows(signed_message.len()).any(|message| message == signed_message.as_bytes());
-        require!(is_message, VaultError::SignedMessageMissing);
-        let is_operator_key = ix.data.windows(operator_pubkey.to_bytes().len()).any(|pubkey| pubkey == operator_pubkey.to_bytes());
-        if is_operator_key == true {
-            let fee_amount = (amount* 2)/100;
-            let withdrawal_amount = amount - fee_amount;
-            let seeds = &[b"vault".as_ref(), vault_admin.as_ref(), &[vault.bump]];
-            let signer = &[&seeds[..]];
+        require!(vault.total_deposited - amount >= vault.total_owed, VaultError::InsufficientBalance); // credited rewards stay reserved for their validators
+
+        let (operators, threshold) = vault.withdrawal_authority();
+        let message_bytes = load_operator_signed_message(&ctx.accounts.instruction_sysvar, &operators, threshold)?;
+        let message = WithdrawalMessage::parse(&message_bytes)?;
+        require!(message.vault == vault.key(), VaultError::VaultMismatch);
+        require!(message.recipient == ctx.accounts.user.key(), VaultError::RecipientMismatch);
+        require!(message.amount == amount, VaultError::AmountMismatch);
+        require!(message.nonce == nonce && nonce == vault.withdrawal_counter, VaultError::NonceMismatch); // a consumed nonce can never be replayed
+        require!(message.expiry_slot == expiry_slot && Clock::get()?.slot <= expiry_slot, VaultError::SignatureExpired);
+
+        if !vault.record_outflow(amount, Clock::get()?.epoch)? {
+            emit!(CircuitBreakerTriggered{
+                vault : vault.key(),
+                attempted : amount,
+                epoch_outflow : vault.epoch_outflow,
+                max_outflow_per_epoch : vault.max_outflow_per_epoch
+            });
+            let vault_key = vault.key();
+            vault.trip_circuit_breaker(vault_key);
+            return Ok(());
+        }
+
+        vault.apply_pending_fee(Clock::get()?.slot);
+        let fee_amount = vault.withdrawal_fee(amount)?;
+        let withdrawal_amount = amount - fee_amount;
+
+        // the vault is a program owned PDA carrying data, so the system program cannot debit it; move lamports directly
+        vault.sub_lamports(amount)?;
+        ctx.accounts.user.add_lamports(withdrawal_amount)?;
+        ctx.accounts.fee_account.add_lamports(fee_amount)?;
+        msg!("succesfull transfer from vault to user wallet");
+
+        let fee_account = &mut ctx.accounts.fee_account;
+        fee_account.total_fee_amount = fee_account.total_fee_amount.checked_add(fee_amount).ok_or(VaultError::MathOverflow)?;
+
+        vault.total_deposited = vault.total_deposited.checked_sub(amount).ok_or(VaultError::MathOverflow)?;
+        vault.total_withdrawn = vault.total_withdrawn.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+        vault.withdrawal_counter = vault.withdrawal_counter.checked_add(1).ok_or(VaultError::MathOverflow)?;
+
+        emit!(WithdrawalSucces{
+            user : ctx.accounts.user.key(),
+            amount,
+            fee : fee_amount,
+            timestamp : Clock::get()?.unix_timestamp
+        });
+        Ok(())
+    }
+
+    pub fn announce_admin_withdrawal(ctx : Context<AnnounceAdminWithdrawal>, amount : u64) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        require!(amount > 0, VaultError::InvalidAmount);
+        let unlock_at = Clock::get()?.unix_timestamp.checked_add(vault.admin_withdrawal_delay).ok_or(VaultError::MathOverflow)?;
+        vault.pending_admin_withdrawal = Some(amount);
+        vault.admin_withdrawal_unlock_at = unlock_at;
+
+        emit!(AdminWithdrawalAnnounced{
+            vault : vault.key(),
+            amount,
+            unlock_at
+        });
+        Ok(())
+    }
+
+    pub fn cancel_admin_withdrawal(ctx : Context<AnnounceAdminWithdrawal>) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        let amount = vault.pending_admin_withdrawal.take().ok_or(VaultError::NoPendingAdminWithdrawal)?;
+        emit!(AdminWithdrawalCancelled{
+            vault : vault.key(),
+            amount
+        });
+        Ok(())
+    }
+
+    pub fn admin_withdrawal(ctx : Context<AdminWithdraw>) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        let clock = Clock::get()?;
+        let requested = vault.pending_admin_withdrawal.ok_or(VaultError::NoPendingAdminWithdrawal)?;
+        require!(clock.unix_timestamp >= vault.admin_withdrawal_unlock_at, VaultError::AdminWithdrawalLocked);
+        if let Some(last_withdrawal) = vault.last_admin_withdrawal {
+            let cooldown_end = last_withdrawal.checked_add(vault.admin_withdrawal_cooldown).ok_or(VaultError::MathOverflow)?;
+            require!(clock.unix_timestamp >= cooldown_end, VaultError::AdminWithdrawalCooldown);
+        }
+
+        let rent_exempt = Rent::get()?.minimum_balance(8 + Vault::INIT_SPACE);
+        let available = vault.get_lamports()
+            .checked_sub(rent_exempt)
+            .and_then(|amount| amount.checked_sub(vault.total_owed))
+            .ok_or(VaultError::AdminWithdrawal)?;
+        let withdrawal_amount = requested.min(available);
+        require!(withdrawal_amount > 0, VaultError::AdminWithdrawal);
+
+        if !vault.record_outflow(withdrawal_amount, clock.epoch)? {
+            emit!(CircuitBreakerTriggered{
+                vault : vault.key(),
+                attempted : withdrawal_amount,
+                epoch_outflow : vault.epoch_outflow,
+                max_outflow_per_epoch : vault.max_outflow_per_epoch
+            });
+            let vault_key = vault.key();
+            vault.trip_circuit_breaker(vault_key);
+            return Ok(()); // returning an error would roll back the pause
+        }
+
+        vault.sub_lamports(withdrawal_amount)?;
+        ctx.accounts.admin_address.add_lamports(withdrawal_amount)?;
+        msg!("Admin withdrawal Succesfull");
+
+        // lamports sent straight to the PDA never went through deposit, so saturate instead of failing
+        vault.total_deposited = vault.total_deposited.saturating_sub(withdrawal_amount);
+        vault.total_withdrawn = vault.total_withdrawn.checked_add(withdrawal_amount).ok_or(VaultError::MathOverflow)?;
+        vault.last_admin_withdrawal = Some(clock.unix_timestamp);
+        vault.pending_admin_withdrawal = None;
+
+        emit!(AdminWithdrawalExecuted{
+            vault : vault.key(),
+            recipient : ctx.accounts.admin_address.key(),
+            amount : withdrawal_amount,
+            timestamp : clock.unix_timestamp
+        });
+        Ok(())
+    }
+
+    // Stricter limits apply at once. Looser ones only take effect through apply_withdrawal_limits after the
+    // current admin withdrawal delay, so a compromised admin key can't zero the limits and drain in one go.
+    pub fn configure_withdrawal_limits(ctx : Context<AnnounceAdminWithdrawal>, admin_withdrawal_cooldown : i64, admin_withdrawal_delay : i64, max_outflow_per_epoch : u64) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        require!(admin_withdrawal_cooldown >= 0 && admin_withdrawal_delay >= 0, VaultError::InvalidWithdrawalLimits);
+        let limits = WithdrawalLimits{ admin_withdrawal_cooldown, admin_withdrawal_delay, max_outflow_per_epoch };
+
+        if !vault.is_stricter(&limits) {
+            let unlock_at = Clock::get()?.unix_timestamp.checked_add(vault.admin_withdrawal_delay).ok_or(VaultError::MathOverflow)?;
+            vault.pending_withdrawal_limits = Some(limits);
+            vault.withdrawal_limits_unlock_at = unlock_at;
+            emit!(WithdrawalLimitsProposed{
+                vault : vault.key(),
+                admin_withdrawal_cooldown,
+                admin_withdrawal_delay,
+                max_outflow_per_epoch,
+                unlock_at
+            });
+            return Ok(());
+        }
+
+        vault.set_withdrawal_limits(&limits);
+        vault.pending_withdrawal_limits = None; // tightening also drops a loosening still waiting
+        emit!(WithdrawalLimitsUpdated{
+            vault : vault.key(),
+            admin_withdrawal_cooldown,
+            admin_withdrawal_delay,
+            max_outflow_per_epoch
+        });
+        Ok(())
+    }
+
+    pub fn apply_withdrawal_limits(ctx : Context<AnnounceAdminWithdrawal>) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        let limits = vault.pending_withdrawal_limits.ok_or(VaultError::NoPendingWithdrawalLimits)?;
+        require!(Clock::get()?.unix_timestamp >= vault.withdrawal_limits_unlock_at, VaultError::WithdrawalLimitsLocked);
+        vault.set_withdrawal_limits(&limits);
+        vault.pending_withdrawal_limits = None;
+
+        emit!(WithdrawalLimitsUpdated{
+            vault : vault.key(),
+            admin_withdrawal_cooldown : limits.admin_withdrawal_cooldown,
+            admin_withdrawal_delay : limits.admin_withdrawal_delay,
+            max_outflow_per_epoch : limits.max_outflow_per_epoch
+        });
+        Ok(())
+    }
+
+    // admin only, sets or clears the given PAUSE_* flags
+    pub fn set_vault_pause(ctx : Context<SetPause>, flags : u8, paused : bool) -> Result<()>{
+        require!(flags != 0 && flags & !PAUSE_ALL == 0, VaultError::InvalidPauseFlags);
+        let vault = &mut ctx.accounts.vault;
+        if paused {
+            vault.pause_flags |= flags;
+        } else {
+            vault.pause_flags &= !flags;
+        }
+        emit!(PauseStatus{
+            vault : vault.key(),
+            flags,
+            paused,
+            pause_flags : vault.pause_flags,
+            changed_by : ctx.accounts.admin.key()
+        });
+        Ok(())
+    }
+
+    // the guardian (or the admin) can only ever add pause flags, unpausing stays with the admin
+    pub fn guardian_pause(ctx : Context<GuardianPause>, flags : u8) -> Result<()>{
+        require!(flags != 0 && flags & !PAUSE_ALL == 0, VaultError::InvalidPauseFlags);
+        let vault = &mut ctx.accounts.vault;
+        let authority = ctx.accounts.authority.key();
+        require!(authority == vault.admin || vault.guardian == Some(authority), VaultError::GuardianError);
+        vault.pause_flags |= flags;
+        emit!(PauseStatus{
+            vault : vault.key(),
+            flags,
+            paused : true,
+            pause_flags : vault.pause_flags,
+            changed_by : authority
+        });
+        Ok(())
+    }
+
+    pub fn set_guardian(ctx : Context<ProposeKey>, guardian : Option<Pubkey>) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        vault.guardian = guardian;
+        emit!(GuardianUpdated{
+            vault : vault.key(),
+            guardian
+        });
+        Ok(())
+    }
+
+    pub fn initialize_reward_account(ctx : Context<InitializeRewardAccount>) -> Result<()>{
+        let reward_account = &mut ctx.accounts.reward_account;
+        if reward_account.validator == Pubkey::default(){
+            msg!("Reward account doesn't exist...creating new!");
+            reward_account.vault = ctx.accounts.vault.key();
+            reward_account.validator = ctx.accounts.validator.key();
+            reward_account.total_credited = 0;
+            reward_account.total_claimed = 0;
+            reward_account.last_credited_at = None;
+            reward_account.bump = ctx.bumps.reward_account;
+        }
+        else {
+            msg!("Reward account already exist.");
+        }
+        Ok(())
+    }
+
+    // remaining accounts carry the RewardAccount PDAs to credit, in the same order as `amounts`.
+    pub fn credit_rewards<'info>(ctx : Context<'_, '_, 'info, 'info, CreditRewards<'info>>, amounts : Vec<u64>, nonce : u64, signature_expiry_slot : u64) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        require!(amounts.len() == ctx.remaining_accounts.len(), RewardAccountError::CreditLengthMismatch);
+        let vault_key = vault.key();
+        let action = OperatorAction::CreditRewards{
+            reward_accounts : ctx.remaining_accounts.iter().map(|account| account.key()).collect(),
+            amounts : amounts.clone()
+        };
+        vault.authorize_operator_action(vault_key, &ctx.accounts.instruction_sysvar, action, nonce, signature_expiry_slot)?;
+        let timestamp = Clock::get()?.unix_timestamp;
+        let mut batch_total : u64 = 0;
+
+        for (account_info, amount) in ctx.remaining_accounts.iter().zip(amounts.iter()){
+            require!(*amount > 0, VaultError::InvalidAmount);
+            require!(account_info.is_writable, RewardAccountError::AccountNotWritable);
+            let mut reward_account : Account<'info, RewardAccount> = Account::try_from(account_info)?;
+            require!(reward_account.vault == vault.key(), RewardAccountError::VaultMismatch);
+
+            reward_account.total_credited = reward_account.total_credited.checked_add(*amount).ok_or(VaultError::MathOverflow)?;
+            reward_account.last_credited_at = Some(timestamp);
+            reward_account.exit(&crate::ID)?;
+            batch_total = batch_total.checked_add(*amount).ok_or(VaultError::MathOverflow)?;
+        }
+
+        // the vault can never owe validators more than it currently holds
+        let total_owed = vault.total_owed.checked_add(batch_total).ok_or(VaultError::MathOverflow)?;
+        require!(total_owed <= vault.total_deposited, VaultError::InsufficientBalance);
+        vault.total_owed = total_owed;
+
+        emit!(RewardsCredited{
+            vault : vault.key(),
+            accounts : amounts.len() as u32,
+            amount : batch_total,
+            timestamp
+        });
+        Ok(())
+    }
+
+    pub fn claim(ctx : Context<Claim>, amount : u64) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        let reward_account = &mut ctx.accounts.reward_account;
+        require!(!vault.is_paused(PAUSE_CLAIMS), VaultError::VaultPaused);
+        require!(amount > 0, VaultError::InvalidAmount);
+        let claimable = reward_account.total_credited.checked_sub(reward_account.total_claimed).ok_or(VaultError::MathOverflow)?;
+        require!(amount <= claimable, RewardAccountError::InsufficientRewards);
+
+        if !vault.record_outflow(amount, Clock::get()?.epoch)? {
+            emit!(CircuitBreakerTriggered{
+                vault : vault.key(),
+                attempted : amount,
+                epoch_outflow : vault.epoch_outflow,
+                max_outflow_per_epoch : vault.max_outflow_per_epoch
+            });
+            let vault_key = vault.key();
+            vault.trip_circuit_breaker(vault_key);
+            return Ok(());
+        }
+
+        vault.apply_pending_fee(Clock::get()?.slot);
+        let fee_amount = vault.withdrawal_fee(amount)?;
+
+        // the vault is a program owned PDA carrying data, so the system program cannot debit it; move lamports directly
+        vault.sub_lamports(amount)?;
+        ctx.accounts.validator.add_lamports(amount - fee_amount)?;
+        ctx.accounts.fee_account.add_lamports(fee_amount)?;
+
+        let fee_account = &mut ctx.accounts.fee_account;
+        fee_account.total_fee_amount = fee_account.total_fee_amount.checked_add(fee_amount).ok_or(VaultError::MathOverflow)?;
+        reward_account.total_claimed = reward_account.total_claimed.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+        vault.total_owed = vault.total_owed.checked_sub(amount).ok_or(VaultError::MathOverflow)?;
+        vault.total_deposited = vault.total_deposited.checked_sub(amount).ok_or(VaultError::MathOverflow)?;
+        vault.total_withdrawn = vault.total_withdrawn.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+
+        emit!(RewardClaimed{
+            validator : ctx.accounts.validator.key(),
+            amount,
+            fee : fee_amount,
+            remaining : claimable - amount,
+            timestamp : Clock::get()?.unix_timestamp
+        });
+        Ok(())
+    }
+
+    pub fn set_withdrawal_fee(ctx : Context<SetWithdrawalFee>, fee_bps : u16) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        require!(fee_bps <= MAX_WITHDRAWAL_FEE_BPS, VaultError::FeeTooHigh);
+        let slot = Clock::get()?.slot;
+        vault.apply_pending_fee(slot);
+
+        // fee changes only apply after the timelock so validators can exit before a raise
+        let effective_slot = slot.checked_add(FEE_TIMELOCK_SLOTS).ok_or(VaultError::MathOverflow)?;
+        vault.pending_withdrawal_fee_bps = Some(fee_bps);
+        vault.fee_effective_slot = effective_slot;
+
+        emit!(WithdrawalFeeScheduled{
+            vault : vault.key(),
+            current_fee_bps : vault.withdrawal_fee_bps,
+            new_fee_bps : fee_bps,
+            effective_slot
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_fees(ctx : Context<WithdrawFees>, amount : u64) -> Result<()>{
+        let fee_account = &mut ctx.accounts.fee_account;
+        require!(amount > 0, VaultError::InvalidAmount);
+        let rent_exempt = Rent::get()?.minimum_balance(8 + FeeAccount::INIT_SPACE);
+        let available = fee_account.get_lamports().checked_sub(rent_exempt).ok_or(VaultError::MathOverflow)?;
+        require!(amount <= available, FeeAccountError::InsufficientFees);
+
+        fee_account.sub_lamports(amount)?;
+        ctx.accounts.admin.add_lamports(amount)?;
+        fee_account.total_fees_withdrawn = fee_account.total_fees_withdrawn.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+
+        emit!(FeesWithdrawn{
+            admin : ctx.accounts.admin.key(),
+            amount,
+            timestamp : Clock::get()?.unix_timestamp
+        });
+        Ok(())
+    }
+
+    pub fn propose_operator(ctx : Context<ProposeKey>, new_operator : Pubkey, activation_delay_slots : u64) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        require!(new_operator != Pubkey::default(), VaultError::InvalidPendingKey);
+        let activation_slot = Clock::get()?.slot.checked_add(activation_delay_slots).ok_or(VaultError::MathOverflow)?;
+        vault.pending_operator = Some(new_operator);
+        vault.operator_activation_slot = activation_slot;
+
+        emit!(OperatorProposed{
+            vault : vault.key(),
+            current_operator : vault.operator,
+            proposed_operator : new_operator,
+            activation_slot
+        });
+        Ok(())
+    }
+
+    pub fn accept_operator(ctx : Context<AcceptOperator>) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        let new_operator = ctx.accounts.new_operator.key();
+        require!(vault.pending_operator == Some(new_operator), VaultError::InvalidPendingKey);
+        require!(Clock::get()?.slot >= vault.operator_activation_slot, VaultError::OperatorNotActive);
+
+        let previous_operator = vault.operator;
+        require!(!vault.operators.contains(&new_operator), VaultError::InvalidPendingKey);
+        // the rotated key also takes the old operator's seat in the M-of-N set, or the rotation would not
+        // change who can authorize withdrawals
+        if let Some(seat) = vault.operators.iter_mut().find(|operator| **operator == previous_operator) {
+            *seat = new_operator;
+        }
+        vault.operator = new_operator;
+        vault.pending_operator = None;
+
+        emit!(OperatorAccepted{
+            vault : vault.key(),
+            previous_operator,
+            operator : new_operator
+        });
+        Ok(())
+    }
+
+    pub fn propose_admin(ctx : Context<ProposeKey>, new_admin : Pubkey) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        require!(new_admin != Pubkey::default(), VaultError::InvalidPendingKey);
+        vault.pending_admin = Some(new_admin);
+
+        emit!(AdminProposed{
+            vault : vault.key(),
+            current_admin : vault.admin,
+            proposed_admin : new_admin
+        });
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx : Context<AcceptAdmin>) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        let new_admin = ctx.accounts.new_admin.key();
+        require!(vault.pending_admin == Some(new_admin), VaultError::InvalidPendingKey);
+
+        let previous_admin = vault.admin;
+        vault.admin = new_admin;
+        vault.pending_admin = None;
+
+        emit!(AdminAccepted{
+            vault : vault.key(),
+            previous_admin,
+            admin : new_admin
+        });
+        Ok(())
+    }
+
+    // Stages a new M-of-N set. It only takes over after OPERATOR_SET_TIMELOCK_SLOTS and once enough of the
+    // new operators have signed the acceptance, so a bad set can be seen and replaced before it matters.
+    pub fn propose_operator_set(ctx : Context<ProposeKey>, operators : Vec<Pubkey>, threshold : u8) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        require!(!operators.is_empty() && operators.len() <= MAX_OPERATORS, VaultError::InvalidOperatorSet);
+        require!(threshold > 0 && threshold as usize <= operators.len(), VaultError::InvalidOperatorSet);
+        for (index, operator) in operators.iter().enumerate() {
+            require!(*operator != Pubkey::default(), VaultError::InvalidOperatorSet);
+            require!(!operators[index + 1..].contains(operator), VaultError::InvalidOperatorSet);
+        }
+
+        let activation_slot = Clock::get()?.slot.checked_add(OPERATOR_SET_TIMELOCK_SLOTS).ok_or(VaultError::MathOverflow)?;
+        vault.pending_operators = operators.clone();
+        vault.pending_operator_threshold = threshold;
+        vault.operator_set_activation_slot = activation_slot;
+
+        emit!(OperatorSetProposed{
+            vault : vault.key(),
+            operators,
+            threshold,
+            activation_slot
+        });
+        Ok(())
+    }
+
+    // the pending operators sign as remaining accounts, at least their threshold of them
+    pub fn accept_operator_set<'info>(ctx : Context<'_, '_, 'info, 'info, AcceptOperatorSet<'info>>) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        require!(!vault.pending_operators.is_empty(), VaultError::InvalidPendingKey);
+        require!(Clock::get()?.slot >= vault.operator_set_activation_slot, VaultError::OperatorNotActive);
+
+        let mut signers : Vec<Pubkey> = Vec::new();
+        for account in ctx.remaining_accounts {
+            if account.is_signer && vault.pending_operators.contains(account.key) && !signers.contains(account.key) {
+                signers.push(account.key());
+            }
+        }
+        require!(signers.len() >= vault.pending_operator_threshold as usize, VaultError::InsufficientOperatorSignatures);
+
+        let operators = std::mem::take(&mut vault.pending_operators);
+        let threshold = vault.pending_operator_threshold;
+        vault.operators = operators.clone();
+        vault.operator_threshold = threshold;
+        vault.pending_operator_threshold = 0;
+
+        emit!(OperatorSetUpdated{
+            vault : vault.key(),
+            operators,
+            threshold
+        });
+        Ok(())
+    }
+
+    pub fn initialize_token_vault(ctx : Context<InitializeTokenVault>) -> Result<()>{
+        let token_vault = &mut ctx.accounts.token_vault;
+        if token_vault.vault == Pubkey::default(){
+            msg!("Token vault doesn't exist...created new!");
+            token_vault.vault = ctx.accounts.vault.key();
+            token_vault.mint = ctx.accounts.mint.key();
+            token_vault.total_deposited = 0;
+            token_vault.total_withdrawn = 0;
+            token_vault.total_fees = 0;
+            token_vault.pending_admin_withdrawal = None;
+            token_vault.admin_withdrawal_unlock_at = 0;
+            token_vault.last_admin_withdrawal = None;
+            token_vault.max_outflow_per_epoch = 0;
+            token_vault.pending_max_outflow_per_epoch = None;
+            token_vault.outflow_limit_unlock_at = 0;
+            token_vault.outflow_epoch = 0;
+            token_vault.epoch_outflow = 0;
+            token_vault.bump = ctx.bumps.token_vault;
+            let vault = &mut ctx.accounts.vault;
+            vault.open_token_vaults = vault.open_token_vaults.checked_add(1).ok_or(VaultError::MathOverflow)?;
+
+            emit!(TokenVaultInitialized{
+                vault : token_vault.vault,
+                mint : token_vault.mint
+            });
+        }
+        else {
+            msg!("Token vault already exist.");
+        }
+        Ok(())
+    }
+
+    pub fn deposit_token(ctx : Context<DepositToken>, amount : u64) -> Result<()>{
+        require!(!ctx.accounts.vault.is_paused(PAUSE_DEPOSITS), VaultError::VaultPaused);
+        require!(amount > 0, VaultError::InvalidAmount);
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked{
+                from : ctx.accounts.user_token_account.to_account_info(),
+                mint : ctx.accounts.mint.to_account_info(),
+                to : ctx.accounts.vault_token_account.to_account_info(),
+                authority : ctx.accounts.user.to_account_info()
+            });
+        token::transfer_checked(cpi_context, amount, ctx.accounts.mint.decimals)?;
+
+        let token_vault = &mut ctx.accounts.token_vault;
+        token_vault.total_deposited = token_vault.total_deposited.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn withdrawal_token(ctx : Context<WithdrawToken>, amount : u64, nonce : u64, expiry_slot : u64) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        let token_vault = &mut ctx.accounts.token_vault;
+        require!(!vault.is_paused(PAUSE_WITHDRAWALS), VaultError::VaultPaused);
+        require!(amount > 0, VaultError::InvalidAmount);
+        require!(token_vault.total_deposited >= amount, VaultError::InsufficientBalance);
+
+        let (operators, threshold) = vault.withdrawal_authority();
+        let message_bytes = load_operator_signed_message(&ctx.accounts.instruction_sysvar, &operators, threshold)?;
+        let message = TokenWithdrawalMessage::parse(&message_bytes)?;
+        require!(message.vault == vault.key(), VaultError::VaultMismatch);
+        require!(message.mint == token_vault.mint, VaultError::MintMismatch);
+        require!(message.recipient == ctx.accounts.user.key(), VaultError::RecipientMismatch);
+        require!(message.amount == amount, VaultError::AmountMismatch);
+        require!(message.nonce == nonce && nonce == vault.withdrawal_counter, VaultError::NonceMismatch); // nonce is shared with lamport withdrawals
+        require!(message.expiry_slot == expiry_slot && Clock::get()?.slot <= expiry_slot, VaultError::SignatureExpired);
+
+        if !token_vault.record_outflow(amount, Clock::get()?.epoch)? {
+            emit!(TokenCircuitBreakerTriggered{
+                vault : vault.key(),
+                mint : token_vault.mint,
+                attempted : amount,
+                epoch_outflow : token_vault.epoch_outflow,
+                max_outflow_per_epoch : token_vault.max_outflow_per_epoch
+            });
+            let vault_key = vault.key();
+            vault.trip_circuit_breaker(vault_key);
+            return Ok(());
+        }
+
+        vault.apply_pending_fee(Clock::get()?.slot);
+        let fee_amount = vault.withdrawal_fee(amount)?;
+        let withdrawal_amount = amount - fee_amount;
+
+        let vault_creator = vault.creator;
+        let seeds = &[b"vault".as_ref(), vault_creator.as_ref(), &[vault.bump]];
+        let signer = &[&seeds[..]];
+        let decimals = ctx.accounts.mint.decimals;
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked{
+                from : ctx.accounts.vault_token_account.to_account_info(),
+                mint : ctx.accounts.mint.to_account_info(),
+                to : ctx.accounts.user_token_account.to_account_info(),
+                authority : vault.to_account_info()
+            },
+            signer
+        );
+        token::transfer_checked(cpi, withdrawal_amount, decimals)?;
+        if fee_amount > 0 {
             let cpi = CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer{
-                    from : vault.to_account_info(),
-                    to : ctx.accounts.user.to_account_info()
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked{
+                    from : ctx.accounts.vault_token_account.to_account_info(),
+                    mint : ctx.accounts.mint.to_account_info(),
+                    to : ctx.accounts.fee_token_account.to_account_info(),
+                    authority : vault.to_account_info()
                 },
                 signer
             );
-            match system_program::transfer(cpi, withdrawal_amount){
-                Ok(_) => {
-                    msg!("succesfull transfer from vault to user wallet");
-                    vault.total_deposited = vault.total_deposited.checked_sub(amount).ok_or(VaultError::MathOverflow)?;
-                    vault.total_withdrawn = vault.total_withdrawn.checked_add(amount).ok_or(VaultError::MathOverflow)?;
-                    vault.withdrawal_counter += 1;
-                    let cpi = CpiContext::new_with_signer(
-                        ctx.accounts.system_program.to_account_info(),
-                        system_program::Transfer{
-                            from : vault.to_account_info(),
-                            to : ctx.accounts.fee_account.to_account_info(),
-                        },
-                        signer
-                    );
-                    system_program::transfer(cpi, fee_amount)?;
-
-                    emit!(WithdrawalSucces{
-                        user : ctx.accounts.user.key(),
-                        amount,
-                        fee : fee_amount,
-                        timestamp : Clock::get()?.unix_timestamp
-                    })
-                }
-                Err(e) => {
-                    msg!("Error transferring amount : {} from vault to user : {}", e, ctx.accounts.user.key());
-                }
-            }
+            token::transfer_checked(cpi, fee_amount, decimals)?;
         }
+        msg!("succesfull token transfer from vault to user wallet");
+
+        token_vault.total_deposited = token_vault.total_deposited.checked_sub(amount).ok_or(VaultError::MathOverflow)?;
+        token_vault.total_withdrawn = token_vault.total_withdrawn.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+        token_vault.total_fees = token_vault.total_fees.checked_add(fee_amount).ok_or(VaultError::MathOverflow)?;
+        vault.withdrawal_counter = vault.withdrawal_counter.checked_add(1).ok_or(VaultError::MathOverflow)?;
+
+        emit!(TokenWithdrawalSucces{
+            user : ctx.accounts.user.key(),
+            mint : token_vault.mint,
+            amount,
+            fee : fee_amount,
+            timestamp : Clock::get()?.unix_timestamp
+        });
         Ok(())
     }
 
-    pub fn admin_withdrawal(ctx : Context<AdminWithdraw>) -> Result<()>{
+    // token admin withdrawals follow the lamport ones: announced, then executable after the vault's delay and cooldown
+    pub fn announce_admin_withdrawal_token(ctx : Context<AnnounceAdminWithdrawalToken>, amount : u64) -> Result<()>{
+        let token_vault = &mut ctx.accounts.token_vault;
+        require!(amount > 0, VaultError::InvalidAmount);
+        let unlock_at = Clock::get()?.unix_timestamp.checked_add(ctx.accounts.vault.admin_withdrawal_delay).ok_or(VaultError::MathOverflow)?;
+        token_vault.pending_admin_withdrawal = Some(amount);
+        token_vault.admin_withdrawal_unlock_at = unlock_at;
+
+        emit!(TokenAdminWithdrawalAnnounced{
+            vault : token_vault.vault,
+            mint : token_vault.mint,
+            amount,
+            unlock_at
+        });
+        Ok(())
+    }
+
+    pub fn cancel_admin_withdrawal_token(ctx : Context<AnnounceAdminWithdrawalToken>) -> Result<()>{
+        let token_vault = &mut ctx.accounts.token_vault;
+        let amount = token_vault.pending_admin_withdrawal.take().ok_or(VaultError::NoPendingAdminWithdrawal)?;
+        emit!(TokenAdminWithdrawalCancelled{
+            vault : token_vault.vault,
+            mint : token_vault.mint,
+            amount
+        });
+        Ok(())
+    }
+
+    pub fn admin_withdrawal_token(ctx : Context<AdminWithdrawToken>) -> Result<()>{
         let vault = &mut ctx.accounts.vault;
-        let vault_admin = vault.admin;
-        let rent_exempt = Rent::get()?.minimum_balance(Vault::INIT_SPACE);
-        require!(vault.total_deposited > rent_exempt, VaultError::AdminWithdrawal);
-        let withdrawal_amount = vault.get_lamports().checked_sub(rent_exempt).ok_or(VaultError::MathOverflow)?;
-        let seeds = &[b"vault".as_ref(), vault_admin.as_ref(), &[vault.bump]];
+        let token_vault = &mut ctx.accounts.token_vault;
+        let clock = Clock::get()?;
+        let requested = token_vault.pending_admin_withdrawal.ok_or(VaultError::NoPendingAdminWithdrawal)?;
+        require!(clock.unix_timestamp >= token_vault.admin_withdrawal_unlock_at, VaultError::AdminWithdrawalLocked);
+        if let Some(last_withdrawal) = token_vault.last_admin_withdrawal {
+            let cooldown_end = last_withdrawal.checked_add(vault.admin_withdrawal_cooldown).ok_or(VaultError::MathOverflow)?;
+            require!(clock.unix_timestamp >= cooldown_end, VaultError::AdminWithdrawalCooldown);
+        }
+        let withdrawal_amount = requested.min(ctx.accounts.vault_token_account.amount);
+        require!(withdrawal_amount > 0, VaultError::AdminWithdrawal);
+
+        if !token_vault.record_outflow(withdrawal_amount, clock.epoch)? {
+            emit!(TokenCircuitBreakerTriggered{
+                vault : vault.key(),
+                mint : token_vault.mint,
+                attempted : withdrawal_amount,
+                epoch_outflow : token_vault.epoch_outflow,
+                max_outflow_per_epoch : token_vault.max_outflow_per_epoch
+            });
+            let vault_key = vault.key();
+            vault.trip_circuit_breaker(vault_key);
+            return Ok(()); // returning an error would roll back the pause
+        }
+
+        let seeds = &[b"vault".as_ref(), vault.creator.as_ref(), &[vault.bump]];
         let signer = &[&seeds[..]];
         let cpi = CpiContext::new_with_signer(
-            ctx.accounts.system_program.to_account_info(),
-            system_program::Transfer{
-                from : vault.to_account_info(),
-                to : ctx.accounts.admin_address.to_account_info()
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked{
+                from : ctx.accounts.vault_token_account.to_account_info(),
+                mint : ctx.accounts.mint.to_account_info(),
+                to : ctx.accounts.admin_token_account.to_account_info(),
+                authority : vault.to_account_info()
             },
-        signer);
-        match system_program::transfer(cpi, withdrawal_amount){
-            Ok(_) => {
-                msg!("Admin withdrawal Succesfull");
-                vault.total_deposited = vault.total_deposited.checked_sub(withdrawal_amount).ok_or(VaultError::MathOverflow)?;
-                vault.total_withdrawn = vault.total_withdrawn.checked_add(withdrawal_amount).ok_or(VaultError::MathOverflow)?;
-                vault.withdrawal_counter += 1;
-            }
-            Err(e) =>{
-                msg!("Unable to process admin withdrawal due to : {}",e);
-            }
-        };
+            signer
+        );
+        token::transfer_checked(cpi, withdrawal_amount, ctx.accounts.mint.decimals)?;
+        msg!("Admin token withdrawal Succesfull");
+
+        // tokens sent straight to the vault ATA never went through deposit_token, so saturate instead of failing
+        token_vault.total_deposited = token_vault.total_deposited.saturating_sub(withdrawal_amount);
+        token_vault.total_withdrawn = token_vault.total_withdrawn.checked_add(withdrawal_amount).ok_or(VaultError::MathOverflow)?;
+        token_vault.last_admin_withdrawal = Some(clock.unix_timestamp);
+        token_vault.pending_admin_withdrawal = None;
+
+        emit!(TokenAdminWithdrawalExecuted{
+            vault : vault.key(),
+            mint : token_vault.mint,
+            recipient : ctx.accounts.admin_token_account.key(),
+            amount : withdrawal_amount,
+            timestamp : clock.unix_timestamp
+        });
+        Ok(())
+    }
+
+    // Per-epoch cap on this mint's outflow in base units, 0 disables it. Same rule as the vault limits: a
+    // stricter cap applies at once, a looser one through apply_token_outflow_limit after the admin delay.
+    pub fn configure_token_outflow_limit(ctx : Context<AnnounceAdminWithdrawalToken>, max_outflow_per_epoch : u64) -> Result<()>{
+        let token_vault = &mut ctx.accounts.token_vault;
+        if !is_stricter_cap(max_outflow_per_epoch, token_vault.max_outflow_per_epoch) {
+            let unlock_at = Clock::get()?.unix_timestamp.checked_add(ctx.accounts.vault.admin_withdrawal_delay).ok_or(VaultError::MathOverflow)?;
+            token_vault.pending_max_outflow_per_epoch = Some(max_outflow_per_epoch);
+            token_vault.outflow_limit_unlock_at = unlock_at;
+            emit!(TokenOutflowLimitProposed{
+                vault : token_vault.vault,
+                mint : token_vault.mint,
+                max_outflow_per_epoch,
+                unlock_at
+            });
+            return Ok(());
+        }
+
+        token_vault.max_outflow_per_epoch = max_outflow_per_epoch;
+        token_vault.pending_max_outflow_per_epoch = None;
+        emit!(TokenOutflowLimitUpdated{
+            vault : token_vault.vault,
+            mint : token_vault.mint,
+            max_outflow_per_epoch
+        });
+        Ok(())
+    }
+
+    pub fn apply_token_outflow_limit(ctx : Context<AnnounceAdminWithdrawalToken>) -> Result<()>{
+        let token_vault = &mut ctx.accounts.token_vault;
+        let max_outflow_per_epoch = token_vault.pending_max_outflow_per_epoch.ok_or(VaultError::NoPendingWithdrawalLimits)?;
+        require!(Clock::get()?.unix_timestamp >= token_vault.outflow_limit_unlock_at, VaultError::WithdrawalLimitsLocked);
+        token_vault.max_outflow_per_epoch = max_outflow_per_epoch;
+        token_vault.pending_max_outflow_per_epoch = None;
+
+        emit!(TokenOutflowLimitUpdated{
+            vault : token_vault.vault,
+            mint : token_vault.mint,
+            max_outflow_per_epoch
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_token_fees(ctx : Context<WithdrawTokenFees>, amount : u64) -> Result<()>{
+        require!(amount > 0, VaultError::InvalidAmount);
+        require!(amount <= ctx.accounts.fee_token_account.amount, FeeAccountError::InsufficientFees);
+
+        let admin_key = ctx.accounts.admin.key();
+        let seeds = &[b"fee_account".as_ref(), admin_key.as_ref(), &[ctx.bumps.fee_account]];
+        let signer = &[&seeds[..]];
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked{
+                from : ctx.accounts.fee_token_account.to_account_info(),
+                mint : ctx.accounts.mint.to_account_info(),
+                to : ctx.accounts.admin_token_account.to_account_info(),
+                authority : ctx.accounts.fee_account.to_account_info()
+            },
+            signer
+        );
+        token::transfer_checked(cpi, amount, ctx.accounts.mint.decimals)?;
+
+        emit!(FeesWithdrawn{
+            admin : admin_key,
+            amount,
+            timestamp : Clock::get()?.unix_timestamp
+        });
+        Ok(())
+    }
+
+    // the validator wallet signs and pays for its own record, it starts out pending until the operator activates it
+    pub fn register_validator(ctx : Context<RegisterValidator>, device_hash : [u8; 32], region_code : u16) -> Result<()>{
+        let clock = Clock::get()?;
+        let record = &mut ctx.accounts.validator_record;
+        record.vault = ctx.accounts.vault.key();
+        record.wallet = ctx.accounts.validator.key();
+        record.device_hash = device_hash;
+        record.region_code = region_code;
+        record.registration_slot = clock.slot;
+        record.status = ValidatorStatus::Pending;
+        record.status_updated_slot = clock.slot;
+        record.bump = ctx.bumps.validator_record;
+
+        emit!(ValidatorRegistered{
+            vault : record.vault,
+            wallet : record.wallet,
+            device_hash,
+            region_code,
+            registration_slot : clock.slot
+        });
         Ok(())
     }
 
-    pub fn set_vault_pause(ctx : Context<SetPause>, paused : bool) -> Result<()>{
+    pub fn set_validator_status(ctx : Context<SetValidatorStatus>, status : ValidatorStatus, nonce : u64, signature_expiry_slot : u64) -> Result<()>{
+        let record = &mut ctx.accounts.validator_record;
+        require!(status != ValidatorStatus::Pending, ValidatorError::InvalidStatus);
+        require!(record.status != status, ValidatorError::InvalidStatus);
+        // an active record can stake and claim epochs, so activation needs the operator quorum
         let vault = &mut ctx.accounts.vault;
-        vault.is_paused = paused;
-        emit!(PauseStatus{
-            paused
+        let vault_key = vault.key();
+        let action = OperatorAction::SetValidatorStatus{ wallet : record.wallet, status };
+        vault.authorize_operator_action(vault_key, &ctx.accounts.instruction_sysvar, action, nonce, signature_expiry_slot)?;
+        let previous_status = record.status;
+        record.status = status;
+        record.status_updated_slot = Clock::get()?.slot;
+
+        emit!(ValidatorStatusChanged{
+            vault : record.vault,
+            wallet : record.wallet,
+            previous_status,
+            status
         });
         Ok(())
     }
 
-    
-}
+    pub fn stake(ctx : Context<Stake>, amount : u64) -> Result<()>{
+        require!(amount > 0, VaultError::InvalidAmount);
+        let stake_account = &mut ctx.accounts.stake_account;
+        if stake_account.validator == Pubkey::default(){
+            msg!("Stake account doesn't exist...creating new!");
+            stake_account.vault = ctx.accounts.vault.key();
+            stake_account.validator = ctx.accounts.validator.key();
+            stake_account.staked_amount = 0;
+            stake_account.unbonding_amount = 0;
+            stake_account.unbonding_release_slot = 0;
+            stake_account.total_slashed = 0;
+            stake_account.bump = ctx.bumps.stake_account;
+        }
 
-#[derive(Accounts, Debug)]
-pub struct InitializeFeeAccount<'info> {
-    #[account(
-        init_if_needed,
-        payer = admin,
-        space = 8 + FeeAccount::INIT_SPACE,
-        seeds = [b"fee_account".as_ref() , admin.key().as_ref()],
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer{
+                from : ctx.accounts.validator.to_account_info(),
+                to : stake_account.to_account_info()
+            });
+        system_program::transfer(cpi_context, amount)?;
+
+        stake_account.staked_amount = stake_account.staked_amount.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+        let vault = &mut ctx.accounts.vault;
+        vault.total_staked = vault.total_staked.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+        emit!(Staked{
+            vault : stake_account.vault,
+            validator : stake_account.validator,
+            amount,
+            total_staked : stake_account.staked_amount
+        });
+        Ok(())
+    }
+
+    pub fn unstake(ctx : Context<Unstake>, amount : u64) -> Result<()>{
+        let stake_account = &mut ctx.accounts.stake_account;
+        require!(amount > 0, VaultError::InvalidAmount);
+        require!(amount <= stake_account.staked_amount, StakeError::InsufficientStake);
+
+        // a new request restarts the unbonding window for everything still unbonding
+        let release_slot = Clock::get()?.slot.checked_add(UNBONDING_PERIOD_SLOTS).ok_or(VaultError::MathOverflow)?;
+        stake_account.staked_amount -= amount;
+        stake_account.unbonding_amount = stake_account.unbonding_amount.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+        stake_account.unbonding_release_slot = release_slot;
+
+        emit!(UnstakeRequested{
+            vault : stake_account.vault,
+            validator : stake_account.validator,
+            amount,
+            release_slot
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_stake(ctx : Context<Unstake>) -> Result<()>{
+        let stake_account = &mut ctx.accounts.stake_account;
+        let amount = stake_account.unbonding_amount;
+        require!(amount > 0, StakeError::NothingToWithdraw);
+        require!(Clock::get()?.slot >= stake_account.unbonding_release_slot, StakeError::StillUnbonding);
+
+        stake_account.sub_lamports(amount)?;
+        ctx.accounts.validator.add_lamports(amount)?;
+        stake_account.unbonding_amount = 0;
+        let vault = &mut ctx.accounts.vault;
+        vault.total_staked = vault.total_staked.saturating_sub(amount);
+
+        emit!(StakeWithdrawn{
+            vault : stake_account.vault,
+            validator : stake_account.validator,
+            amount
+        });
+        Ok(())
+    }
+
+    pub fn slash(ctx : Context<Slash>, amount : u64, reason_code : u8) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        let authority = ctx.accounts.authority.key();
+        require!(authority == vault.admin || authority == vault.operator, StakeError::UnauthorizedSlasher);
+        require!(amount > 0, VaultError::InvalidAmount);
+
+        let stake_account = &mut ctx.accounts.stake_account;
+        let slashable = stake_account.staked_amount.checked_add(stake_account.unbonding_amount).ok_or(VaultError::MathOverflow)?;
+        require!(amount <= slashable, StakeError::InsufficientStake);
+
+        // active stake is slashed first, unbonding stake is still liable for reports made during its window
+        let from_staked = amount.min(stake_account.staked_amount);
+        stake_account.staked_amount -= from_staked;
+        stake_account.unbonding_amount -= amount - from_staked;
+        stake_account.total_slashed = stake_account.total_slashed.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+
+        stake_account.sub_lamports(amount)?;
+        vault.total_staked = vault.total_staked.saturating_sub(amount);
+        ctx.accounts.fee_account.add_lamports(amount)?;
+        let fee_account = &mut ctx.accounts.fee_account;
+        fee_account.total_fee_amount = fee_account.total_fee_amount.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+
+        emit!(ValidatorSlashed{
+            vault : vault.key(),
+            validator : stake_account.validator,
+            authority,
+            amount,
+            remaining_stake : stake_account.staked_amount + stake_account.unbonding_amount,
+            reason_code,
+            timestamp : Clock::get()?.unix_timestamp
+        });
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn post_epoch(ctx : Context<PostEpoch>, epoch_id : u64, merkle_root : [u8; 32], total_amount : u64, leaf_count : u32, expiry_slot : u64, nonce : u64, signature_expiry_slot : u64) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        require!(total_amount > 0, VaultError::InvalidAmount);
+        require!(leaf_count > 0 && leaf_count as usize <= MAX_EPOCH_LEAVES, EpochError::TooManyLeaves);
+        require!(expiry_slot > Clock::get()?.slot, EpochError::EpochExpired);
+        let vault_key = vault.key();
+        let action = OperatorAction::PostEpoch{ epoch_id, merkle_root, total_amount, leaf_count, expiry_slot };
+        vault.authorize_operator_action(vault_key, &ctx.accounts.instruction_sysvar, action, nonce, signature_expiry_slot)?;
+
+        // the whole epoch is reserved up front, same as ledger credits
+        let total_owed = vault.total_owed.checked_add(total_amount).ok_or(VaultError::MathOverflow)?;
+        require!(total_owed <= vault.total_deposited, VaultError::InsufficientBalance);
+        vault.total_owed = total_owed;
+
+        let epoch = &mut ctx.accounts.epoch;
+        epoch.vault = vault.key();
+        epoch.epoch_id = epoch_id;
+        epoch.merkle_root = merkle_root;
+        epoch.total_amount = total_amount;
+        epoch.claimed_amount = 0;
+        epoch.leaf_count = leaf_count;
+        epoch.expiry_slot = expiry_slot;
+        epoch.claimed_bitmap = [0u8; MAX_EPOCH_LEAVES / 8];
+        epoch.bump = ctx.bumps.epoch;
+
+        emit!(EpochPosted{
+            vault : vault.key(),
+            epoch_id,
+            merkle_root,
+            total_amount,
+            leaf_count,
+            expiry_slot
+        });
+        Ok(())
+    }
+
+    pub fn claim_epoch(ctx : Context<ClaimEpoch>, _epoch_id : u64, index : u32, amount : u64, proof : Vec<[u8; 32]>) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        let epoch = &mut ctx.accounts.epoch;
+        let validator = ctx.accounts.validator.key();
+        require!(!vault.is_paused(PAUSE_CLAIMS), VaultError::VaultPaused);
+        require!(amount > 0, VaultError::InvalidAmount);
+        require!(Clock::get()?.slot <= epoch.expiry_slot, EpochError::EpochExpired);
+        require!(index < epoch.leaf_count, EpochError::InvalidLeafIndex);
+        require!(!epoch.is_claimed(index), EpochError::AlreadyClaimed);
+
+        let leaf = epoch_leaf_hash(index, &validator, amount);
+        require!(verify_merkle_proof(&proof, &epoch.merkle_root, leaf), EpochError::InvalidProof);
+
+        if !vault.record_outflow(amount, Clock::get()?.epoch)? {
+            emit!(CircuitBreakerTriggered{
+                vault : vault.key(),
+                attempted : amount,
+                epoch_outflow : vault.epoch_outflow,
+                max_outflow_per_epoch : vault.max_outflow_per_epoch
+            });
+            let vault_key = vault.key();
+            vault.trip_circuit_breaker(vault_key);
+            return Ok(());
+        }
+
+        let claimed_amount = epoch.claimed_amount.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+        require!(claimed_amount <= epoch.total_amount, EpochError::EpochExhausted);
+        epoch.claimed_amount = claimed_amount;
+        epoch.set_claimed(index);
+
+        vault.apply_pending_fee(Clock::get()?.slot);
+        let fee_amount = vault.withdrawal_fee(amount)?;
+        vault.sub_lamports(amount)?;
+        ctx.accounts.validator.add_lamports(amount - fee_amount)?;
+        ctx.accounts.fee_account.add_lamports(fee_amount)?;
+
+        let fee_account = &mut ctx.accounts.fee_account;
+        fee_account.total_fee_amount = fee_account.total_fee_amount.checked_add(fee_amount).ok_or(VaultError::MathOverflow)?;
+        vault.total_owed = vault.total_owed.checked_sub(amount).ok_or(VaultError::MathOverflow)?;
+        vault.total_deposited = vault.total_deposited.checked_sub(amount).ok_or(VaultError::MathOverflow)?;
+        vault.total_withdrawn = vault.total_withdrawn.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+
+        emit!(EpochClaimed{
+            vault : vault.key(),
+            epoch_id : epoch.epoch_id,
+            validator,
+            index,
+            amount,
+            fee : fee_amount
+        });
+        Ok(())
+    }
+
+    pub fn close_epoch(ctx : Context<CloseEpoch>, epoch_id : u64, nonce : u64, signature_expiry_slot : u64) -> Result<()>{
+        let vault = &mut ctx.accounts.vault;
+        let epoch = &ctx.accounts.epoch;
+        require!(Clock::get()?.slot > epoch.expiry_slot, EpochError::EpochNotExpired);
+        let vault_key = vault.key();
+        vault.authorize_operator_action(vault_key, &ctx.accounts.instruction_sysvar, OperatorAction::CloseEpoch{ epoch_id }, nonce, signature_expiry_slot)?;
+
+        // unclaimed rewards return to the vault's free balance
+        let unclaimed = epoch.total_amount - epoch.claimed_amount;
+        vault.total_owed = vault.total_owed.checked_sub(unclaimed).ok_or(VaultError::MathOverflow)?;
+
+        emit!(EpochClosed{
+            vault : vault.key(),
+            epoch_id : epoch.epoch_id,
+            unclaimed
+        });
+        Ok(())
+    }
+
+    pub fn commit_uptime(ctx : Context<CommitUptime>, website_hash : [u8; 32], epoch : u64, results_hash : [u8; 32], uptime_bps : u16, validator_count : u32) -> Result<()>{
+        require!(uptime_bps <= 10_000, UptimeError::InvalidUptime);
+        require!(validator_count > 0, UptimeError::NoValidators);
+
+        let clock = Clock::get()?;
+        let commitment = &mut ctx.accounts.commitment;
+        commitment.vault = ctx.accounts.vault.key();
+        commitment.website_hash = website_hash;
+        commitment.epoch = epoch;
+        commitment.results_hash = results_hash;
+        commitment.uptime_bps = uptime_bps;
+        commitment.validator_count = validator_count;
+        commitment.operator = ctx.accounts.operator.key();
+        commitment.committed_slot = clock.slot;
+        commitment.bump = ctx.bumps.commitment;
+
+        emit!(UptimeCommitted{
+            vault : commitment.vault,
+            website_hash,
+            epoch,
+            results_hash,
+            uptime_bps,
+            validator_count,
+            timestamp : clock.unix_timestamp
+        });
+        Ok(())
+    }
+
+    // Upgrades a vault and its fee account written with an older layout. Both are taken unchecked since an old
+    // layout doesn't deserialize as the current struct, and each one is only rewritten when it is behind.
+    pub fn migrate_vault(ctx : Context<MigrateVault>) -> Result<()>{
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let fee_account_info = ctx.accounts.fee_account.to_account_info();
+        let (vault_version, vault) = read_vault(&vault_info)?;
+        require!(vault.admin == ctx.accounts.admin.key(), VaultError::AdminError);
+        require!(vault.fee_account == fee_account_info.key(), VaultError::FeeAccountMismatch);
+        let (fee_account_version, fee_account) = read_fee_account(&fee_account_info)?;
+        require!(vault_version < VAULT_VERSION || fee_account_version < FEE_ACCOUNT_VERSION, VaultError::AlreadyMigrated);
+
+        if vault_version < VAULT_VERSION {
+            write_migrated_account(&vault_info, &vault, &ctx.accounts.admin, &ctx.accounts.system_program)?;
+            msg!("Vault migrated from version {} to {}", vault_version, VAULT_VERSION);
+        }
+        if fee_account_version < FEE_ACCOUNT_VERSION {
+            write_migrated_account(&fee_account_info, &fee_account, &ctx.accounts.admin, &ctx.accounts.system_program)?;
+            msg!("Fee account migrated from version {} to {}", fee_account_version, FEE_ACCOUNT_VERSION);
+        }
+
+        emit!(VaultMigrated{
+            vault : vault_info.key(),
+            vault_from_version : vault_version,
+            fee_account_from_version : fee_account_version,
+            version : VAULT_VERSION
+        });
+        Ok(())
+    }
+
+    pub fn close_token_vault(ctx : Context<CloseTokenVault>) -> Result<()>{
+        let token_vault = &ctx.accounts.token_vault;
+        require!(ctx.accounts.vault_token_account.amount == 0, VaultError::VaultNotEmpty);
+        require!(token_vault.pending_admin_withdrawal.is_none(), VaultError::VaultNotEmpty);
+
+        let vault = &mut ctx.accounts.vault;
+        let seeds = &[b"vault".as_ref(), vault.creator.as_ref(), &[vault.bump]];
+        let signer = &[&seeds[..]];
+        let cpi = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount{
+                account : ctx.accounts.vault_token_account.to_account_info(),
+                destination : ctx.accounts.admin.to_account_info(),
+                authority : vault.to_account_info()
+            },
+            signer
+        );
+        token::close_account(cpi)?;
+        vault.open_token_vaults = vault.open_token_vaults.saturating_sub(1);
+
+        emit!(TokenVaultClosed{
+            vault : vault.key(),
+            mint : token_vault.mint
+        });
+        Ok(())
+    }
+
+    pub fn close_vault(ctx : Context<CloseVault>) -> Result<()>{
+        let vault = &ctx.accounts.vault;
+        require!(vault.total_deposited == 0 && vault.total_owed == 0, VaultError::VaultNotEmpty);
+        require!(vault.pending_admin_withdrawal.is_none(), VaultError::VaultNotEmpty);
+        // stake and token balances live in their own accounts, they must be returned and closed first
+        require!(vault.total_staked == 0 && vault.open_token_vaults == 0, VaultError::VaultNotEmpty);
+        // lamports sent straight to the PDA have to be taken out with an admin withdrawal first
+        let rent_exempt = Rent::get()?.minimum_balance(vault.to_account_info().data_len());
+        require!(vault.get_lamports() <= rent_exempt, VaultError::VaultNotEmpty);
+
+        emit!(VaultClosed{
+            vault : vault.key(),
+            admin : ctx.accounts.admin.key(),
+            rent : vault.get_lamports()
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts, Debug)]
+pub struct InitializeFeeAccount<'info> {
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + FeeAccount::INIT_SPACE,
+        seeds = [b"fee_account".as_ref() , admin.key().as_ref()],
+        bump
+    )]
+    pub fee_account : Account<'info, FeeAccount>,
+    #[account(mut)]
+    pub admin : Signer<'info>,
+    pub system_program : Program<'info, System>,
+}
+
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct FeeAccount{
+    pub version : u8,
+    pub admin : Pubkey,
+    pub total_fee_amount : u64,
+    pub total_fees_withdrawn : u64
+}
+
+#[derive(Accounts, Debug)]
+pub struct InitializeVault<'info> {
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + Vault::INIT_SPACE,
+        seeds = [b"vault".as_ref() , admin.key().as_ref()],
+        bump
+    )]
+    pub vault : Account<'info, Vault>,
+    #[account(mut)]
+    pub admin : Signer<'info>,
+    pub system_program : Program<'info, System>,
+}
+
+#[account]
+#[derive(InitSpace ,Debug)]
+pub struct Vault{
+    pub version : u8, // layout version, first so migrations can read it at a fixed offset
+    pub admin : Pubkey,
+    pub creator : Pubkey, // admin the PDA was derived from, seeds stay valid after admin rotation
+    pub operator : Pubkey,
+    pub pending_admin : Option<Pubkey>,
+    pub pending_operator : Option<Pubkey>,
+    pub operator_activation_slot : u64,
+    #[max_len(MAX_OPERATORS)]
+    pub operators : Vec<Pubkey>, // M-of-N set authorizing withdrawals, empty means `operator` alone
+    pub operator_threshold : u8,
+    #[max_len(MAX_OPERATORS)]
+    pub pending_operators : Vec<Pubkey>, // replacement set waiting for accept_operator_set, empty when none
+    pub pending_operator_threshold : u8,
+    pub operator_set_activation_slot : u64,
+    pub guardian : Option<Pubkey>, // may set pause flags, never clear them or move funds
+    pub fee_account : Pubkey,
+    pub total_deposited : u64,
+    pub total_withdrawn : u64,
+    pub withdrawal_fee_bps : u16,
+    pub pending_withdrawal_fee_bps : Option<u16>,
+    pub fee_effective_slot : u64,
+    pub last_admin_withdrawal : Option<i64>,
+    pub pause_flags : u8, // PAUSE_* bits, each set bit blocks that kind of operation
+    pub withdrawal_counter : u64,
+    pub total_owed : u64, // rewards credited to validator ledgers but not yet claimed
+    pub admin_withdrawal_cooldown : i64, // seconds between two admin withdrawals
+    pub admin_withdrawal_delay : i64, // seconds between announcing and executing an admin withdrawal
+    pub pending_admin_withdrawal : Option<u64>,
+    pub admin_withdrawal_unlock_at : i64,
+    pub max_outflow_per_epoch : u64, // lamports, 0 disables the circuit breaker
+    pub outflow_epoch : u64,
+    pub epoch_outflow : u64,
+    pub pending_withdrawal_limits : Option<WithdrawalLimits>, // loosened limits waiting out the current delay
+    pub withdrawal_limits_unlock_at : i64,
+    pub open_token_vaults : u16, // token vaults not yet closed, close_vault waits for all of them
+    pub total_staked : u64, // lamports held by stake accounts, staked and unbonding
+    pub operator_action_counter : u64, // nonce of the next quorum signed OperatorActionMessage
+    pub bump : u8
+}
+
+pub const MAX_WITHDRAWAL_FEE_BPS : u16 = 1_000;
+pub const MAX_OPERATORS : usize = 7;
+pub const DEFAULT_ADMIN_WITHDRAWAL_COOLDOWN : i64 = 7 * 24 * 60 * 60;
+pub const DEFAULT_ADMIN_WITHDRAWAL_DELAY : i64 = 48 * 60 * 60;
+pub const FEE_TIMELOCK_SLOTS : u64 = 216_000; // roughly one day of 400ms slots
+pub const OPERATOR_SET_TIMELOCK_SLOTS : u64 = 216_000;
+pub const PAUSE_DEPOSITS : u8 = 1 << 0;
+pub const PAUSE_WITHDRAWALS : u8 = 1 << 1;
+pub const PAUSE_CLAIMS : u8 = 1 << 2;
+pub const PAUSE_ALL : u8 = PAUSE_DEPOSITS | PAUSE_WITHDRAWALS | PAUSE_CLAIMS;
+
+impl Vault {
+    pub fn is_paused(&self, flag : u8) -> bool{
+        self.pause_flags & flag != 0
+    }
+
+    // promotes a scheduled fee change once its timelock has passed
+    pub fn apply_pending_fee(&mut self, slot : u64){
+        if let Some(fee_bps) = self.pending_withdrawal_fee_bps {
+            if slot >= self.fee_effective_slot {
+                self.withdrawal_fee_bps = fee_bps;
+                self.pending_withdrawal_fee_bps = None;
+            }
+        }
+    }
+
+    // The operator set and threshold authorizing signed withdrawals and operator actions. Vaults that
+    // never configured a set keep the original single operator, 1-of-1.
+    pub fn withdrawal_authority(&self) -> (Vec<Pubkey>, u8){
+        if self.operators.is_empty() {
+            (vec![self.operator], 1)
+        } else {
+            (self.operators.clone(), self.operator_threshold)
+        }
+    }
+
+    // Adds `amount` to the current epoch's lamport outflow. Returns false when the cap would be exceeded;
+    // the caller must then trip the breaker, skip the transfer and return Ok so the pause is persisted.
+    pub fn record_outflow(&mut self, amount : u64, epoch : u64) -> Result<bool>{
+        add_epoch_outflow(&mut self.outflow_epoch, &mut self.epoch_outflow, self.max_outflow_per_epoch, amount, epoch)
+    }
+
+    // Operator instructions that create or release a claim on vault funds need the same quorum as a
+    // withdrawal: the set has to sign exactly `action` with the current action nonce, which is consumed.
+    pub fn authorize_operator_action(&mut self, vault_key : Pubkey, instruction_sysvar : &AccountInfo, action : OperatorAction, nonce : u64, expiry_slot : u64) -> Result<()>{
+        let (operators, threshold) = self.withdrawal_authority();
+        let message_bytes = load_operator_signed_message(instruction_sysvar, &operators, threshold)?;
+        let message = OperatorActionMessage::parse(&message_bytes)?;
+        require!(message.vault == vault_key, VaultError::VaultMismatch);
+        require!(message.action == action, VaultError::ActionMismatch);
+        require!(message.nonce == nonce && nonce == self.operator_action_counter, VaultError::NonceMismatch);
+        require!(message.expiry_slot == expiry_slot && Clock::get()?.slot <= expiry_slot, VaultError::SignatureExpired);
+        self.operator_action_counter = self.operator_action_counter.checked_add(1).ok_or(VaultError::MathOverflow)?;
+        Ok(())
+    }
+
+    // A tripped outflow cap, lamport or token, stops every withdrawal and claim until the admin unpauses.
+    // The instruction still succeeds, PauseStatus tells the submitter that nothing was transferred.
+    pub fn trip_circuit_breaker(&mut self, vault_key : Pubkey){
+        msg!("Outflow cap exceeded, pausing withdrawals and claims");
+        let flags = PAUSE_WITHDRAWALS | PAUSE_CLAIMS;
+        self.pause_flags |= flags;
+        emit!(PauseStatus{
+            vault : vault_key,
+            flags,
+            paused : true,
+            pause_flags : self.pause_flags,
+            changed_by : vault_key
+        });
+    }
+
+    // A limit change is stricter when it can only slow money down: longer or equal delay and cooldown, and
+    // a cap that is set and no higher than the current one.
+    pub fn is_stricter(&self, limits : &WithdrawalLimits) -> bool{
+        limits.admin_withdrawal_cooldown >= self.admin_withdrawal_cooldown
+            && limits.admin_withdrawal_delay >= self.admin_withdrawal_delay
+            && is_stricter_cap(limits.max_outflow_per_epoch, self.max_outflow_per_epoch)
+    }
+
+    fn set_withdrawal_limits(&mut self, limits : &WithdrawalLimits){
+        self.admin_withdrawal_cooldown = limits.admin_withdrawal_cooldown;
+        self.admin_withdrawal_delay = limits.admin_withdrawal_delay;
+        self.max_outflow_per_epoch = limits.max_outflow_per_epoch;
+    }
+
+    pub fn withdrawal_fee(&self, amount : u64) -> Result<u64>{
+        let fee = (amount as u128)
+            .checked_mul(self.withdrawal_fee_bps as u128)
+            .ok_or(VaultError::MathOverflow)?
+            / 10_000;
+        Ok(fee as u64)
+    }
+}
+
+// Adds `amount` to an epoch outflow counter, resetting it when a new epoch starts. Returns false and
+// leaves the counter alone when the cap (0 disables it) would be exceeded.
+fn add_epoch_outflow(outflow_epoch : &mut u64, epoch_outflow : &mut u64, max_outflow_per_epoch : u64, amount : u64, epoch : u64) -> Result<bool>{
+    if *outflow_epoch != epoch {
+        *outflow_epoch = epoch;
+        *epoch_outflow = 0;
+    }
+    let outflow = epoch_outflow.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+    if max_outflow_per_epoch > 0 && outflow > max_outflow_per_epoch {
+        return Ok(false);
+    }
+    *epoch_outflow = outflow;
+    Ok(true)
+}
+
+// an outflow cap of 0 means no cap, so it is the loosest value
+fn is_stricter_cap(new_cap : u64, current_cap : u64) -> bool{
+    new_cap != 0 && (current_cap == 0 || new_cap <= current_cap)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
+pub struct WithdrawalLimits{
+    pub admin_withdrawal_cooldown : i64,
+    pub admin_withdrawal_delay : i64,
+    pub max_outflow_per_epoch : u64
+}
+
+pub const VAULT_VERSION : u8 = 1;
+pub const FEE_ACCOUNT_VERSION : u8 = 1;
+
+/// Vault layout before the version byte was added (version 0). Only used to read accounts that still
+/// need `migrate_vault`.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Debug)]
+pub struct VaultV0{
+    pub admin : Pubkey,
+    pub operator : Pubkey,
+    pub fee_account : Pubkey,
+    pub total_deposited : u64,
+    pub total_withdrawn : u64,
+    pub withdrawal_fee_bps : u16,
+    pub last_admin_withdrawal : Option<i64>,
+    pub is_paused : bool,
+    pub withdrawal_counter : u64,
+    pub bump : u8
+}
+
+impl From<VaultV0> for Vault {
+    // every field added since gets the value `initialize_vault` gives a fresh vault. Version 0 predates
+    // reward ledgers, staking and token vaults, so total_owed, total_staked and open_token_vaults are 0.
+    fn from(legacy : VaultV0) -> Self {
+        Vault{
+            version : VAULT_VERSION,
+            admin : legacy.admin,
+            creator : legacy.admin,
+            operator : legacy.operator,
+            pending_admin : None,
+            pending_operator : None,
+            operator_activation_slot : 0,
+            operators : Vec::new(),
+            operator_threshold : 0,
+            pending_operators : Vec::new(),
+            pending_operator_threshold : 0,
+            operator_set_activation_slot : 0,
+            guardian : None,
+            fee_account : legacy.fee_account,
+            total_deposited : legacy.total_deposited,
+            total_withdrawn : legacy.total_withdrawn,
+            withdrawal_fee_bps : legacy.withdrawal_fee_bps,
+            pending_withdrawal_fee_bps : None,
+            fee_effective_slot : 0,
+            last_admin_withdrawal : legacy.last_admin_withdrawal,
+            pause_flags : if legacy.is_paused { PAUSE_ALL } else { 0 },
+            withdrawal_counter : legacy.withdrawal_counter,
+            total_owed : 0,
+            admin_withdrawal_cooldown : DEFAULT_ADMIN_WITHDRAWAL_COOLDOWN,
+            admin_withdrawal_delay : DEFAULT_ADMIN_WITHDRAWAL_DELAY,
+            pending_admin_withdrawal : None,
+            admin_withdrawal_unlock_at : 0,
+            max_outflow_per_epoch : 0,
+            outflow_epoch : 0,
+            epoch_outflow : 0,
+            pending_withdrawal_limits : None,
+            withdrawal_limits_unlock_at : 0,
+            open_token_vaults : 0,
+            total_staked : 0,
+            operator_action_counter : 0,
+            bump : legacy.bump
+        }
+    }
+}
+
+/// Fee account layout before the version byte was added (version 0).
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Debug)]
+pub struct FeeAccountV0{
+    pub admin : Pubkey,
+    pub total_fee_amount : u64
+}
+
+impl From<FeeAccountV0> for FeeAccount {
+    fn from(legacy : FeeAccountV0) -> Self {
+        FeeAccount{
+            version : FEE_ACCOUNT_VERSION,
+            admin : legacy.admin,
+            total_fee_amount : legacy.total_fee_amount,
+            total_fees_withdrawn : 0
+        }
+    }
+}
+
+// Checks that `account` is a program account with the discriminator of `T` and returns its data.
+fn program_account_data<'a, T : Discriminator>(account : &'a AccountInfo) -> Result<std::cell::Ref<'a, &'a mut [u8]>>{
+    require!(account.owner == &crate::ID, VaultError::InvalidAccountLayout);
+    let data = account.try_borrow_data()?;
+    require!(data.len() > 8 && &data[..8] == T::DISCRIMINATOR, VaultError::InvalidAccountLayout);
+    Ok(data)
+}
+
+// Returns the stored layout version of a vault together with its state in the current layout. Unversioned
+// accounts are recognized by their size, every later layout starts with the version byte.
+fn read_vault(account : &AccountInfo) -> Result<(u8, Vault)>{
+    let data = program_account_data::<Vault>(account)?;
+    if data.len() == 8 + VaultV0::INIT_SPACE {
+        let legacy = VaultV0::deserialize(&mut &data[8..]).map_err(|_| error!(VaultError::InvalidAccountLayout))?;
+        return Ok((0, legacy.into()));
+    }
+    match data[8] {
+        VAULT_VERSION => Ok((VAULT_VERSION, Vault::try_deserialize(&mut &data[..]).map_err(|_| error!(VaultError::InvalidAccountLayout))?)),
+        _ => err!(VaultError::UnsupportedVersion)
+    }
+}
+
+fn read_fee_account(account : &AccountInfo) -> Result<(u8, FeeAccount)>{
+    let data = program_account_data::<FeeAccount>(account)?;
+    if data.len() == 8 + FeeAccountV0::INIT_SPACE {
+        let legacy = FeeAccountV0::deserialize(&mut &data[8..]).map_err(|_| error!(VaultError::InvalidAccountLayout))?;
+        return Ok((0, legacy.into()));
+    }
+    match data[8] {
+        FEE_ACCOUNT_VERSION => Ok((FEE_ACCOUNT_VERSION, FeeAccount::try_deserialize(&mut &data[..]).map_err(|_| error!(VaultError::InvalidAccountLayout))?)),
+        _ => err!(VaultError::UnsupportedVersion)
+    }
+}
+
+// Grows an account to the current layout of `T` and writes the upgraded state. The admin pays only the rent
+// difference, so lamports already held (vault deposits, collected fees) stay exactly as accounted.
+fn write_migrated_account<'info, T : AccountSerialize + Space>(account : &AccountInfo<'info>, state : &T, admin : &Signer<'info>, system_program : &Program<'info, System>) -> Result<()>{
+    let new_len = 8 + T::INIT_SPACE;
+    let rent = Rent::get()?;
+    let top_up = rent.minimum_balance(new_len).saturating_sub(rent.minimum_balance(account.data_len()));
+    if top_up > 0 {
+        let cpi_context = CpiContext::new(
+            system_program.to_account_info(),
+            anchor_lang::system_program::Transfer{
+                from : admin.to_account_info(),
+                to : account.clone()
+            });
+        anchor_lang::system_program::transfer(cpi_context, top_up)?;
+    }
+    if account.data_len() < new_len {
+        account.resize(new_len)?;
+    }
+    let mut data = account.try_borrow_mut_data()?;
+    state.try_serialize(&mut &mut data[..])?;
+    Ok(())
+}
+
+#[derive(Accounts, Debug)]
+pub struct MigrateVault<'info>{
+    /// CHECK: may still hold an older layout; owner, discriminator and admin are checked by the instruction
+    #[account(mut)]
+    pub vault : UncheckedAccount<'info>,
+    /// CHECK: may still hold an older layout; must be the fee account recorded in the vault
+    #[account(mut)]
+    pub fee_account : UncheckedAccount<'info>,
+    #[account(mut)]
+    pub admin : Signer<'info>,
+    pub system_program : Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTokenVault<'info>{
+    #[account(mut, has_one = admin @ VaultError::AdminError)]
+    pub vault : Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"token_vault".as_ref(), vault.key().as_ref(), mint.key().as_ref()],
+        bump = token_vault.bump,
+        close = admin
+    )]
+    pub token_vault : Account<'info, TokenVault>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = vault)]
+    pub vault_token_account : Account<'info, TokenAccount>,
+    pub mint : Account<'info, Mint>,
+    #[account(mut)]
+    pub admin : Signer<'info>,
+    pub token_program : Program<'info, Token>,
+}
+
+#[derive(Accounts, Debug)]
+pub struct CloseVault<'info>{
+    #[account(mut, has_one = admin @ VaultError::AdminError, close = admin)]
+    pub vault : Account<'info, Vault>,
+    #[account(mut)]
+    pub admin : Signer<'info>,
+}
+
+#[derive(Accounts, Debug)]
+pub struct SetWithdrawalFee<'info>{
+    #[account(mut, has_one = admin @ VaultError::AdminError)]
+    pub vault : Account<'info, Vault>,
+    pub admin : Signer<'info>,
+}
+
+#[derive(Accounts, Debug)]
+pub struct WithdrawFees<'info>{
+    #[account(
+        mut,
+        seeds = [b"fee_account".as_ref(), admin.key().as_ref()],
+        bump,
+        has_one = admin @ FeeAccountError::AdminError
+    )]
+    pub fee_account : Account<'info, FeeAccount>,
+    #[account(mut)]
+    pub admin : Signer<'info>,
+}
+
+#[derive(Accounts, Debug)]
+pub struct ProposeKey<'info>{
+    #[account(mut, has_one = admin @ VaultError::AdminError)]
+    pub vault : Account<'info, Vault>,
+    pub admin : Signer<'info>,
+}
+
+#[derive(Accounts, Debug)]
+pub struct AcceptOperator<'info>{
+    #[account(mut)]
+    pub vault : Account<'info, Vault>,
+    pub new_operator : Signer<'info>,
+}
+
+#[derive(Accounts, Debug)]
+pub struct AcceptOperatorSet<'info>{
+    #[account(mut)]
+    pub vault : Account<'info, Vault>,
+}
+
+#[derive(Accounts, Debug)]
+pub struct AcceptAdmin<'info>{
+    #[account(mut)]
+    pub vault : Account<'info, Vault>,
+    pub new_admin : Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTokenVault<'info>{
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + TokenVault::INIT_SPACE,
+        seeds = [b"token_vault".as_ref(), vault.key().as_ref(), mint.key().as_ref()],
         bump
     )]
+    pub token_vault : Account<'info, TokenVault>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        associated_token::mint = mint,
+        associated_token::authority = vault
+    )]
+    pub vault_token_account : Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        associated_token::mint = mint,
+        associated_token::authority = fee_account
+    )]
+    pub fee_token_account : Account<'info, TokenAccount>,
+    #[account(mut, has_one = admin @ VaultError::AdminError, has_one = fee_account)]
+    pub vault : Account<'info, Vault>,
     pub fee_account : Account<'info, FeeAccount>,
+    pub mint : Account<'info, Mint>,
+    #[account(mut)]
+    pub admin : Signer<'info>,
+    pub token_program : Program<'info, Token>,
+    pub associated_token_program : Program<'info, AssociatedToken>,
+    pub system_program : Program<'info, System>,
+}
+
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct TokenVault{
+    pub vault : Pubkey,
+    pub mint : Pubkey,
+    pub total_deposited : u64,
+    pub total_withdrawn : u64,
+    pub total_fees : u64,
+    pub pending_admin_withdrawal : Option<u64>,
+    pub admin_withdrawal_unlock_at : i64,
+    pub last_admin_withdrawal : Option<i64>,
+    pub max_outflow_per_epoch : u64, // token base units, 0 disables the circuit breaker for this mint
+    pub pending_max_outflow_per_epoch : Option<u64>,
+    pub outflow_limit_unlock_at : i64,
+    pub outflow_epoch : u64,
+    pub epoch_outflow : u64,
+    pub bump : u8
+}
+
+impl TokenVault {
+    // Same as Vault::record_outflow for this mint's cap
+    pub fn record_outflow(&mut self, amount : u64, epoch : u64) -> Result<bool>{
+        add_epoch_outflow(&mut self.outflow_epoch, &mut self.epoch_outflow, self.max_outflow_per_epoch, amount, epoch)
+    }
+}
+
+#[derive(Accounts)]
+pub struct DepositToken<'info>{
+    pub vault : Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"token_vault".as_ref(), vault.key().as_ref(), mint.key().as_ref()],
+        bump = token_vault.bump
+    )]
+    pub token_vault : Account<'info, TokenVault>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = vault)]
+    pub vault_token_account : Account<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = user)]
+    pub user_token_account : Account<'info, TokenAccount>,
+    pub mint : Account<'info, Mint>,
+    pub user : Signer<'info>,
+    pub token_program : Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawToken<'info>{
+    #[account(mut)]
+    pub vault : Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"token_vault".as_ref(), vault.key().as_ref(), mint.key().as_ref()],
+        bump = token_vault.bump
+    )]
+    pub token_vault : Account<'info, TokenVault>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = vault)]
+    pub vault_token_account : Account<'info, TokenAccount>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = vault.fee_account)]
+    pub fee_token_account : Account<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = user)]
+    pub user_token_account : Account<'info, TokenAccount>,
+    pub mint : Account<'info, Mint>,
+    pub user : Signer<'info>,
+    /// CHECK: address is constrained to the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instruction_sysvar : AccountInfo<'info>,
+    pub token_program : Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AnnounceAdminWithdrawalToken<'info>{
+    #[account(has_one = admin @ VaultError::AdminError)]
+    pub vault : Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"token_vault".as_ref(), vault.key().as_ref(), mint.key().as_ref()],
+        bump = token_vault.bump
+    )]
+    pub token_vault : Account<'info, TokenVault>,
+    pub mint : Account<'info, Mint>,
+    pub admin : Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AdminWithdrawToken<'info>{
+    #[account(mut, has_one = admin @ VaultError::AdminError)]
+    pub vault : Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"token_vault".as_ref(), vault.key().as_ref(), mint.key().as_ref()],
+        bump = token_vault.bump
+    )]
+    pub token_vault : Account<'info, TokenVault>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = vault)]
+    pub vault_token_account : Account<'info, TokenAccount>,
+    #[account(mut, token::mint = mint)]
+    pub admin_token_account : Account<'info, TokenAccount>,
+    pub mint : Account<'info, Mint>,
+    pub admin : Signer<'info>,
+    pub token_program : Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTokenFees<'info>{
+    #[account(
+        seeds = [b"fee_account".as_ref(), admin.key().as_ref()],
+        bump,
+        has_one = admin @ FeeAccountError::AdminError
+    )]
+    pub fee_account : Account<'info, FeeAccount>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = fee_account)]
+    pub fee_token_account : Account<'info, TokenAccount>,
+    #[account(mut, token::mint = mint)]
+    pub admin_token_account : Account<'info, TokenAccount>,
+    pub mint : Account<'info, Mint>,
+    pub admin : Signer<'info>,
+    pub token_program : Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum ValidatorStatus{
+    Pending,
+    Active,
+    Inactive
+}
+
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct ValidatorRecord{
+    pub vault : Pubkey,
+    pub wallet : Pubkey,
+    pub device_hash : [u8; 32], // hash of the device fingerprint, the raw identifier stays off-chain
+    pub region_code : u16, // coarse region only, never a precise location
+    pub registration_slot : u64,
+    pub status : ValidatorStatus,
+    pub status_updated_slot : u64,
+    pub bump : u8
+}
+
+#[derive(Accounts, Debug)]
+pub struct RegisterValidator<'info>{
+    #[account(
+        init,
+        payer = validator,
+        space = 8 + ValidatorRecord::INIT_SPACE,
+        seeds = [b"validator".as_ref(), vault.key().as_ref(), validator.key().as_ref()],
+        bump
+    )]
+    pub validator_record : Account<'info, ValidatorRecord>,
+    pub vault : Account<'info, Vault>,
+    #[account(mut)]
+    pub validator : Signer<'info>,
+    pub system_program : Program<'info, System>,
+}
+
+#[derive(Accounts, Debug)]
+pub struct SetValidatorStatus<'info>{
+    #[account(mut, has_one = operator @ VaultError::OperatorError)]
+    pub vault : Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"validator".as_ref(), vault.key().as_ref(), validator_record.wallet.as_ref()],
+        bump = validator_record.bump,
+        has_one = vault @ ValidatorError::VaultMismatch
+    )]
+    pub validator_record : Account<'info, ValidatorRecord>,
+    pub operator : Signer<'info>,
+    /// CHECK: address is constrained to the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instruction_sysvar : AccountInfo<'info>,
+}
+
+pub const UNBONDING_PERIOD_SLOTS : u64 = 432_000; // one epoch
+
+#[derive(Accounts, Debug)]
+pub struct Stake<'info>{
+    #[account(
+        init_if_needed,
+        payer = validator,
+        space = 8 + StakeAccount::INIT_SPACE,
+        seeds = [b"stake".as_ref(), vault.key().as_ref(), validator.key().as_ref()],
+        bump
+    )]
+    pub stake_account : Account<'info, StakeAccount>,
+    #[account(mut)]
+    pub vault : Account<'info, Vault>,
+    #[account(mut)]
+    pub validator : Signer<'info>,
+    #[account(
+        seeds = [b"validator".as_ref(), vault.key().as_ref(), validator.key().as_ref()],
+        bump = validator_record.bump,
+        constraint = validator_record.status == ValidatorStatus::Active @ ValidatorError::NotActive
+    )]
+    pub validator_record : Account<'info, ValidatorRecord>,
+    pub system_program : Program<'info, System>,
+}
+
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct StakeAccount{
+    pub vault : Pubkey,
+    pub validator : Pubkey,
+    pub staked_amount : u64,
+    pub unbonding_amount : u64,
+    pub unbonding_release_slot : u64,
+    pub total_slashed : u64,
+    pub bump : u8
+}
+
+#[derive(Accounts, Debug)]
+pub struct Unstake<'info>{
+    #[account(
+        mut,
+        seeds = [b"stake".as_ref(), stake_account.vault.as_ref(), validator.key().as_ref()],
+        bump = stake_account.bump,
+        has_one = validator @ StakeError::ValidatorMismatch
+    )]
+    pub stake_account : Account<'info, StakeAccount>,
+    #[account(mut, address = stake_account.vault @ StakeError::VaultMismatch)]
+    pub vault : Account<'info, Vault>,
+    #[account(mut)]
+    pub validator : Signer<'info>,
+}
+
+#[derive(Accounts, Debug)]
+pub struct Slash<'info>{
+    #[account(mut, has_one = fee_account)]
+    pub vault : Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"stake".as_ref(), vault.key().as_ref(), stake_account.validator.as_ref()],
+        bump = stake_account.bump,
+        has_one = vault @ StakeError::VaultMismatch
+    )]
+    pub stake_account : Account<'info, StakeAccount>,
+    #[account(mut)]
+    pub fee_account : Account<'info, FeeAccount>,
+    pub authority : Signer<'info>,
+}
+
+pub const MAX_EPOCH_LEAVES : usize = 1024;
+
+#[derive(Accounts, Debug)]
+#[instruction(epoch_id : u64)]
+pub struct PostEpoch<'info>{
+    #[account(
+        init,
+        payer = operator,
+        space = 8 + Epoch::INIT_SPACE,
+        seeds = [b"epoch".as_ref(), vault.key().as_ref(), epoch_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub epoch : Account<'info, Epoch>,
+    #[account(mut, has_one = operator @ VaultError::OperatorError)]
+    pub vault : Account<'info, Vault>,
+    #[account(mut)]
+    pub operator : Signer<'info>,
+    pub system_program : Program<'info, System>,
+    /// CHECK: address is constrained to the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instruction_sysvar : AccountInfo<'info>,
+}
+
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Epoch{
+    pub vault : Pubkey,
+    pub epoch_id : u64,
+    pub merkle_root : [u8; 32],
+    pub total_amount : u64,
+    pub claimed_amount : u64,
+    pub leaf_count : u32,
+    pub expiry_slot : u64,
+    pub claimed_bitmap : [u8; MAX_EPOCH_LEAVES / 8],
+    pub bump : u8
+}
+
+impl Epoch {
+    pub fn is_claimed(&self, index : u32) -> bool{
+        self.claimed_bitmap[index as usize / 8] & (1 << (index % 8)) != 0
+    }
+
+    pub fn set_claimed(&mut self, index : u32){
+        self.claimed_bitmap[index as usize / 8] |= 1 << (index % 8);
+    }
+}
+
+/// Leaf of an epoch reward tree: sha256(0x00 || index u32 le || validator || amount u64 le).
+/// The 0x00/0x01 prefixes keep a leaf from ever being accepted as an inner node.
+pub fn epoch_leaf_hash(index : u32, validator : &Pubkey, amount : u64) -> [u8; 32]{
+    hashv(&[&[0u8], &index.to_le_bytes(), validator.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+// inner nodes hash the sorted pair, so proofs don't need left/right flags
+pub fn verify_merkle_proof(proof : &[[u8; 32]], root : &[u8; 32], leaf : [u8; 32]) -> bool{
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            hashv(&[&[1u8], &node, sibling]).to_bytes()
+        } else {
+            hashv(&[&[1u8], sibling, &node]).to_bytes()
+        }
+    });
+    computed == *root
+}
+
+#[derive(Accounts, Debug)]
+#[instruction(epoch_id : u64)]
+pub struct ClaimEpoch<'info>{
+    #[account(mut)]
+    pub vault : Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"epoch".as_ref(), vault.key().as_ref(), epoch_id.to_le_bytes().as_ref()],
+        bump = epoch.bump,
+        has_one = vault @ EpochError::VaultMismatch
+    )]
+    pub epoch : Account<'info, Epoch>,
+    #[account(mut)]
+    pub validator : Signer<'info>,
+    #[account(
+        seeds = [b"validator".as_ref(), vault.key().as_ref(), validator.key().as_ref()],
+        bump = validator_record.bump,
+        constraint = validator_record.status == ValidatorStatus::Active @ ValidatorError::NotActive
+    )]
+    pub validator_record : Account<'info, ValidatorRecord>,
+    #[account(mut, address = vault.fee_account)]
+    pub fee_account : Account<'info, FeeAccount>,
+}
+
+#[derive(Accounts, Debug)]
+#[instruction(epoch_id : u64)]
+pub struct CloseEpoch<'info>{
+    #[account(mut, has_one = operator @ VaultError::OperatorError)]
+    pub vault : Account<'info, Vault>,
+    #[account(
+        mut,
+        close = operator,
+        seeds = [b"epoch".as_ref(), vault.key().as_ref(), epoch_id.to_le_bytes().as_ref()],
+        bump = epoch.bump,
+        has_one = vault @ EpochError::VaultMismatch
+    )]
+    pub epoch : Account<'info, Epoch>,
     #[account(mut)]
-    pub admin : Signer<'info>,
-    pub system_program : SystemAccount<'info>,
-}
-
-#[account]
-#[derive(InitSpace, Debug)]
-pub struct FeeAccount{
-    pub admin : Pubkey,
-    pub total_fee_amount : u64
+    pub operator : Signer<'info>,
+    /// CHECK: address is constrained to the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instruction_sysvar : AccountInfo<'info>,
 }
 
+// One commitment per website per reporting epoch; `init` makes it write-once so a published SLA
+// number can't be rewritten later.
 #[derive(Accounts, Debug)]
-pub struct InitializeVault<'info> {
+#[instruction(website_hash : [u8; 32], epoch : u64)]
+pub struct CommitUptime<'info>{
     #[account(
-        init_if_needed,
-        payer = admin,
-        space = 8 + Vault::INIT_SPACE,
-        seeds = [b"vault".as_ref() , admin.key().as_ref()],
+        init,
+        payer = operator,
+        space = 8 + UptimeCommitment::INIT_SPACE,
+        seeds = [b"uptime".as_ref(), vault.key().as_ref(), website_hash.as_ref(), epoch.to_le_bytes().as_ref()],
         bump
     )]
+    pub commitment : Account<'info, UptimeCommitment>,
+    #[account(has_one = operator @ VaultError::OperatorError)]
     pub vault : Account<'info, Vault>,
     #[account(mut)]
-    pub admin : Signer<'info>,
-    pub system_program : SystemAccount<'info>,
+    pub operator : Signer<'info>,
+    pub system_program : Program<'info, System>,
 }
 
 #[account]
-#[derive(InitSpace ,Debug)]
-pub struct Vault{
-    pub admin : Pubkey,
+#[derive(InitSpace, Debug)]
+pub struct UptimeCommitment{
+    pub vault : Pubkey,
+    pub website_hash : [u8; 32], // sha256 of the backend website id
+    pub epoch : u64,
+    pub results_hash : [u8; 32], // hash of the aggregated measurement set for the epoch
+    pub uptime_bps : u16,
+    pub validator_count : u32,
     pub operator : Pubkey,
-    pub fee_account : Pubkey,
-    pub total_deposited : u64,
-    pub total_withdrawn : u64,
-    pub withdrawal_fee_bps : u16,
-    pub last_admin_withdrawal : Option<i64>,
-    pub is_paused : bool,
-    pub withdrawal_counter : u64,
+    pub committed_slot : u64,
     pub bump : u8
 }
 
+/// Canonical payload the operator signs to authorize a withdrawal. It is the borsh encoding of
+/// this struct (vault, recipient, amount, nonce, expiry slot), 88 bytes with little endian integers.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct WithdrawalMessage{
+    pub vault : Pubkey,
+    pub recipient : Pubkey,
+    pub amount : u64,
+    pub nonce : u64,
+    pub expiry_slot : u64
+}
+
+impl WithdrawalMessage {
+    pub const LEN : usize = 32 + 32 + 8 + 8 + 8;
+
+    pub fn to_bytes(&self) -> Vec<u8>{
+        self.try_to_vec().expect("fixed size message always serializes")
+    }
+
+    pub fn parse(data : &[u8]) -> Result<Self>{
+        require!(data.len() == Self::LEN, VaultError::SignedMessageMissing);
+        Self::try_from_slice(data).map_err(|_| error!(VaultError::SignedMessageMissing))
+    }
+}
+
+pub const OPERATOR_ACTION_DOMAIN : [u8; 8] = *b"dupt-act";
+
+/// Payload the operator set signs to authorize an operator instruction, see
+/// [`Vault::authorize_operator_action`]. Borsh encoded and led by [`OPERATOR_ACTION_DOMAIN`] instead of
+/// the vault key, so it never parses as a withdrawal message and a withdrawal never parses as an action.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct OperatorActionMessage{
+    pub domain : [u8; 8],
+    pub vault : Pubkey,
+    pub action : OperatorAction,
+    pub nonce : u64,
+    pub expiry_slot : u64
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum OperatorAction{
+    SetValidatorStatus{ wallet : Pubkey, status : ValidatorStatus },
+    CreditRewards{ reward_accounts : Vec<Pubkey>, amounts : Vec<u64> },
+    PostEpoch{ epoch_id : u64, merkle_root : [u8; 32], total_amount : u64, leaf_count : u32, expiry_slot : u64 },
+    CloseEpoch{ epoch_id : u64 }
+}
+
+impl OperatorActionMessage {
+    pub fn new(vault : Pubkey, action : OperatorAction, nonce : u64, expiry_slot : u64) -> Self{
+        Self{ domain : OPERATOR_ACTION_DOMAIN, vault, action, nonce, expiry_slot }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>{
+        self.try_to_vec().expect("operator action always serializes")
+    }
+
+    pub fn parse(data : &[u8]) -> Result<Self>{
+        let message = Self::try_from_slice(data).map_err(|_| error!(VaultError::SignedMessageMissing))?;
+        require!(message.domain == OPERATOR_ACTION_DOMAIN, VaultError::SignedMessageMissing);
+        Ok(message)
+    }
+}
+
+const ED25519_SIGNATURE_OFFSETS_START : usize = 2;
+const ED25519_SIGNATURE_OFFSETS_SIZE : usize = 14;
+const ED25519_PUBKEY_SIZE : usize = 32;
+const ED25519_SIGNATURE_SIZE : usize = 64;
+
+// Reads every Ed25519SignatureOffsets entry of an ed25519 program instruction and returns the signer
+// pubkey and signed message of each. All offsets have to point into the instruction itself (index
+// u16::MAX), otherwise the verified data could live in another instruction than the one we inspect.
+fn parse_ed25519_instruction(data : &[u8]) -> Result<Vec<(Pubkey, &[u8])>>{
+    require!(data.len() >= ED25519_SIGNATURE_OFFSETS_START, VaultError::InvalidSignatureInstruction);
+    let num_signatures = data[0] as usize;
+    require!(num_signatures > 0, VaultError::InvalidSignatureInstruction);
+    require!(
+        data.len() >= ED25519_SIGNATURE_OFFSETS_START + num_signatures * ED25519_SIGNATURE_OFFSETS_SIZE,
+        VaultError::InvalidSignatureInstruction
+    );
+
+    let mut signatures = Vec::with_capacity(num_signatures);
+    for signature_index in 0..num_signatures {
+        let start = ED25519_SIGNATURE_OFFSETS_START + signature_index * ED25519_SIGNATURE_OFFSETS_SIZE;
+        let offsets = &data[start..start + ED25519_SIGNATURE_OFFSETS_SIZE];
+        let read_u16 = |index : usize| u16::from_le_bytes([offsets[index * 2], offsets[index * 2 + 1]]);
+        let signature_offset = read_u16(0) as usize;
+        let signature_instruction_index = read_u16(1);
+        let public_key_offset = read_u16(2) as usize;
+        let public_key_instruction_index = read_u16(3);
+        let message_data_offset = read_u16(4) as usize;
+        let message_data_size = read_u16(5) as usize;
+        let message_instruction_index = read_u16(6);
+
+        require!(
+            signature_instruction_index == u16::MAX && public_key_instruction_index == u16::MAX && message_instruction_index == u16::MAX,
+            VaultError::InvalidSignatureInstruction
+        );
+        require!(signature_offset + ED25519_SIGNATURE_SIZE <= data.len(), VaultError::InvalidSignatureInstruction);
+
+        let public_key = data.get(public_key_offset..public_key_offset + ED25519_PUBKEY_SIZE).ok_or(VaultError::InvalidSignatureInstruction)?;
+        let message = data.get(message_data_offset..message_data_offset + message_data_size).ok_or(VaultError::InvalidSignatureInstruction)?;
+        let signer = Pubkey::try_from(public_key).map_err(|_| error!(VaultError::InvalidSignatureInstruction))?;
+        signatures.push((signer, message));
+    }
+    Ok(signatures)
+}
+
+// Operator signatures are verified by ed25519 program instructions placed before the current one,
+// one or several signatures per instruction. The first operator signed message found is the one being
+// authorized and it needs `threshold` distinct operators over those exact bytes.
+fn load_operator_signed_message(instruction_sysvar : &AccountInfo, operators : &[Pubkey], threshold : u8) -> Result<Vec<u8>>{
+    let current_index = load_current_index_checked(instruction_sysvar)? as usize;
+    require!(current_index > 0, VaultError::ProgramMissing);
+
+    let mut found_signature_ix = false;
+    let mut message : Option<Vec<u8>> = None;
+    let mut signers : Vec<Pubkey> = Vec::new();
+    for index in 0..current_index {
+        let ix = load_instruction_at_checked(index, instruction_sysvar)?;
+        if ix.program_id != ed25519_program::ID {
+            continue;
+        }
+        found_signature_ix = true;
+        for (signer, signed_message) in parse_ed25519_instruction(&ix.data)? {
+            if !operators.contains(&signer) || signers.contains(&signer) {
+                continue;
+            }
+            let authorized = message.get_or_insert_with(|| signed_message.to_vec());
+            if authorized.as_slice() == signed_message {
+                signers.push(signer);
+            }
+        }
+    }
+
+    require!(found_signature_ix, VaultError::ProgramMissing);
+    let message = message.ok_or(VaultError::OperatorError)?;
+    require!(signers.len() >= threshold as usize, VaultError::InsufficientOperatorSignatures);
+    Ok(message)
+}
+
+/// Token variant of [`WithdrawalMessage`], the mint is bound into the payload so a signature for one
+/// asset can't be spent against another. 120 bytes, distinct from the 88 byte lamport message.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct TokenWithdrawalMessage{
+    pub vault : Pubkey,
+    pub mint : Pubkey,
+    pub recipient : Pubkey,
+    pub amount : u64,
+    pub nonce : u64,
+    pub expiry_slot : u64
+}
+
+impl TokenWithdrawalMessage {
+    pub const LEN : usize = 32 + 32 + 32 + 8 + 8 + 8;
+
+    pub fn to_bytes(&self) -> Vec<u8>{
+        self.try_to_vec().expect("fixed size message always serializes")
+    }
+
+    pub fn parse(data : &[u8]) -> Result<Self>{
+        require!(data.len() == Self::LEN, VaultError::SignedMessageMissing);
+        Self::try_from_slice(data).map_err(|_| error!(VaultError::SignedMessageMissing))
+    }
+}
+
 #[derive(Accounts, Debug)]
 pub struct Deposit<'info> {
     #[account(mut)]
     pub vault : Account<'info, Vault>,
     #[account(mut)]
     pub user : Signer<'info>,
-    pub system_program : SystemAccount<'info>,
+    pub system_program : Program<'info, System>,
 }
 
 #[derive(Accounts, Debug)]
 pub struct Withdraw<'info> {
     #[account(mut)]
     pub vault : Account<'info, Vault>,
+    // the operator signed message binds the recipient, so anyone (e.g. the backend payout service) can submit
     #[account(mut)]
-    pub user : Signer<'info>,
-    pub system_program : SystemAccount<'info>,
+    pub user : SystemAccount<'info>,
+    pub system_program : Program<'info, System>,
+    /// CHECK: address is constrained to the instructions sysvar
     #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
     pub instruction_sysvar : AccountInfo<'info>,
+    #[account(mut, address = vault.fee_account)]
     pub fee_account : Account<'info, FeeAccount>
 }
 
 #[derive(Accounts,Debug)]
 pub struct AdminWithdraw<'info>{
-     #[account(mut)]
+    #[account(mut, has_one = admin @ VaultError::AdminError)]
     pub vault : Account<'info, Vault>,
     #[account(mut)]
     pub admin : Signer<'info>,
+    /// CHECK: any wallet the admin chooses to receive the withdrawal
+    #[account(mut)]
     pub admin_address : AccountInfo<'info>,
-    pub system_program : SystemAccount<'info>,
+    pub system_program : Program<'info, System>,
+}
+
+#[derive(Accounts,Debug)]
+pub struct AnnounceAdminWithdrawal<'info>{
+    #[account(mut, has_one = admin @ VaultError::AdminError)]
+    pub vault : Account<'info, Vault>,
+    pub admin : Signer<'info>,
 }
 
 #[derive(Accounts,Debug)]
 pub struct SetPause<'info>{
-    #[account(mut)]
+    #[account(mut, has_one = admin @ VaultError::AdminError)]
     pub vault : Account<'info, Vault>,
     pub admin : Signer<'info>,
 }
 
+#[derive(Accounts,Debug)]
+pub struct GuardianPause<'info>{
+    #[account(mut)]
+    pub vault : Account<'info, Vault>,
+    pub authority : Signer<'info>,
+}
+
+#[derive(Accounts, Debug)]
+pub struct InitializeRewardAccount<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + RewardAccount::INIT_SPACE,
+        seeds = [b"reward_account".as_ref(), vault.key().as_ref(), validator.key().as_ref()],
+        bump
+    )]
+    pub reward_account : Account<'info, RewardAccount>,
+    pub vault : Account<'info, Vault>,
+    /// CHECK: only used as a seed, the ledger is bound to this wallet
+    pub validator : AccountInfo<'info>,
+    #[account(mut)]
+    pub payer : Signer<'info>,
+    pub system_program : Program<'info, System>,
+}
+
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct RewardAccount{
+    pub vault : Pubkey,
+    pub validator : Pubkey,
+    pub total_credited : u64,
+    pub total_claimed : u64,
+    pub last_credited_at : Option<i64>,
+    pub bump : u8
+}
+
+#[derive(Accounts, Debug)]
+pub struct CreditRewards<'info> {
+    #[account(mut, has_one = operator @ VaultError::OperatorError)]
+    pub vault : Account<'info, Vault>,
+    pub operator : Signer<'info>,
+    /// CHECK: address is constrained to the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instruction_sysvar : AccountInfo<'info>,
+}
+
+#[derive(Accounts, Debug)]
+pub struct Claim<'info> {
+    #[account(mut)]
+    pub vault : Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"reward_account".as_ref(), vault.key().as_ref(), validator.key().as_ref()],
+        bump = reward_account.bump,
+        has_one = vault @ RewardAccountError::VaultMismatch,
+        has_one = validator @ RewardAccountError::ValidatorMismatch
+    )]
+    pub reward_account : Account<'info, RewardAccount>,
+    #[account(mut)]
+    pub validator : Signer<'info>,
+    #[account(
+        seeds = [b"validator".as_ref(), vault.key().as_ref(), validator.key().as_ref()],
+        bump = validator_record.bump,
+        constraint = validator_record.status == ValidatorStatus::Active @ ValidatorError::NotActive
+    )]
+    pub validator_record : Account<'info, ValidatorRecord>,
+    #[account(mut, address = vault.fee_account)]
+    pub fee_account : Account<'info, FeeAccount>,
+}
+
 #[error_code]
 pub enum VaultError {
     #[msg("the vault admin doesn't match the admin key")]
@@ -274,13 +2379,137 @@ pub enum VaultError {
     #[msg("deposited amount in vault less than withdraw amount")]
     InsufficientBalance,
     #[msg("unable to withdraw deposited amount to admin address")]
-    AdminWithdrawal
+    AdminWithdrawal,
+    #[msg("the vault operator doesn't match the signer")]
+    OperatorError,
+    #[msg("ed25519 instruction is malformed or references data outside itself")]
+    InvalidSignatureInstruction,
+    #[msg("signed message was issued for a different vault")]
+    VaultMismatch,
+    #[msg("signed message was issued for a different recipient")]
+    RecipientMismatch,
+    #[msg("signed message amount doesn't match the requested amount")]
+    AmountMismatch,
+    #[msg("signed message nonce doesn't match the vault withdrawal counter")]
+    NonceMismatch,
+    #[msg("signed message has expired")]
+    SignatureExpired,
+    #[msg("withdrawal fee exceeds the maximum allowed basis points")]
+    FeeTooHigh,
+    #[msg("signer doesn't match the pending key or the key is invalid")]
+    InvalidPendingKey,
+    #[msg("the pending operator activation slot has not been reached")]
+    OperatorNotActive,
+    #[msg("signed message was issued for a different mint")]
+    MintMismatch,
+    #[msg("no admin withdrawal has been announced")]
+    NoPendingAdminWithdrawal,
+    #[msg("announced admin withdrawal is still timelocked")]
+    AdminWithdrawalLocked,
+    #[msg("admin withdrawal cooldown has not elapsed")]
+    AdminWithdrawalCooldown,
+    #[msg("withdrawal cooldown and delay must not be negative")]
+    InvalidWithdrawalLimits,
+    #[msg("operator set must be non-empty, unique and within the threshold bounds")]
+    InvalidOperatorSet,
+    #[msg("not enough distinct operator signatures over the withdrawal message")]
+    InsufficientOperatorSignatures,
+    #[msg("account is not a vault or fee account of this program")]
+    InvalidAccountLayout,
+    #[msg("account layout version is not supported")]
+    UnsupportedVersion,
+    #[msg("vault and fee account already use the current layout")]
+    AlreadyMigrated,
+    #[msg("vault still holds deposits, owed rewards or a pending admin withdrawal")]
+    VaultNotEmpty,
+    #[msg("pause flags must be a non-empty combination of PAUSE_DEPOSITS, PAUSE_WITHDRAWALS and PAUSE_CLAIMS")]
+    InvalidPauseFlags,
+    #[msg("only the admin or the guardian can pause the vault")]
+    GuardianError,
+    #[msg("no withdrawal limit change is pending")]
+    NoPendingWithdrawalLimits,
+    #[msg("loosened withdrawal limits are still timelocked")]
+    WithdrawalLimitsLocked,
+    #[msg("operator signed message authorizes a different action")]
+    ActionMismatch,
+    #[msg("fee account is not the one recorded in the vault")]
+    FeeAccountMismatch
 }
 
-#[error_code]
+#[error_code(offset = 6200)]
+pub enum StakeError {
+    #[msg("amount exceeds the stake available")]
+    InsufficientStake,
+    #[msg("unbonding period has not elapsed")]
+    StillUnbonding,
+    #[msg("no unbonded stake to withdraw")]
+    NothingToWithdraw,
+    #[msg("only the vault admin or operator can slash")]
+    UnauthorizedSlasher,
+    #[msg("stake account belongs to a different vault")]
+    VaultMismatch,
+    #[msg("stake account belongs to a different validator")]
+    ValidatorMismatch
+}
+
+#[error_code(offset = 6300)]
+pub enum EpochError {
+    #[msg("epoch leaf count must be between 1 and the maximum supported")]
+    TooManyLeaves,
+    #[msg("epoch has expired")]
+    EpochExpired,
+    #[msg("epoch has not expired yet")]
+    EpochNotExpired,
+    #[msg("leaf index is outside the epoch")]
+    InvalidLeafIndex,
+    #[msg("reward for this leaf was already claimed")]
+    AlreadyClaimed,
+    #[msg("merkle proof doesn't match the epoch root")]
+    InvalidProof,
+    #[msg("epoch belongs to a different vault")]
+    VaultMismatch,
+    #[msg("claims would exceed the epoch total")]
+    EpochExhausted
+}
+
+#[error_code(offset = 6400)]
+pub enum UptimeError {
+    #[msg("uptime basis points must be at most 10000")]
+    InvalidUptime,
+    #[msg("an uptime commitment needs at least one contributing validator")]
+    NoValidators
+}
+
+#[error_code(offset = 6500)]
+pub enum RewardAccountError {
+    #[msg("number of amounts doesn't match the number of reward accounts")]
+    CreditLengthMismatch,
+    #[msg("reward account must be passed as writable")]
+    AccountNotWritable,
+    #[msg("reward account belongs to a different vault")]
+    VaultMismatch,
+    #[msg("reward account belongs to a different validator")]
+    ValidatorMismatch,
+    #[msg("claim amount exceeds unclaimed rewards")]
+    InsufficientRewards
+}
+
+#[error_code(offset = 6600)]
+pub enum ValidatorError {
+    #[msg("validator is not active in the registry")]
+    NotActive,
+    #[msg("status must be Active or Inactive and differ from the current one")]
+    InvalidStatus,
+    #[msg("validator record belongs to a different vault")]
+    VaultMismatch
+}
+
+#[error_code(offset = 6100)]
 pub enum FeeAccountError {
     #[msg("the fee account admin does not match the specified pubkey")]
-    AdminError
+    AdminError,
+    #[msg("requested amount exceeds the collected fees above rent")]
+    InsufficientFees
 }
 
 #[event]
@@ -298,5 +2527,310 @@ pub struct WithdrawalSucces{
 
 #[event]
 pub struct PauseStatus{
-    pub paused : bool
-}
\ No newline at end of file
+    pub vault : Pubkey,
+    pub flags : u8, // the PAUSE_* flags this change touched
+    pub paused : bool,
+    pub pause_flags : u8, // all flags set after the change
+    pub changed_by : Pubkey
+}
+
+#[event]
+pub struct GuardianUpdated{
+    pub vault : Pubkey,
+    pub guardian : Option<Pubkey>
+}
+
+#[event]
+pub struct RewardsCredited{
+    pub vault : Pubkey,
+    pub accounts : u32,
+    pub amount : u64,
+    pub timestamp : i64
+}
+
+#[event]
+pub struct RewardClaimed{
+    pub validator : Pubkey,
+    pub amount : u64,
+    pub fee : u64,
+    pub remaining : u64,
+    pub timestamp : i64
+}
+
+#[event]
+pub struct WithdrawalFeeScheduled{
+    pub vault : Pubkey,
+    pub current_fee_bps : u16,
+    pub new_fee_bps : u16,
+    pub effective_slot : u64
+}
+
+#[event]
+pub struct FeesWithdrawn{
+    pub admin : Pubkey,
+    pub amount : u64,
+    pub timestamp : i64
+}
+
+#[event]
+pub struct OperatorProposed{
+    pub vault : Pubkey,
+    pub current_operator : Pubkey,
+    pub proposed_operator : Pubkey,
+    pub activation_slot : u64
+}
+
+#[event]
+pub struct OperatorAccepted{
+    pub vault : Pubkey,
+    pub previous_operator : Pubkey,
+    pub operator : Pubkey
+}
+
+#[event]
+pub struct AdminProposed{
+    pub vault : Pubkey,
+    pub current_admin : Pubkey,
+    pub proposed_admin : Pubkey
+}
+
+#[event]
+pub struct AdminAccepted{
+    pub vault : Pubkey,
+    pub previous_admin : Pubkey,
+    pub admin : Pubkey
+}
+
+#[event]
+pub struct TokenVaultInitialized{
+    pub vault : Pubkey,
+    pub mint : Pubkey
+}
+
+#[event]
+pub struct TokenVaultClosed{
+    pub vault : Pubkey,
+    pub mint : Pubkey
+}
+
+#[event]
+pub struct TokenWithdrawalSucces{
+    pub user : Pubkey,
+    pub mint : Pubkey,
+    pub amount : u64,
+    pub fee : u64,
+    pub timestamp : i64
+}
+
+#[event]
+pub struct TokenAdminWithdrawalAnnounced{
+    pub vault : Pubkey,
+    pub mint : Pubkey,
+    pub amount : u64,
+    pub unlock_at : i64
+}
+
+#[event]
+pub struct TokenAdminWithdrawalCancelled{
+    pub vault : Pubkey,
+    pub mint : Pubkey,
+    pub amount : u64
+}
+
+#[event]
+pub struct TokenAdminWithdrawalExecuted{
+    pub vault : Pubkey,
+    pub mint : Pubkey,
+    pub recipient : Pubkey,
+    pub amount : u64,
+    pub timestamp : i64
+}
+
+#[event]
+pub struct TokenOutflowLimitUpdated{
+    pub vault : Pubkey,
+    pub mint : Pubkey,
+    pub max_outflow_per_epoch : u64
+}
+
+#[event]
+pub struct TokenOutflowLimitProposed{
+    pub vault : Pubkey,
+    pub mint : Pubkey,
+    pub max_outflow_per_epoch : u64,
+    pub unlock_at : i64
+}
+
+#[event]
+pub struct TokenCircuitBreakerTriggered{
+    pub vault : Pubkey,
+    pub mint : Pubkey,
+    pub attempted : u64,
+    pub epoch_outflow : u64,
+    pub max_outflow_per_epoch : u64
+}
+
+#[event]
+pub struct ValidatorRegistered{
+    pub vault : Pubkey,
+    pub wallet : Pubkey,
+    pub device_hash : [u8; 32],
+    pub region_code : u16,
+    pub registration_slot : u64
+}
+
+#[event]
+pub struct ValidatorStatusChanged{
+    pub vault : Pubkey,
+    pub wallet : Pubkey,
+    pub previous_status : ValidatorStatus,
+    pub status : ValidatorStatus
+}
+
+#[event]
+pub struct Staked{
+    pub vault : Pubkey,
+    pub validator : Pubkey,
+    pub amount : u64,
+    pub total_staked : u64
+}
+
+#[event]
+pub struct UnstakeRequested{
+    pub vault : Pubkey,
+    pub validator : Pubkey,
+    pub amount : u64,
+    pub release_slot : u64
+}
+
+#[event]
+pub struct StakeWithdrawn{
+    pub vault : Pubkey,
+    pub validator : Pubkey,
+    pub amount : u64
+}
+
+#[event]
+pub struct ValidatorSlashed{
+    pub vault : Pubkey,
+    pub validator : Pubkey,
+    pub authority : Pubkey,
+    pub amount : u64,
+    pub remaining_stake : u64,
+    pub reason_code : u8,
+    pub timestamp : i64
+}
+
+#[event]
+pub struct EpochPosted{
+    pub vault : Pubkey,
+    pub epoch_id : u64,
+    pub merkle_root : [u8; 32],
+    pub total_amount : u64,
+    pub leaf_count : u32,
+    pub expiry_slot : u64
+}
+
+#[event]
+pub struct EpochClaimed{
+    pub vault : Pubkey,
+    pub epoch_id : u64,
+    pub validator : Pubkey,
+    pub index : u32,
+    pub amount : u64,
+    pub fee : u64
+}
+
+#[event]
+pub struct EpochClosed{
+    pub vault : Pubkey,
+    pub epoch_id : u64,
+    pub unclaimed : u64
+}
+
+#[event]
+pub struct AdminWithdrawalAnnounced{
+    pub vault : Pubkey,
+    pub amount : u64,
+    pub unlock_at : i64
+}
+
+#[event]
+pub struct AdminWithdrawalCancelled{
+    pub vault : Pubkey,
+    pub amount : u64
+}
+
+#[event]
+pub struct AdminWithdrawalExecuted{
+    pub vault : Pubkey,
+    pub recipient : Pubkey,
+    pub amount : u64,
+    pub timestamp : i64
+}
+
+#[event]
+pub struct WithdrawalLimitsUpdated{
+    pub vault : Pubkey,
+    pub admin_withdrawal_cooldown : i64,
+    pub admin_withdrawal_delay : i64,
+    pub max_outflow_per_epoch : u64
+}
+
+#[event]
+pub struct WithdrawalLimitsProposed{
+    pub vault : Pubkey,
+    pub admin_withdrawal_cooldown : i64,
+    pub admin_withdrawal_delay : i64,
+    pub max_outflow_per_epoch : u64,
+    pub unlock_at : i64
+}
+
+#[event]
+pub struct CircuitBreakerTriggered{
+    pub vault : Pubkey,
+    pub attempted : u64,
+    pub epoch_outflow : u64,
+    pub max_outflow_per_epoch : u64
+}
+
+#[event]
+pub struct UptimeCommitted{
+    pub vault : Pubkey,
+    pub website_hash : [u8; 32],
+    pub epoch : u64,
+    pub results_hash : [u8; 32],
+    pub uptime_bps : u16,
+    pub validator_count : u32,
+    pub timestamp : i64
+}
+
+#[event]
+pub struct OperatorSetProposed{
+    pub vault : Pubkey,
+    pub operators : Vec<Pubkey>,
+    pub threshold : u8,
+    pub activation_slot : u64
+}
+
+#[event]
+pub struct OperatorSetUpdated{
+    pub vault : Pubkey,
+    pub operators : Vec<Pubkey>,
+    pub threshold : u8
+}
+
+#[event]
+pub struct VaultMigrated{
+    pub vault : Pubkey,
+    pub vault_from_version : u8,
+    pub fee_account_from_version : u8,
+    pub version : u8
+}
+
+#[event]
+pub struct VaultClosed{
+    pub vault : Pubkey,
+    pub admin : Pubkey,
+    pub rent : u64
+}
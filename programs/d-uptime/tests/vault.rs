@@ -0,0 +1,1534 @@
+// In-process tests for the vault instructions of d_uptime, run on litesvm so no validator is needed.
+// They load the compiled program, so build it first: `anchor build` (or `cargo build-sbf`), then
+// `cargo test -p d-uptime`. Until the program exists build.rs leaves d_uptime_so unset and this file
+// compiles to nothing.
+#![cfg(d_uptime_so)]
+
+// litesvm's TransactionResult carries the whole failed transaction's metadata in its error
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::{
+    prelude::Clock, solana_program::instruction::Instruction, system_program, AccountDeserialize,
+    AnchorSerialize, Discriminator, InstructionData, Space, ToAccountMetas,
+};
+use d_uptime::{
+    accounts, epoch_leaf_hash, instruction, EpochError, FeeAccount, OperatorAction, OperatorActionMessage, ValidatorError, ValidatorRecord, ValidatorStatus, FeeAccountV0, TokenWithdrawalMessage, Vault, VaultError,
+    VaultV0, WithdrawalMessage, DEFAULT_ADMIN_WITHDRAWAL_COOLDOWN, DEFAULT_ADMIN_WITHDRAWAL_DELAY,
+    FEE_ACCOUNT_VERSION, OPERATOR_SET_TIMELOCK_SLOTS, PAUSE_ALL, PAUSE_CLAIMS, PAUSE_DEPOSITS, PAUSE_WITHDRAWALS, VAULT_VERSION,
+};
+use litesvm::{types::TransactionResult, LiteSVM};
+use litesvm_token::{CreateAssociatedTokenAccount, CreateMint, MintTo};
+use solana_sdk::{
+    ed25519_instruction::{
+        new_ed25519_instruction_with_signature, offsets_to_ed25519_instruction,
+        Ed25519SignatureOffsets,
+    },
+    hash::hashv,
+    instruction::InstructionError,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::{Transaction, TransactionError},
+};
+
+const PROGRAM_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../target/deploy/d_uptime.so");
+const DEPOSIT: u64 = 10 * LAMPORTS_PER_SOL;
+
+struct TestVault {
+    svm: LiteSVM,
+    admin: Keypair,
+    operator: Keypair,
+    user: Keypair,
+    vault: Pubkey,
+    fee_account: Pubkey,
+}
+
+fn send(svm: &mut LiteSVM, instructions: &[Instruction], payer: &Keypair, signers: &[&Keypair]) -> TransactionResult {
+    let mut all_signers = vec![payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), &all_signers, svm.latest_blockhash());
+    let result = svm.send_transaction(tx);
+    // identical transactions (e.g. a replayed withdrawal) must not be rejected as already processed
+    svm.expire_blockhash();
+    result
+}
+
+fn assert_error(result: TransactionResult, error: impl Into<u32>) {
+    let code = error.into();
+    match result {
+        Ok(_) => panic!("transaction succeeded, expected custom error {}", code),
+        Err(failed) => match failed.err {
+            TransactionError::InstructionError(_, InstructionError::Custom(actual)) => {
+                assert_eq!(actual, code, "logs: {:#?}", failed.meta.logs)
+            }
+            other => panic!("expected custom error {}, got {:?}, logs: {:#?}", code, other, failed.meta.logs),
+        },
+    }
+}
+
+impl TestVault {
+    // fee account + vault initialized, vault funded with DEPOSIT lamports by the user
+    fn new() -> Self {
+        let mut svm = LiteSVM::new();
+        svm.add_program_from_file(d_uptime::ID, PROGRAM_PATH).expect("build the program with `anchor build` first");
+
+        let admin = Keypair::new();
+        let operator = Keypair::new();
+        let user = Keypair::new();
+        for keypair in [&admin, &operator, &user] {
+            svm.airdrop(&keypair.pubkey(), 100 * LAMPORTS_PER_SOL).unwrap();
+        }
+
+        let (fee_account, _) = Pubkey::find_program_address(&[b"fee_account", admin.pubkey().as_ref()], &d_uptime::ID);
+        let (vault, _) = Pubkey::find_program_address(&[b"vault", admin.pubkey().as_ref()], &d_uptime::ID);
+
+        let mut test = Self { svm, admin, operator, user, vault, fee_account };
+        let init_fee_account = test.ix(
+            accounts::InitializeFeeAccount {
+                fee_account,
+                admin: test.admin.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::InitializeFeeAccount {},
+        );
+        let init_vault = test.ix(
+            accounts::InitializeVault {
+                vault,
+                admin: test.admin.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::InitializeVault {
+                operator: test.operator.pubkey(),
+                fee_account,
+            },
+        );
+        let admin = test.admin.insecure_clone();
+        send(&mut test.svm, &[init_fee_account, init_vault], &admin, &[]).unwrap();
+        test.deposit(DEPOSIT).unwrap();
+        test
+    }
+
+    fn ix(&self, accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+        Instruction {
+            program_id: d_uptime::ID,
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    fn vault_state(&self) -> Vault {
+        let account = self.svm.get_account(&self.vault).unwrap();
+        Vault::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    fn fee_account_state(&self) -> FeeAccount {
+        let account = self.svm.get_account(&self.fee_account).unwrap();
+        FeeAccount::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    fn lamports(&self, pubkey: &Pubkey) -> u64 {
+        self.svm.get_account(pubkey).map(|account| account.lamports).unwrap_or(0)
+    }
+
+    fn slot(&self) -> u64 {
+        self.svm.get_sysvar::<Clock>().slot
+    }
+
+    fn advance_time(&mut self, seconds: i64) {
+        let mut clock = self.svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += seconds;
+        self.svm.set_sysvar(&clock);
+    }
+
+    fn deposit(&mut self, amount: u64) -> TransactionResult {
+        let ix = self.ix(
+            accounts::Deposit {
+                vault: self.vault,
+                user: self.user.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::Deposit { amount },
+        );
+        let user = self.user.insecure_clone();
+        send(&mut self.svm, &[ix], &user, &[])
+    }
+
+    fn message(&self, amount: u64) -> WithdrawalMessage {
+        WithdrawalMessage {
+            vault: self.vault,
+            recipient: self.user.pubkey(),
+            amount,
+            nonce: self.vault_state().withdrawal_counter,
+            expiry_slot: self.slot() + 100,
+        }
+    }
+
+    fn signature_ix(signer: &Keypair, message: &[u8]) -> Instruction {
+        let signature: [u8; 64] = signer.sign_message(message).into();
+        new_ed25519_instruction_with_signature(message, &signature, &signer.pubkey().to_bytes())
+    }
+
+    fn withdraw_ix(&self, amount: u64, nonce: u64, expiry_slot: u64) -> Instruction {
+        self.ix(
+            accounts::Withdraw {
+                vault: self.vault,
+                user: self.user.pubkey(),
+                system_program: system_program::ID,
+                instruction_sysvar: sysvar::instructions::ID,
+                fee_account: self.fee_account,
+            },
+            instruction::Withdrawal { amount, nonce, expiry_slot },
+        )
+    }
+
+    // the recipient doesn't sign, admin pays so the user's balance only moves by the withdrawal itself
+    fn withdraw(&mut self, signature_ixs: Vec<Instruction>, amount: u64, nonce: u64, expiry_slot: u64) -> TransactionResult {
+        let mut instructions = signature_ixs;
+        instructions.push(self.withdraw_ix(amount, nonce, expiry_slot));
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &instructions, &admin, &[])
+    }
+
+    fn withdraw_signed(&mut self, signer: &Keypair, message: &WithdrawalMessage) -> TransactionResult {
+        let signature_ix = Self::signature_ix(signer, &message.to_bytes());
+        self.withdraw(vec![signature_ix], message.amount, message.nonce, message.expiry_slot)
+    }
+
+    fn admin_call(&mut self, data: impl InstructionData) -> TransactionResult {
+        let ix = self.ix(
+            accounts::AnnounceAdminWithdrawal {
+                vault: self.vault,
+                admin: self.admin.pubkey(),
+            },
+            data,
+        );
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &[ix], &admin, &[])
+    }
+
+    // loosening only applies once the current admin withdrawal delay has passed
+    fn loosen_withdrawal_limits(&mut self, admin_withdrawal_cooldown: i64, admin_withdrawal_delay: i64, max_outflow_per_epoch: u64) {
+        self.admin_call(instruction::ConfigureWithdrawalLimits {
+            admin_withdrawal_cooldown,
+            admin_withdrawal_delay,
+            max_outflow_per_epoch,
+        })
+        .unwrap();
+        let delay = self.vault_state().admin_withdrawal_delay;
+        self.advance_time(delay);
+        self.admin_call(instruction::ApplyWithdrawalLimits {}).unwrap();
+    }
+
+    fn admin_withdrawal(&mut self) -> TransactionResult {
+        let ix = self.ix(
+            accounts::AdminWithdraw {
+                vault: self.vault,
+                admin: self.admin.pubkey(),
+                admin_address: self.admin.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::AdminWithdrawal {},
+        );
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &[ix], &admin, &[])
+    }
+
+    // rewrites an account with the given unversioned layout, keeping its lamports
+    fn set_legacy_data<T: AnchorSerialize + Space>(&mut self, pubkey: Pubkey, discriminator: &[u8], state: &T) {
+        let mut data = discriminator.to_vec();
+        state.serialize(&mut data).unwrap();
+        data.resize(8 + T::INIT_SPACE, 0);
+        let mut account = self.svm.get_account(&pubkey).unwrap();
+        account.data = data;
+        self.svm.set_account(pubkey, account).unwrap();
+    }
+
+    fn migrate(&mut self) -> TransactionResult {
+        self.migrate_accounts(self.fee_account)
+    }
+
+    fn migrate_accounts(&mut self, fee_account: Pubkey) -> TransactionResult {
+        let ix = self.ix(
+            accounts::MigrateVault {
+                vault: self.vault,
+                fee_account,
+                admin: self.admin.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::MigrateVault {},
+        );
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &[ix], &admin, &[])
+    }
+
+    fn close(&mut self) -> TransactionResult {
+        let ix = self.ix(
+            accounts::CloseVault {
+                vault: self.vault,
+                admin: self.admin.pubkey(),
+            },
+            instruction::CloseVault {},
+        );
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &[ix], &admin, &[])
+    }
+
+    fn set_pause(&mut self, flags: u8, paused: bool) -> TransactionResult {
+        let ix = self.ix(
+            accounts::SetPause {
+                vault: self.vault,
+                admin: self.admin.pubkey(),
+            },
+            instruction::SetVaultPause { flags, paused },
+        );
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &[ix], &admin, &[])
+    }
+
+    fn propose_operator_set(&mut self, operators: &[&Keypair], threshold: u8) -> TransactionResult {
+        let ix = self.ix(
+            accounts::ProposeKey {
+                vault: self.vault,
+                admin: self.admin.pubkey(),
+            },
+            instruction::ProposeOperatorSet {
+                operators: operators.iter().map(|operator| operator.pubkey()).collect(),
+                threshold,
+            },
+        );
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &[ix], &admin, &[])
+    }
+
+    // the given pending operators sign the acceptance as remaining accounts
+    fn accept_operator_set(&mut self, signers: &[&Keypair]) -> TransactionResult {
+        let mut ix = self.ix(accounts::AcceptOperatorSet { vault: self.vault }, instruction::AcceptOperatorSet {});
+        ix.accounts.extend(
+            signers
+                .iter()
+                .map(|signer| anchor_lang::solana_program::instruction::AccountMeta::new_readonly(signer.pubkey(), true)),
+        );
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &[ix], &admin, signers)
+    }
+
+    // proposes the set, waits out the timelock and accepts it with every new operator signing
+    fn set_operator_set(&mut self, operators: &[&Keypair], threshold: u8) {
+        self.propose_operator_set(operators, threshold).unwrap();
+        let slot = self.slot();
+        self.svm.warp_to_slot(slot + OPERATOR_SET_TIMELOCK_SLOTS);
+        self.accept_operator_set(operators).unwrap();
+    }
+
+    fn guardian_pause(&mut self, authority: &Keypair, flags: u8) -> TransactionResult {
+        let ix = self.ix(
+            accounts::GuardianPause {
+                vault: self.vault,
+                authority: authority.pubkey(),
+            },
+            instruction::GuardianPause { flags },
+        );
+        send(&mut self.svm, &[ix], authority, &[])
+    }
+}
+
+#[test]
+fn initializes_fee_account_and_vault() {
+    let test = TestVault::new();
+    let vault = test.vault_state();
+    assert_eq!(vault.admin, test.admin.pubkey());
+    assert_eq!(vault.operator, test.operator.pubkey());
+    assert_eq!(vault.fee_account, test.fee_account);
+    assert_eq!(vault.withdrawal_fee_bps, 200);
+    assert_eq!(vault.admin_withdrawal_cooldown, DEFAULT_ADMIN_WITHDRAWAL_COOLDOWN);
+    assert_eq!(vault.admin_withdrawal_delay, DEFAULT_ADMIN_WITHDRAWAL_DELAY);
+    assert_eq!(vault.pause_flags, 0);
+    assert_eq!(vault.version, VAULT_VERSION);
+
+    let fee_account = test.fee_account_state();
+    assert_eq!(fee_account.version, FEE_ACCOUNT_VERSION);
+    assert_eq!(fee_account.admin, test.admin.pubkey());
+    assert_eq!(fee_account.total_fee_amount, 0);
+}
+
+#[test]
+fn deposit_updates_totals() {
+    let mut test = TestVault::new();
+    let before = test.lamports(&test.vault);
+    test.deposit(LAMPORTS_PER_SOL).unwrap();
+
+    assert_eq!(test.lamports(&test.vault), before + LAMPORTS_PER_SOL);
+    assert_eq!(test.vault_state().total_deposited, DEPOSIT + LAMPORTS_PER_SOL);
+}
+
+#[test]
+fn deposit_rejects_zero_amount() {
+    let mut test = TestVault::new();
+    assert_error(test.deposit(0), VaultError::InvalidAmount);
+}
+
+#[test]
+fn paused_vault_rejects_deposits_until_unpaused() {
+    let mut test = TestVault::new();
+    test.set_pause(PAUSE_DEPOSITS, true).unwrap();
+    assert!(test.vault_state().is_paused(PAUSE_DEPOSITS));
+    assert_error(test.deposit(LAMPORTS_PER_SOL), VaultError::VaultPaused);
+
+    test.set_pause(PAUSE_DEPOSITS, false).unwrap();
+    test.deposit(LAMPORTS_PER_SOL).unwrap();
+}
+
+#[test]
+fn pause_flags_are_independent() {
+    let mut test = TestVault::new();
+    test.set_pause(PAUSE_WITHDRAWALS, true).unwrap();
+    test.deposit(LAMPORTS_PER_SOL).unwrap();
+
+    let message = test.message(LAMPORTS_PER_SOL);
+    let operator = test.operator.insecure_clone();
+    assert_error(test.withdraw_signed(&operator, &message), VaultError::VaultPaused);
+    assert_error(test.set_pause(PAUSE_ALL + 1, true), VaultError::InvalidPauseFlags);
+}
+
+#[test]
+fn guardian_can_pause_but_not_unpause() {
+    let mut test = TestVault::new();
+    let guardian = Keypair::new();
+    test.svm.airdrop(&guardian.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let set_guardian = test.ix(
+        accounts::ProposeKey {
+            vault: test.vault,
+            admin: test.admin.pubkey(),
+        },
+        instruction::SetGuardian { guardian: Some(guardian.pubkey()) },
+    );
+    let admin = test.admin.insecure_clone();
+    send(&mut test.svm, &[set_guardian], &admin, &[]).unwrap();
+
+    let user = test.user.insecure_clone();
+    assert_error(test.guardian_pause(&user, PAUSE_ALL), VaultError::GuardianError);
+    test.guardian_pause(&guardian, PAUSE_ALL).unwrap();
+    assert_eq!(test.vault_state().pause_flags, PAUSE_ALL);
+
+    let unpause = test.ix(
+        accounts::SetPause {
+            vault: test.vault,
+            admin: guardian.pubkey(),
+        },
+        instruction::SetVaultPause { flags: PAUSE_ALL, paused: false },
+    );
+    assert_error(send(&mut test.svm, &[unpause], &guardian, &[]), VaultError::AdminError);
+
+    test.set_pause(PAUSE_ALL, false).unwrap();
+    assert_eq!(test.vault_state().pause_flags, 0);
+}
+
+#[test]
+fn withdrawal_pays_recipient_and_collects_fee() {
+    let mut test = TestVault::new();
+    let amount = LAMPORTS_PER_SOL;
+    let user_before = test.lamports(&test.user.pubkey());
+    let fee_before = test.lamports(&test.fee_account);
+
+    let message = test.message(amount);
+    let operator = test.operator.insecure_clone();
+    test.withdraw_signed(&operator, &message).unwrap();
+
+    let fee = amount * 200 / 10_000;
+    assert_eq!(test.lamports(&test.user.pubkey()), user_before + amount - fee);
+    assert_eq!(test.lamports(&test.fee_account), fee_before + fee);
+    assert_eq!(test.fee_account_state().total_fee_amount, fee);
+
+    let vault = test.vault_state();
+    assert_eq!(vault.total_deposited, DEPOSIT - amount);
+    assert_eq!(vault.total_withdrawn, amount);
+    assert_eq!(vault.withdrawal_counter, 1);
+}
+
+#[test]
+fn withdrawal_rejects_replayed_message() {
+    let mut test = TestVault::new();
+    let message = test.message(LAMPORTS_PER_SOL);
+    let operator = test.operator.insecure_clone();
+    test.withdraw_signed(&operator, &message).unwrap();
+    assert_error(test.withdraw_signed(&operator, &message), VaultError::NonceMismatch);
+}
+
+#[test]
+fn withdrawal_rejects_paused_vault() {
+    let mut test = TestVault::new();
+    test.set_pause(PAUSE_WITHDRAWALS, true).unwrap();
+    let message = test.message(LAMPORTS_PER_SOL);
+    let operator = test.operator.insecure_clone();
+    assert_error(test.withdraw_signed(&operator, &message), VaultError::VaultPaused);
+}
+
+#[test]
+fn withdrawal_rejects_zero_amount() {
+    let mut test = TestVault::new();
+    let message = test.message(0);
+    let operator = test.operator.insecure_clone();
+    assert_error(test.withdraw_signed(&operator, &message), VaultError::InvalidAmount);
+}
+
+#[test]
+fn withdrawal_rejects_amount_above_deposits() {
+    let mut test = TestVault::new();
+    let message = test.message(DEPOSIT + 1);
+    let operator = test.operator.insecure_clone();
+    assert_error(test.withdraw_signed(&operator, &message), VaultError::InsufficientBalance);
+}
+
+#[test]
+fn withdrawal_requires_signature_instruction() {
+    let mut test = TestVault::new();
+    let message = test.message(LAMPORTS_PER_SOL);
+    assert_error(
+        test.withdraw(vec![], message.amount, message.nonce, message.expiry_slot),
+        VaultError::ProgramMissing,
+    );
+}
+
+#[test]
+fn withdrawal_rejects_non_operator_signer() {
+    let mut test = TestVault::new();
+    let message = test.message(LAMPORTS_PER_SOL);
+    let user = test.user.insecure_clone();
+    assert_error(test.withdraw_signed(&user, &message), VaultError::OperatorError);
+}
+
+#[test]
+fn withdrawal_rejects_malformed_message() {
+    let mut test = TestVault::new();
+    let message = test.message(LAMPORTS_PER_SOL);
+    let mut bytes = message.to_bytes();
+    bytes.push(0);
+    let signature_ix = TestVault::signature_ix(&test.operator, &bytes);
+    assert_error(
+        test.withdraw(vec![signature_ix], message.amount, message.nonce, message.expiry_slot),
+        VaultError::SignedMessageMissing,
+    );
+}
+
+#[test]
+fn withdrawal_rejects_offsets_into_other_instructions() {
+    let mut test = TestVault::new();
+    let message = test.message(LAMPORTS_PER_SOL);
+    let bytes = message.to_bytes();
+    let signature: [u8; 64] = test.operator.sign_message(&bytes).into();
+
+    // same layout the helper produces, but the offsets reference instruction 0 explicitly; the
+    // precompile accepts it, the vault must not
+    let public_key_offset = 2 + 14;
+    let signature_offset = public_key_offset + 32;
+    let message_data_offset = signature_offset + 64;
+    let mut signature_ix = offsets_to_ed25519_instruction(&[Ed25519SignatureOffsets {
+        signature_offset: signature_offset as u16,
+        signature_instruction_index: 0,
+        public_key_offset: public_key_offset as u16,
+        public_key_instruction_index: 0,
+        message_data_offset: message_data_offset as u16,
+        message_data_size: bytes.len() as u16,
+        message_instruction_index: 0,
+    }]);
+    signature_ix.data.extend_from_slice(&test.operator.pubkey().to_bytes());
+    signature_ix.data.extend_from_slice(&signature);
+    signature_ix.data.extend_from_slice(&bytes);
+
+    assert_error(
+        test.withdraw(vec![signature_ix], message.amount, message.nonce, message.expiry_slot),
+        VaultError::InvalidSignatureInstruction,
+    );
+}
+
+#[test]
+fn withdrawal_rejects_message_for_other_vault() {
+    let mut test = TestVault::new();
+    let mut message = test.message(LAMPORTS_PER_SOL);
+    message.vault = Pubkey::new_unique();
+    let operator = test.operator.insecure_clone();
+    assert_error(test.withdraw_signed(&operator, &message), VaultError::VaultMismatch);
+}
+
+#[test]
+fn withdrawal_rejects_message_for_other_recipient() {
+    let mut test = TestVault::new();
+    let mut message = test.message(LAMPORTS_PER_SOL);
+    message.recipient = Pubkey::new_unique();
+    let operator = test.operator.insecure_clone();
+    assert_error(test.withdraw_signed(&operator, &message), VaultError::RecipientMismatch);
+}
+
+#[test]
+fn withdrawal_rejects_mismatched_amount() {
+    let mut test = TestVault::new();
+    let message = test.message(LAMPORTS_PER_SOL);
+    let signature_ix = TestVault::signature_ix(&test.operator, &message.to_bytes());
+    assert_error(
+        test.withdraw(vec![signature_ix], message.amount * 2, message.nonce, message.expiry_slot),
+        VaultError::AmountMismatch,
+    );
+}
+
+#[test]
+fn withdrawal_rejects_expired_message() {
+    let mut test = TestVault::new();
+    let message = test.message(LAMPORTS_PER_SOL);
+    test.svm.warp_to_slot(message.expiry_slot + 1);
+    let operator = test.operator.insecure_clone();
+    assert_error(test.withdraw_signed(&operator, &message), VaultError::SignatureExpired);
+}
+
+#[test]
+fn withdrawal_requires_operator_threshold() {
+    let mut test = TestVault::new();
+    let operator = test.operator.insecure_clone();
+    let second = Keypair::new();
+    let third = Keypair::new();
+    test.set_operator_set(&[&operator, &second, &third], 2);
+
+    let message = test.message(LAMPORTS_PER_SOL);
+    assert_error(test.withdraw_signed(&operator, &message), VaultError::InsufficientOperatorSignatures);
+
+    let signature_ixs = vec![
+        TestVault::signature_ix(&operator, &message.to_bytes()),
+        TestVault::signature_ix(&third, &message.to_bytes()),
+    ];
+    test.withdraw(signature_ixs, message.amount, message.nonce, message.expiry_slot).unwrap();
+    assert_eq!(test.vault_state().withdrawal_counter, 1);
+}
+
+#[test]
+fn operator_set_rejects_invalid_threshold() {
+    let mut test = TestVault::new();
+    let operator = test.operator.insecure_clone();
+    assert_error(test.propose_operator_set(&[&operator], 2), VaultError::InvalidOperatorSet);
+}
+
+#[test]
+fn operator_set_requires_timelock_and_new_operator_signatures() {
+    let mut test = TestVault::new();
+    let (first, second) = (Keypair::new(), Keypair::new());
+    assert_error(test.accept_operator_set(&[&first]), VaultError::InvalidPendingKey);
+
+    test.propose_operator_set(&[&first, &second], 2).unwrap();
+    assert_error(test.accept_operator_set(&[&first, &second]), VaultError::OperatorNotActive);
+
+    let slot = test.slot();
+    test.svm.warp_to_slot(slot + OPERATOR_SET_TIMELOCK_SLOTS);
+    assert_error(test.accept_operator_set(&[&first]), VaultError::InsufficientOperatorSignatures);
+    // until the new set is accepted the current operator still authorizes withdrawals
+    assert!(test.vault_state().operators.is_empty());
+
+    test.accept_operator_set(&[&first, &second]).unwrap();
+    let vault = test.vault_state();
+    assert_eq!(vault.operators, vec![first.pubkey(), second.pubkey()]);
+    assert_eq!(vault.operator_threshold, 2);
+    assert!(vault.pending_operators.is_empty());
+}
+
+#[test]
+fn admin_withdrawal_requires_announcement_and_timelock() {
+    let mut test = TestVault::new();
+    assert_error(test.admin_withdrawal(), VaultError::NoPendingAdminWithdrawal);
+
+    test.admin_call(instruction::AnnounceAdminWithdrawal { amount: LAMPORTS_PER_SOL }).unwrap();
+    assert_error(test.admin_withdrawal(), VaultError::AdminWithdrawalLocked);
+
+    test.advance_time(DEFAULT_ADMIN_WITHDRAWAL_DELAY);
+    let admin_before = test.lamports(&test.admin.pubkey());
+    test.admin_withdrawal().unwrap();
+
+    // the admin also paid the 5000 lamport transaction fee
+    assert_eq!(test.lamports(&test.admin.pubkey()), admin_before + LAMPORTS_PER_SOL - 5000);
+    let vault = test.vault_state();
+    assert_eq!(vault.total_deposited, DEPOSIT - LAMPORTS_PER_SOL);
+    assert!(vault.last_admin_withdrawal.is_some());
+    assert!(vault.pending_admin_withdrawal.is_none());
+}
+
+#[test]
+fn admin_withdrawal_enforces_cooldown() {
+    let mut test = TestVault::new();
+    test.loosen_withdrawal_limits(3600, 0, 0);
+
+    test.admin_call(instruction::AnnounceAdminWithdrawal { amount: LAMPORTS_PER_SOL }).unwrap();
+    test.admin_withdrawal().unwrap();
+    test.admin_call(instruction::AnnounceAdminWithdrawal { amount: LAMPORTS_PER_SOL }).unwrap();
+    assert_error(test.admin_withdrawal(), VaultError::AdminWithdrawalCooldown);
+
+    test.advance_time(3600);
+    test.admin_withdrawal().unwrap();
+}
+
+#[test]
+fn admin_withdrawal_rejects_empty_vault() {
+    let mut test = TestVault::new();
+    test.loosen_withdrawal_limits(0, 0, 0);
+    test.admin_call(instruction::AnnounceAdminWithdrawal { amount: DEPOSIT }).unwrap();
+    test.admin_withdrawal().unwrap();
+
+    test.admin_call(instruction::AnnounceAdminWithdrawal { amount: 1 }).unwrap();
+    assert_error(test.admin_withdrawal(), VaultError::AdminWithdrawal);
+}
+
+#[test]
+fn withdrawal_limits_reject_negative_values() {
+    let mut test = TestVault::new();
+    assert_error(
+        test.admin_call(instruction::ConfigureWithdrawalLimits {
+            admin_withdrawal_cooldown: -1,
+            admin_withdrawal_delay: 0,
+            max_outflow_per_epoch: 0,
+        }),
+        VaultError::InvalidWithdrawalLimits,
+    );
+}
+
+#[test]
+fn loosened_withdrawal_limits_wait_for_the_delay() {
+    let mut test = TestVault::new();
+    test.admin_call(instruction::ConfigureWithdrawalLimits {
+        admin_withdrawal_cooldown: 0,
+        admin_withdrawal_delay: 0,
+        max_outflow_per_epoch: 0,
+    })
+    .unwrap();
+    let vault = test.vault_state();
+    assert_eq!(vault.admin_withdrawal_delay, DEFAULT_ADMIN_WITHDRAWAL_DELAY);
+    assert!(vault.pending_withdrawal_limits.is_some());
+    assert_error(test.admin_call(instruction::ApplyWithdrawalLimits {}), VaultError::WithdrawalLimitsLocked);
+
+    // tightening applies at once and drops the pending loosening
+    test.admin_call(instruction::ConfigureWithdrawalLimits {
+        admin_withdrawal_cooldown: DEFAULT_ADMIN_WITHDRAWAL_COOLDOWN,
+        admin_withdrawal_delay: DEFAULT_ADMIN_WITHDRAWAL_DELAY,
+        max_outflow_per_epoch: LAMPORTS_PER_SOL,
+    })
+    .unwrap();
+    let vault = test.vault_state();
+    assert_eq!(vault.max_outflow_per_epoch, LAMPORTS_PER_SOL);
+    assert!(vault.pending_withdrawal_limits.is_none());
+    assert_error(test.admin_call(instruction::ApplyWithdrawalLimits {}), VaultError::NoPendingWithdrawalLimits);
+
+    test.loosen_withdrawal_limits(0, 0, 0);
+    let vault = test.vault_state();
+    assert_eq!((vault.admin_withdrawal_cooldown, vault.admin_withdrawal_delay, vault.max_outflow_per_epoch), (0, 0, 0));
+}
+
+#[test]
+fn outflow_cap_pauses_vault() {
+    let mut test = TestVault::new();
+    // a first cap is stricter than none, so it applies immediately
+    test.admin_call(instruction::ConfigureWithdrawalLimits {
+        admin_withdrawal_cooldown: DEFAULT_ADMIN_WITHDRAWAL_COOLDOWN,
+        admin_withdrawal_delay: DEFAULT_ADMIN_WITHDRAWAL_DELAY,
+        max_outflow_per_epoch: LAMPORTS_PER_SOL,
+    })
+    .unwrap();
+
+    let message = test.message(2 * LAMPORTS_PER_SOL);
+    let operator = test.operator.insecure_clone();
+    test.withdraw_signed(&operator, &message).unwrap();
+
+    let vault = test.vault_state();
+    assert_eq!(vault.pause_flags, PAUSE_WITHDRAWALS | PAUSE_CLAIMS);
+    assert_eq!(vault.total_deposited, DEPOSIT);
+    assert_eq!(vault.withdrawal_counter, 0);
+}
+
+#[test]
+fn admin_only_instructions_reject_other_signers() {
+    let mut test = TestVault::new();
+    let ix = test.ix(
+        accounts::SetWithdrawalFee {
+            vault: test.vault,
+            admin: test.user.pubkey(),
+        },
+        instruction::SetWithdrawalFee { fee_bps: 100 },
+    );
+    let user = test.user.insecure_clone();
+    assert_error(send(&mut test.svm, &[ix], &user, &[]), VaultError::AdminError);
+}
+
+#[test]
+fn withdrawal_fee_is_bounded() {
+    let mut test = TestVault::new();
+    let ix = test.ix(
+        accounts::SetWithdrawalFee {
+            vault: test.vault,
+            admin: test.admin.pubkey(),
+        },
+        instruction::SetWithdrawalFee { fee_bps: d_uptime::MAX_WITHDRAWAL_FEE_BPS + 1 },
+    );
+    let admin = test.admin.insecure_clone();
+    assert_error(send(&mut test.svm, &[ix], &admin, &[]), VaultError::FeeTooHigh);
+}
+
+#[test]
+fn key_rotation_requires_pending_key_and_activation() {
+    let mut test = TestVault::new();
+    let new_operator = Keypair::new();
+    test.svm.airdrop(&new_operator.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+    let propose = test.ix(
+        accounts::ProposeKey {
+            vault: test.vault,
+            admin: test.admin.pubkey(),
+        },
+        instruction::ProposeOperator {
+            new_operator: new_operator.pubkey(),
+            activation_delay_slots: 10,
+        },
+    );
+    let admin = test.admin.insecure_clone();
+    send(&mut test.svm, &[propose], &admin, &[]).unwrap();
+
+    let accept = |test: &TestVault, signer: &Keypair| {
+        test.ix(
+            accounts::AcceptOperator {
+                vault: test.vault,
+                new_operator: signer.pubkey(),
+            },
+            instruction::AcceptOperator {},
+        )
+    };
+
+    let user = test.user.insecure_clone();
+    let ix = accept(&test, &user);
+    assert_error(send(&mut test.svm, &[ix], &user, &[]), VaultError::InvalidPendingKey);
+
+    let ix = accept(&test, &new_operator);
+    assert_error(send(&mut test.svm, std::slice::from_ref(&ix), &new_operator, &[]), VaultError::OperatorNotActive);
+
+    let slot = test.slot();
+    test.svm.warp_to_slot(slot + 10);
+    send(&mut test.svm, &[ix], &new_operator, &[]).unwrap();
+    assert_eq!(test.vault_state().operator, new_operator.pubkey());
+}
+
+#[test]
+fn operator_rotation_replaces_seat_in_operator_set() {
+    let mut test = TestVault::new();
+    let second = Keypair::new();
+    let new_operator = Keypair::new();
+    test.svm.airdrop(&new_operator.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let operator = test.operator.insecure_clone();
+    test.set_operator_set(&[&operator, &second], 1);
+    let admin = test.admin.insecure_clone();
+    let propose = test.ix(
+        accounts::ProposeKey {
+            vault: test.vault,
+            admin: admin.pubkey(),
+        },
+        instruction::ProposeOperator {
+            new_operator: new_operator.pubkey(),
+            activation_delay_slots: 0,
+        },
+    );
+    send(&mut test.svm, &[propose], &admin, &[]).unwrap();
+    let accept = test.ix(
+        accounts::AcceptOperator {
+            vault: test.vault,
+            new_operator: new_operator.pubkey(),
+        },
+        instruction::AcceptOperator {},
+    );
+    send(&mut test.svm, &[accept], &new_operator, &[]).unwrap();
+    assert_eq!(test.vault_state().operators, vec![new_operator.pubkey(), second.pubkey()]);
+
+    // the rotated out key can no longer authorize withdrawals, its replacement can
+    let message = test.message(LAMPORTS_PER_SOL);
+    assert_error(test.withdraw_signed(&operator, &message), VaultError::OperatorError);
+    test.withdraw_signed(&new_operator, &message).unwrap();
+}
+
+#[test]
+fn credit_rewards_rejects_overflowing_batch() {
+    let mut test = TestVault::new();
+    let reward_accounts = [test.reward_account(), test.reward_account()];
+    let operator = test.operator.insecure_clone();
+    assert_error(
+        test.credit_rewards(&reward_accounts, vec![u64::MAX, 1], vec![u64::MAX, 1], &[&operator]),
+        VaultError::MathOverflow,
+    );
+}
+
+struct TestTokenVault {
+    mint: Pubkey,
+    token_vault: Pubkey,
+    vault_token_account: Pubkey,
+    fee_token_account: Pubkey,
+    user_token_account: Pubkey,
+    admin_token_account: Pubkey,
+}
+
+impl TestVault {
+    // token vault for a fresh 6 decimal mint, funded with `deposit` by the user
+    fn token_vault(&mut self, deposit: u64) -> TestTokenVault {
+        let admin = self.admin.insecure_clone();
+        let user = self.user.insecure_clone();
+        let mint = CreateMint::new(&mut self.svm, &admin).decimals(6).send().unwrap();
+        let ata = |owner: &Pubkey| anchor_spl::associated_token::get_associated_token_address(owner, &mint);
+        let (token_vault, _) = Pubkey::find_program_address(
+            &[b"token_vault", self.vault.as_ref(), mint.as_ref()],
+            &d_uptime::ID,
+        );
+        let init = self.ix(
+            accounts::InitializeTokenVault {
+                token_vault,
+                vault_token_account: ata(&self.vault),
+                fee_token_account: ata(&self.fee_account),
+                vault: self.vault,
+                fee_account: self.fee_account,
+                mint,
+                admin: admin.pubkey(),
+                token_program: anchor_spl::token::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: system_program::ID,
+            },
+            instruction::InitializeTokenVault {},
+        );
+        send(&mut self.svm, &[init], &admin, &[]).unwrap();
+
+        let user_token_account = CreateAssociatedTokenAccount::new(&mut self.svm, &admin, &mint)
+            .owner(&user.pubkey())
+            .send()
+            .unwrap();
+        let admin_token_account = CreateAssociatedTokenAccount::new(&mut self.svm, &admin, &mint).send().unwrap();
+        MintTo::new(&mut self.svm, &admin, &mint, &user_token_account, deposit).send().unwrap();
+        let tokens = TestTokenVault {
+            mint,
+            token_vault,
+            vault_token_account: ata(&self.vault),
+            fee_token_account: ata(&self.fee_account),
+            user_token_account,
+            admin_token_account,
+        };
+        let deposit = self.ix(
+            accounts::DepositToken {
+                vault: self.vault,
+                token_vault,
+                vault_token_account: tokens.vault_token_account,
+                user_token_account,
+                mint,
+                user: user.pubkey(),
+                token_program: anchor_spl::token::ID,
+            },
+            instruction::DepositToken { amount: deposit },
+        );
+        send(&mut self.svm, &[deposit], &user, &[]).unwrap();
+        tokens
+    }
+
+    fn token_balance(&self, token_account: &Pubkey) -> u64 {
+        let account = self.svm.get_account(token_account).unwrap();
+        anchor_spl::token::TokenAccount::try_deserialize(&mut account.data.as_slice()).unwrap().amount
+    }
+
+    fn withdraw_token(&mut self, tokens: &TestTokenVault, message: &TokenWithdrawalMessage) -> TransactionResult {
+        let withdraw = self.ix(
+            accounts::WithdrawToken {
+                vault: self.vault,
+                token_vault: tokens.token_vault,
+                vault_token_account: tokens.vault_token_account,
+                fee_token_account: tokens.fee_token_account,
+                user_token_account: tokens.user_token_account,
+                mint: tokens.mint,
+                user: self.user.pubkey(),
+                instruction_sysvar: sysvar::instructions::ID,
+                token_program: anchor_spl::token::ID,
+            },
+            instruction::WithdrawalToken {
+                amount: message.amount,
+                nonce: message.nonce,
+                expiry_slot: message.expiry_slot,
+            },
+        );
+        let signature_ix = Self::signature_ix(&self.operator, &message.to_bytes());
+        let (admin, user) = (self.admin.insecure_clone(), self.user.insecure_clone());
+        send(&mut self.svm, &[signature_ix, withdraw], &admin, &[&user])
+    }
+
+    fn token_admin_call(&mut self, tokens: &TestTokenVault, data: impl InstructionData) -> TransactionResult {
+        let ix = self.ix(
+            accounts::AnnounceAdminWithdrawalToken {
+                vault: self.vault,
+                token_vault: tokens.token_vault,
+                mint: tokens.mint,
+                admin: self.admin.pubkey(),
+            },
+            data,
+        );
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &[ix], &admin, &[])
+    }
+
+    fn admin_withdrawal_token(&mut self, tokens: &TestTokenVault) -> TransactionResult {
+        let ix = self.ix(
+            accounts::AdminWithdrawToken {
+                vault: self.vault,
+                token_vault: tokens.token_vault,
+                vault_token_account: tokens.vault_token_account,
+                admin_token_account: tokens.admin_token_account,
+                mint: tokens.mint,
+                admin: self.admin.pubkey(),
+                token_program: anchor_spl::token::ID,
+            },
+            instruction::AdminWithdrawalToken {},
+        );
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &[ix], &admin, &[])
+    }
+}
+
+#[test]
+fn token_withdrawal_rejects_message_for_other_mint() {
+    let mut test = TestVault::new();
+    let tokens = test.token_vault(1_000_000);
+    let admin = test.admin.insecure_clone();
+    let other_mint = CreateMint::new(&mut test.svm, &admin).decimals(6).send().unwrap();
+
+    let message = TokenWithdrawalMessage {
+        vault: test.vault,
+        mint: other_mint,
+        recipient: test.user.pubkey(),
+        amount: 500_000,
+        nonce: 0,
+        expiry_slot: test.slot() + 100,
+    };
+    assert_error(test.withdraw_token(&tokens, &message), VaultError::MintMismatch);
+}
+
+#[test]
+fn token_admin_withdrawal_requires_announcement_and_timelock() {
+    let mut test = TestVault::new();
+    let tokens = test.token_vault(1_000_000);
+    assert_error(test.admin_withdrawal_token(&tokens), VaultError::NoPendingAdminWithdrawal);
+
+    test.token_admin_call(&tokens, instruction::AnnounceAdminWithdrawalToken { amount: 400_000 }).unwrap();
+    assert_error(test.admin_withdrawal_token(&tokens), VaultError::AdminWithdrawalLocked);
+
+    test.advance_time(DEFAULT_ADMIN_WITHDRAWAL_DELAY);
+    test.admin_withdrawal_token(&tokens).unwrap();
+    assert_eq!(test.token_balance(&tokens.admin_token_account), 400_000);
+    assert_eq!(test.token_balance(&tokens.vault_token_account), 600_000);
+
+    test.token_admin_call(&tokens, instruction::AnnounceAdminWithdrawalToken { amount: 400_000 }).unwrap();
+    test.advance_time(DEFAULT_ADMIN_WITHDRAWAL_DELAY);
+    assert_error(test.admin_withdrawal_token(&tokens), VaultError::AdminWithdrawalCooldown);
+}
+
+#[test]
+fn token_outflow_cap_pauses_vault() {
+    let mut test = TestVault::new();
+    let tokens = test.token_vault(1_000_000);
+    test.token_admin_call(&tokens, instruction::ConfigureTokenOutflowLimit { max_outflow_per_epoch: 100_000 }).unwrap();
+
+    let message = TokenWithdrawalMessage {
+        vault: test.vault,
+        mint: tokens.mint,
+        recipient: test.user.pubkey(),
+        amount: 500_000,
+        nonce: 0,
+        expiry_slot: test.slot() + 100,
+    };
+    test.withdraw_token(&tokens, &message).unwrap();
+
+    // the breaker pauses the whole vault and nothing leaves the token account
+    assert_eq!(test.vault_state().pause_flags, PAUSE_WITHDRAWALS | PAUSE_CLAIMS);
+    assert_eq!(test.vault_state().withdrawal_counter, 0);
+    assert_eq!(test.token_balance(&tokens.vault_token_account), 1_000_000);
+}
+
+#[test]
+fn migrate_vault_upgrades_unversioned_layouts() {
+    let mut test = TestVault::new();
+    let bump = test.vault_state().bump;
+    let legacy_vault = VaultV0 {
+        admin: test.admin.pubkey(),
+        operator: test.operator.pubkey(),
+        fee_account: test.fee_account,
+        total_deposited: DEPOSIT,
+        total_withdrawn: 0,
+        withdrawal_fee_bps: 200,
+        last_admin_withdrawal: None,
+        is_paused: false,
+        withdrawal_counter: 3,
+        bump,
+    };
+    let legacy_fee_account = FeeAccountV0 {
+        admin: test.admin.pubkey(),
+        total_fee_amount: 42,
+    };
+    let (vault, fee_account) = (test.vault, test.fee_account);
+    test.set_legacy_data(vault, Vault::DISCRIMINATOR, &legacy_vault);
+    test.set_legacy_data(fee_account, FeeAccount::DISCRIMINATOR, &legacy_fee_account);
+    let lamports_before = test.lamports(&vault);
+
+    test.migrate().unwrap();
+
+    let state = test.vault_state();
+    assert_eq!(state.version, VAULT_VERSION);
+    assert_eq!(state.creator, test.admin.pubkey());
+    assert_eq!(state.total_deposited, DEPOSIT);
+    assert_eq!(state.withdrawal_counter, 3);
+    assert_eq!(state.admin_withdrawal_delay, DEFAULT_ADMIN_WITHDRAWAL_DELAY);
+    let fee_state = test.fee_account_state();
+    assert_eq!(fee_state.version, FEE_ACCOUNT_VERSION);
+    assert_eq!(fee_state.total_fee_amount, 42);
+
+    // the admin covers the larger rent, deposits are untouched
+    let rent_difference = test.svm.minimum_balance_for_rent_exemption(8 + Vault::INIT_SPACE)
+        - test.svm.minimum_balance_for_rent_exemption(8 + VaultV0::INIT_SPACE);
+    assert_eq!(test.lamports(&vault), lamports_before + rent_difference);
+
+    assert_error(test.migrate(), VaultError::AlreadyMigrated);
+}
+
+#[test]
+fn migrate_vault_rejects_foreign_fee_account() {
+    let mut test = TestVault::new();
+    let other_admin = Keypair::new();
+    test.svm.airdrop(&other_admin.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    let (other_fee_account, _) = Pubkey::find_program_address(&[b"fee_account", other_admin.pubkey().as_ref()], &d_uptime::ID);
+    let init = test.ix(
+        accounts::InitializeFeeAccount {
+            fee_account: other_fee_account,
+            admin: other_admin.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::InitializeFeeAccount {},
+    );
+    send(&mut test.svm, &[init], &other_admin, &[]).unwrap();
+
+    assert_error(test.migrate_accounts(other_fee_account), VaultError::FeeAccountMismatch);
+}
+
+#[test]
+fn migrate_vault_rejects_accounts_it_cant_read() {
+    let mut test = TestVault::new();
+    let vault = test.vault;
+    let original = test.svm.get_account(&vault).unwrap();
+
+    // nothing after the discriminator
+    let mut truncated = original.clone();
+    truncated.data.truncate(8);
+    test.svm.set_account(vault, truncated).unwrap();
+    assert_error(test.migrate(), VaultError::InvalidAccountLayout);
+
+    // current version byte but cut short
+    let mut cut_short = original.clone();
+    cut_short.data.truncate(8 + 40);
+    test.svm.set_account(vault, cut_short).unwrap();
+    assert_error(test.migrate(), VaultError::InvalidAccountLayout);
+
+    // right layout, owned by another program
+    let mut foreign = original.clone();
+    foreign.owner = system_program::ID;
+    test.svm.set_account(vault, foreign).unwrap();
+    assert_error(test.migrate(), VaultError::InvalidAccountLayout);
+}
+
+#[test]
+fn migrate_vault_rejects_unknown_versions() {
+    let mut test = TestVault::new();
+    let (vault, fee_account) = (test.vault, test.fee_account);
+    let original = test.svm.get_account(&vault).unwrap();
+
+    let mut future = original.clone();
+    future.data[8] = VAULT_VERSION + 1;
+    test.svm.set_account(vault, future).unwrap();
+    assert_error(test.migrate(), VaultError::UnsupportedVersion);
+
+    test.svm.set_account(vault, original).unwrap();
+    let mut future_fee_account = test.svm.get_account(&fee_account).unwrap();
+    future_fee_account.data[8] = FEE_ACCOUNT_VERSION + 1;
+    test.svm.set_account(fee_account, future_fee_account).unwrap();
+    assert_error(test.migrate(), VaultError::UnsupportedVersion);
+}
+
+#[test]
+fn close_vault_requires_empty_vault() {
+    let mut test = TestVault::new();
+    assert_error(test.close(), VaultError::VaultNotEmpty);
+
+    test.loosen_withdrawal_limits(0, 0, 0);
+    test.admin_call(instruction::AnnounceAdminWithdrawal { amount: DEPOSIT }).unwrap();
+    test.admin_withdrawal().unwrap();
+
+    let rent = test.lamports(&test.vault);
+    let admin_before = test.lamports(&test.admin.pubkey());
+    test.close().unwrap();
+    assert_eq!(test.lamports(&test.vault), 0);
+    assert_eq!(test.lamports(&test.admin.pubkey()), admin_before + rent - 5000);
+}
+
+#[test]
+fn close_vault_waits_for_token_vaults() {
+    let mut test = TestVault::new();
+    let tokens = test.token_vault(1_000_000);
+    test.loosen_withdrawal_limits(0, 0, 0);
+    test.admin_call(instruction::AnnounceAdminWithdrawal { amount: DEPOSIT }).unwrap();
+    test.admin_withdrawal().unwrap();
+    assert_eq!(test.vault_state().open_token_vaults, 1);
+    assert_error(test.close(), VaultError::VaultNotEmpty);
+
+    let close_token_vault = test.ix(
+        accounts::CloseTokenVault {
+            vault: test.vault,
+            token_vault: tokens.token_vault,
+            vault_token_account: tokens.vault_token_account,
+            mint: tokens.mint,
+            admin: test.admin.pubkey(),
+            token_program: anchor_spl::token::ID,
+        },
+        instruction::CloseTokenVault {},
+    );
+    let admin = test.admin.insecure_clone();
+    assert_error(send(&mut test.svm, std::slice::from_ref(&close_token_vault), &admin, &[]), VaultError::VaultNotEmpty);
+
+    test.token_admin_call(&tokens, instruction::AnnounceAdminWithdrawalToken { amount: 1_000_000 }).unwrap();
+    test.admin_withdrawal_token(&tokens).unwrap();
+    send(&mut test.svm, &[close_token_vault], &admin, &[]).unwrap();
+    assert!(test.svm.get_account(&tokens.token_vault).is_none_or(|account| account.lamports == 0));
+    assert!(test.svm.get_account(&tokens.vault_token_account).is_none_or(|account| account.lamports == 0));
+    assert_eq!(test.vault_state().open_token_vaults, 0);
+    test.close().unwrap();
+}
+
+#[test]
+fn staking_requires_active_registry_record() {
+    let mut test = TestVault::new();
+    let validator = test.user.insecure_clone();
+    let (validator_record, _) = Pubkey::find_program_address(
+        &[b"validator", test.vault.as_ref(), validator.pubkey().as_ref()],
+        &d_uptime::ID,
+    );
+    let (stake_account, _) = Pubkey::find_program_address(
+        &[b"stake", test.vault.as_ref(), validator.pubkey().as_ref()],
+        &d_uptime::ID,
+    );
+
+    let register = test.ix(
+        accounts::RegisterValidator {
+            validator_record,
+            vault: test.vault,
+            validator: validator.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::RegisterValidator {
+            device_hash: [7u8; 32],
+            region_code: 49,
+        },
+    );
+    send(&mut test.svm, &[register], &validator, &[]).unwrap();
+    let account = test.svm.get_account(&validator_record).unwrap();
+    let record = ValidatorRecord::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(record.wallet, validator.pubkey());
+    assert_eq!(record.region_code, 49);
+    assert_eq!(record.status, ValidatorStatus::Pending);
+
+    let stake = test.ix(
+        accounts::Stake {
+            stake_account,
+            vault: test.vault,
+            validator: validator.pubkey(),
+            validator_record,
+            system_program: system_program::ID,
+        },
+        instruction::Stake { amount: LAMPORTS_PER_SOL },
+    );
+    assert_error(send(&mut test.svm, std::slice::from_ref(&stake), &validator, &[]), ValidatorError::NotActive);
+
+    let user_activate = test.ix(
+        accounts::SetValidatorStatus {
+            vault: test.vault,
+            validator_record,
+            operator: validator.pubkey(),
+            instruction_sysvar: sysvar::instructions::ID,
+        },
+        instruction::SetValidatorStatus {
+            status: ValidatorStatus::Active,
+            nonce: 0,
+            signature_expiry_slot: test.slot() + 100,
+        },
+    );
+    assert_error(send(&mut test.svm, &[user_activate], &validator, &[]), VaultError::OperatorError);
+    let operator = test.operator.insecure_clone();
+    test.set_validator_status(validator.pubkey(), ValidatorStatus::Active, &[&operator]).unwrap();
+
+    send(&mut test.svm, &[stake], &validator, &[]).unwrap();
+    assert_eq!(test.vault_state().total_staked, LAMPORTS_PER_SOL);
+}
+
+impl TestVault {
+    // one ed25519 instruction per signer over `action` with the vault's current action nonce, plus the
+    // nonce and expiry slot the instruction has to repeat
+    fn sign_action(&self, action: OperatorAction, signers: &[&Keypair]) -> (Vec<Instruction>, u64, u64) {
+        let message = OperatorActionMessage::new(self.vault, action, self.vault_state().operator_action_counter, self.slot() + 100);
+        let signature_ixs = signers.iter().map(|signer| Self::signature_ix(signer, &message.to_bytes())).collect();
+        (signature_ixs, message.nonce, message.expiry_slot)
+    }
+
+    // the vault operator submits and pays for operator instructions
+    fn send_action(&mut self, mut signature_ixs: Vec<Instruction>, ix: Instruction) -> TransactionResult {
+        signature_ixs.push(ix);
+        let operator = self.operator.insecure_clone();
+        send(&mut self.svm, &signature_ixs, &operator, &[])
+    }
+
+    // moves the vault to a 2-of-3 set that keeps the original operator, returns the other two operators
+    fn two_of_three_operators(&mut self) -> [Keypair; 2] {
+        let others = [Keypair::new(), Keypair::new()];
+        let operator = self.operator.insecure_clone();
+        self.set_operator_set(&[&operator, &others[0], &others[1]], 2);
+        others
+    }
+
+    fn validator_record_address(&self, wallet: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"validator", self.vault.as_ref(), wallet.as_ref()], &d_uptime::ID).0
+    }
+
+    fn register_validator(&mut self, validator: &Keypair) -> Pubkey {
+        self.svm.airdrop(&validator.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        let validator_record = self.validator_record_address(&validator.pubkey());
+        let register = self.ix(
+            accounts::RegisterValidator {
+                validator_record,
+                vault: self.vault,
+                validator: validator.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::RegisterValidator {
+                device_hash: [7u8; 32],
+                region_code: 49,
+            },
+        );
+        send(&mut self.svm, &[register], validator, &[]).unwrap();
+        validator_record
+    }
+
+    fn set_validator_status(&mut self, wallet: Pubkey, status: ValidatorStatus, signers: &[&Keypair]) -> TransactionResult {
+        let (signature_ixs, nonce, signature_expiry_slot) =
+            self.sign_action(OperatorAction::SetValidatorStatus { wallet, status }, signers);
+        let ix = self.ix(
+            accounts::SetValidatorStatus {
+                vault: self.vault,
+                validator_record: self.validator_record_address(&wallet),
+                operator: self.operator.pubkey(),
+                instruction_sysvar: sysvar::instructions::ID,
+            },
+            instruction::SetValidatorStatus { status, nonce, signature_expiry_slot },
+        );
+        self.send_action(signature_ixs, ix)
+    }
+
+    // registers `validator` and has the single operator activate it, returns the registry record
+    fn active_validator(&mut self, validator: &Keypair) -> Pubkey {
+        let validator_record = self.register_validator(validator);
+        let operator = self.operator.insecure_clone();
+        self.set_validator_status(validator.pubkey(), ValidatorStatus::Active, &[&operator]).unwrap();
+        validator_record
+    }
+
+    fn reward_account(&mut self) -> Pubkey {
+        let validator = Pubkey::new_unique();
+        let (reward_account, _) = Pubkey::find_program_address(
+            &[b"reward_account", self.vault.as_ref(), validator.as_ref()],
+            &d_uptime::ID,
+        );
+        let ix = self.ix(
+            accounts::InitializeRewardAccount {
+                reward_account,
+                vault: self.vault,
+                validator,
+                payer: self.admin.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::InitializeRewardAccount {},
+        );
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &[ix], &admin, &[]).unwrap();
+        reward_account
+    }
+
+    fn credit_rewards(&mut self, reward_accounts: &[Pubkey], signed_amounts: Vec<u64>, amounts: Vec<u64>, signers: &[&Keypair]) -> TransactionResult {
+        let action = OperatorAction::CreditRewards {
+            reward_accounts: reward_accounts.to_vec(),
+            amounts: signed_amounts,
+        };
+        let (signature_ixs, nonce, signature_expiry_slot) = self.sign_action(action, signers);
+        let mut ix = self.ix(
+            accounts::CreditRewards {
+                vault: self.vault,
+                operator: self.operator.pubkey(),
+                instruction_sysvar: sysvar::instructions::ID,
+            },
+            instruction::CreditRewards { amounts, nonce, signature_expiry_slot },
+        );
+        ix.accounts.extend(
+            reward_accounts
+                .iter()
+                .map(|reward_account| anchor_lang::solana_program::instruction::AccountMeta::new(*reward_account, false)),
+        );
+        self.send_action(signature_ixs, ix)
+    }
+
+    fn epoch_address(&self, epoch_id: u64) -> Pubkey {
+        Pubkey::find_program_address(&[b"epoch", self.vault.as_ref(), &epoch_id.to_le_bytes()], &d_uptime::ID).0
+    }
+
+    fn post_epoch(&mut self, epoch_id: u64, merkle_root: [u8; 32], total_amount: u64, leaf_count: u32, signers: &[&Keypair]) -> TransactionResult {
+        let expiry_slot = self.slot() + 100;
+        let action = OperatorAction::PostEpoch { epoch_id, merkle_root, total_amount, leaf_count, expiry_slot };
+        let (signature_ixs, nonce, signature_expiry_slot) = self.sign_action(action, signers);
+        let ix = self.ix(
+            accounts::PostEpoch {
+                epoch: self.epoch_address(epoch_id),
+                vault: self.vault,
+                operator: self.operator.pubkey(),
+                system_program: system_program::ID,
+                instruction_sysvar: sysvar::instructions::ID,
+            },
+            instruction::PostEpoch {
+                epoch_id,
+                merkle_root,
+                total_amount,
+                leaf_count,
+                expiry_slot,
+                nonce,
+                signature_expiry_slot,
+            },
+        );
+        self.send_action(signature_ixs, ix)
+    }
+
+    fn close_epoch(&mut self, epoch_id: u64, signers: &[&Keypair]) -> TransactionResult {
+        let (signature_ixs, nonce, signature_expiry_slot) = self.sign_action(OperatorAction::CloseEpoch { epoch_id }, signers);
+        let ix = self.ix(
+            accounts::CloseEpoch {
+                vault: self.vault,
+                epoch: self.epoch_address(epoch_id),
+                operator: self.operator.pubkey(),
+                instruction_sysvar: sysvar::instructions::ID,
+            },
+            instruction::CloseEpoch { epoch_id, nonce, signature_expiry_slot },
+        );
+        self.send_action(signature_ixs, ix)
+    }
+
+    fn claim_epoch(&mut self, epoch_id: u64, validator: &Keypair, index: u32, amount: u64, proof: Vec<[u8; 32]>) -> TransactionResult {
+        let ix = self.ix(
+            accounts::ClaimEpoch {
+                vault: self.vault,
+                epoch: self.epoch_address(epoch_id),
+                validator: validator.pubkey(),
+                validator_record: self.validator_record_address(&validator.pubkey()),
+                fee_account: self.fee_account,
+            },
+            instruction::ClaimEpoch { _epoch_id: epoch_id, index, amount, proof },
+        );
+        let admin = self.admin.insecure_clone();
+        send(&mut self.svm, &[ix], &admin, &[validator])
+    }
+}
+
+#[test]
+fn claim_epoch_rejects_claims_beyond_epoch_total() {
+    let mut test = TestVault::new();
+    let (first, second) = (test.user.insecure_clone(), Keypair::new());
+    test.active_validator(&first);
+    test.active_validator(&second);
+
+    // a root whose leaves add up to more than the posted total
+    let leaves = [
+        epoch_leaf_hash(0, &first.pubkey(), LAMPORTS_PER_SOL),
+        epoch_leaf_hash(1, &second.pubkey(), LAMPORTS_PER_SOL),
+    ];
+    let (low, high) = if leaves[0] <= leaves[1] { (leaves[0], leaves[1]) } else { (leaves[1], leaves[0]) };
+    let root = hashv(&[&[1u8], &low, &high]).to_bytes();
+    let operator = test.operator.insecure_clone();
+    test.post_epoch(1, root, LAMPORTS_PER_SOL, 2, &[&operator]).unwrap();
+
+    test.claim_epoch(1, &first, 0, LAMPORTS_PER_SOL, vec![leaves[1]]).unwrap();
+    assert_error(
+        test.claim_epoch(1, &second, 1, LAMPORTS_PER_SOL, vec![leaves[0]]),
+        EpochError::EpochExhausted,
+    );
+    assert_eq!(test.vault_state().total_owed, 0);
+}
+
+#[test]
+fn validator_activation_needs_operator_quorum() {
+    let mut test = TestVault::new();
+    let validator = Keypair::new();
+    let validator_record = test.register_validator(&validator);
+    let [second, _] = test.two_of_three_operators();
+    let operator = test.operator.insecure_clone();
+
+    assert_error(
+        test.set_validator_status(validator.pubkey(), ValidatorStatus::Active, &[&operator]),
+        VaultError::InsufficientOperatorSignatures,
+    );
+    test.set_validator_status(validator.pubkey(), ValidatorStatus::Active, &[&operator, &second]).unwrap();
+    let account = test.svm.get_account(&validator_record).unwrap();
+    let record = ValidatorRecord::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(record.status, ValidatorStatus::Active);
+    assert_eq!(test.vault_state().operator_action_counter, 1);
+}
+
+#[test]
+fn credit_rewards_needs_operator_quorum() {
+    let mut test = TestVault::new();
+    let reward_account = test.reward_account();
+    let [second, _] = test.two_of_three_operators();
+    let operator = test.operator.insecure_clone();
+
+    assert_error(
+        test.credit_rewards(&[reward_account], vec![LAMPORTS_PER_SOL], vec![LAMPORTS_PER_SOL], &[&operator]),
+        VaultError::InsufficientOperatorSignatures,
+    );
+    // the quorum signed one amount, the operator can't submit another
+    assert_error(
+        test.credit_rewards(&[reward_account], vec![LAMPORTS_PER_SOL], vec![DEPOSIT / 2], &[&operator, &second]),
+        VaultError::ActionMismatch,
+    );
+    assert_eq!(test.vault_state().total_owed, 0);
+
+    test.credit_rewards(&[reward_account], vec![LAMPORTS_PER_SOL], vec![LAMPORTS_PER_SOL], &[&operator, &second]).unwrap();
+    assert_eq!(test.vault_state().total_owed, LAMPORTS_PER_SOL);
+}
+
+#[test]
+fn post_epoch_needs_operator_quorum() {
+    let mut test = TestVault::new();
+    let [second, _] = test.two_of_three_operators();
+    let operator = test.operator.insecure_clone();
+
+    assert_error(
+        test.post_epoch(1, [1u8; 32], LAMPORTS_PER_SOL, 1, &[&operator]),
+        VaultError::InsufficientOperatorSignatures,
+    );
+    assert!(test.svm.get_account(&test.epoch_address(1)).is_none_or(|account| account.lamports == 0));
+
+    test.post_epoch(1, [1u8; 32], LAMPORTS_PER_SOL, 1, &[&operator, &second]).unwrap();
+    assert_eq!(test.vault_state().total_owed, LAMPORTS_PER_SOL);
+}
+
+#[test]
+fn close_epoch_needs_operator_quorum() {
+    let mut test = TestVault::new();
+    let [second, _] = test.two_of_three_operators();
+    let operator = test.operator.insecure_clone();
+    test.post_epoch(1, [1u8; 32], LAMPORTS_PER_SOL, 1, &[&operator, &second]).unwrap();
+    let slot = test.slot();
+    test.svm.warp_to_slot(slot + 101);
+
+    assert_error(test.close_epoch(1, &[&operator]), VaultError::InsufficientOperatorSignatures);
+    assert_eq!(test.vault_state().total_owed, LAMPORTS_PER_SOL);
+
+    test.close_epoch(1, &[&operator, &second]).unwrap();
+    assert_eq!(test.vault_state().total_owed, 0);
+}